@@ -0,0 +1,90 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use hyper::{StatusCode, header::AUTHORIZATION};
+
+use crate::{
+    application_context::ApplicationContext, github_api::GitHubApiProvider,
+    installations::RegisteredInstallation, problem::Problem,
+};
+
+/// Only lets requests through that carry the configured admin token as bearer
+/// token. The endpoints are disabled if no token is configured.
+pub async fn require_admin_token<ApiProvider: GitHubApiProvider>(
+    State(app_context): State<Arc<ApplicationContext<ApiProvider>>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Problem> {
+    let Some(admin_token) = app_context.config().admin_token.clone() else {
+        return Err(Problem::new(
+            StatusCode::FORBIDDEN,
+            "Admin endpoints are disabled",
+            Some("No admin token is configured"),
+        ));
+    };
+
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), admin_token.as_bytes()));
+
+    if !authorized {
+        return Err(Problem::new(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid admin token",
+            None::<String>,
+        ));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Compares without returning early, so the time taken does not tell how
+/// much of a guessed token is right
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .fold(0, |difference, (left, right)| difference | (left ^ right))
+            == 0
+}
+
+pub async fn installations_handler<ApiProvider: GitHubApiProvider>(
+    State(app_context): State<Arc<ApplicationContext<ApiProvider>>>,
+) -> Json<Vec<RegisteredInstallation>> {
+    Json(app_context.installations().list())
+}
+
+pub async fn installation_handler<ApiProvider: GitHubApiProvider>(
+    State(app_context): State<Arc<ApplicationContext<ApiProvider>>>,
+    Path(installation_id): Path<usize>,
+) -> Result<Json<RegisteredInstallation>, Problem> {
+    app_context
+        .installations()
+        .get(installation_id)
+        .map(Json)
+        .ok_or_else(|| {
+            Problem::new(
+                StatusCode::NOT_FOUND,
+                "Installation not found",
+                Some(format!("Installation {installation_id} is not registered")),
+            )
+        })
+}
@@ -0,0 +1,333 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    collections::HashMap,
+    env, fmt, fs, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use reader::ConfigReader;
+use thiserror::Error;
+use toml::Table;
+
+mod reader;
+
+/// Environment variable with the path of the configuration file
+pub const CONFIG_FILE_VARIABLE: &str = "KORITSU_CONFIG_FILE";
+
+#[derive(Clone)]
+pub struct ApplicationConfig {
+    /// `https://api.github.com` or `https://<host>/api/v3` for GitHub
+    /// Enterprise Server
+    pub github_base_url: String,
+    pub github_api_version: Option<String>,
+    pub github_backend: GitHubBackend,
+    pub github_http: HttpClientConfig,
+    pub github_webhook_secret: String,
+    pub client_id: String,
+    /// Ordered by preference. The next key is used if GitHub rejects a key,
+    /// e.g. while keys are rotated.
+    pub private_keys: Vec<PrivateKeySource>,
+    pub server: ServerConfig,
+    /// Bearer token the `/admin` endpoints require. Without it they are
+    /// disabled.
+    pub admin_token: Option<String>,
+    /// Workflow runs are only trusted if they were triggered by one of these
+    /// events. Runs for pull requests can contain code of any contributor.
+    pub allowed_trigger_events: Vec<String>,
+    pub default_policy: MergePolicy,
+    /// Policies for single repositories by their full name. They replace the
+    /// default policy completely.
+    pub repository_policies: HashMap<String, MergePolicy>,
+}
+
+impl ApplicationConfig {
+    /// Loads the configuration file named by `KORITSU_CONFIG_FILE`, if any, and
+    /// applies the overrides from the process environment.
+    pub fn load() -> Result<ApplicationConfig, ConfigError> {
+        let path = env::var_os(CONFIG_FILE_VARIABLE).map(PathBuf::from);
+        Self::load_file(path.as_deref())
+    }
+
+    /// Loads the configuration file at the path, if any, and applies the
+    /// overrides from the process environment.
+    pub fn load_file(path: Option<&Path>) -> Result<ApplicationConfig, ConfigError> {
+        let content = match path {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .map_err(|error| ConfigError::UnreadableFile(path.to_owned(), error))?;
+                Some((path, content))
+            }
+            None => None,
+        };
+
+        let file = content
+            .as_ref()
+            .map(|(path, content)| (*path, content.as_str()));
+
+        Self::from_sources(file, |name| env::var(name).ok())
+    }
+
+    /// Builds the configuration from the content of a configuration file and
+    /// a lookup for environment variables. Values from the environment take
+    /// precedence over the file.
+    pub fn from_sources(
+        file: Option<(&Path, &str)>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<ApplicationConfig, ConfigError> {
+        let table = match file {
+            Some((path, content)) => content
+                .parse::<Table>()
+                .map_err(|error| ConfigError::InvalidFile(path.to_owned(), Box::new(error)))?,
+            None => Table::new(),
+        };
+
+        let mut reader = ConfigReader::new(table);
+        reader.apply_env_overrides(env);
+
+        let config = reader.read();
+
+        match reader.into_problems() {
+            problems if problems.is_empty() => Ok(config),
+            problems => Err(ConfigError::Invalid(problems)),
+        }
+    }
+
+    pub fn policy_for(&self, repository_name: &str) -> &MergePolicy {
+        self.repository_policies
+            .get(repository_name)
+            .unwrap_or(&self.default_policy)
+    }
+}
+
+/// Where the private key of the GitHub App comes from. PKCS#1 and PKCS#8 keys
+/// are accepted, PEM or DER encoded.
+#[derive(Clone)]
+pub enum PrivateKeySource {
+    File(PathBuf),
+    /// The PEM encoded key, or the base64 encoded DER key
+    Inline(String),
+}
+
+impl fmt::Display for PrivateKeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrivateKeySource::File(path) => write!(f, "file {}", path.display()),
+            PrivateKeySource::Inline(_) => write!(f, "github.private_key"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerConfig {
+    pub listen_address: IpAddr,
+    pub port: u16,
+    /// Terminates TLS in the application if set. Otherwise plain HTTP is
+    /// served, e.g. behind a reverse proxy.
+    pub tls: Option<TlsConfig>,
+}
+
+impl ServerConfig {
+    pub fn socket_address(&self) -> SocketAddr {
+        SocketAddr::new(self.listen_address, self.port)
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listen_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 8080,
+            tls: None,
+        }
+    }
+}
+
+/// PEM encoded certificate chain and private key. Both files are reloaded
+/// when they change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsConfig {
+    pub certificate_file: PathBuf,
+    pub key_file: PathBuf,
+}
+
+/// How requests to GitHub are sent
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpClientConfig {
+    pub connect_timeout: Duration,
+    /// Covers the whole request from connecting until the body is read
+    pub request_timeout: Duration,
+    /// Idle connections are closed after this time
+    pub pool_idle_timeout: Duration,
+    pub pool_max_idle_per_host: usize,
+    /// `http://` or `https://` URL of the proxy for all requests. Without it
+    /// the proxy is taken from `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY`.
+    pub proxy: Option<String>,
+    /// Hosts, domains and IP networks that are reached without the proxy, in
+    /// the format of `NO_PROXY`
+    pub no_proxy: Vec<String>,
+    /// PEM files with root certificates that are trusted in addition to the
+    /// ones of the system, e.g. the internal CA of GitHub Enterprise Server
+    pub ca_certificate_files: Vec<PathBuf>,
+    /// PEM encoded certificate and PKCS#8 key the client authenticates with
+    pub client_certificate: Option<TlsConfig>,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        HttpClientConfig {
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(60),
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: 16,
+            proxy: None,
+            no_proxy: Vec::new(),
+            ca_certificate_files: Vec::new(),
+            client_certificate: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergePolicy {
+    /// Branches with this prefix are ready branches
+    pub branch_prefix: String,
+    pub merge_strategy: MergeStrategy,
+    /// Names or paths like `.github/workflows/ci.yml` of the workflows that
+    /// must succeed before a ready branch gets merged. If the list is empty
+    /// any successful workflow run triggers the merge.
+    pub required_workflows: Vec<String>,
+    /// Workflow run conclusions that count as success, e.g. `skipped` or
+    /// `neutral` in addition to `success`.
+    pub accepted_conclusions: Vec<String>,
+    /// Deletes a ready branch after it was merged
+    pub delete_branch: bool,
+    /// Only merges ready branches whose commits all have a verified signature
+    pub require_signed_commits: bool,
+}
+
+impl MergePolicy {
+    pub fn is_ready_branch(&self, branch: &str) -> bool {
+        branch.starts_with(&self.branch_prefix)
+    }
+
+    /// Applies a policy file like `.github/koritsu.toml` on top of this
+    /// policy. Keys that are missing in the file keep their value. Setting one
+    /// of the locked keys is a problem.
+    pub fn merged_with_file(
+        &self,
+        path: &Path,
+        content: &str,
+        locked_keys: &[String],
+    ) -> Result<MergePolicy, ConfigError> {
+        let table = parse_file(path, content)?;
+
+        match reader::read_policy_file(&table, self, locked_keys) {
+            (policy, problems) if problems.is_empty() => Ok(policy),
+            (_, problems) => Err(ConfigError::Invalid(problems)),
+        }
+    }
+
+    /// Applies the policy file of an organisation on top of this policy. In
+    /// addition to the policy keys the file can lock keys.
+    pub fn merged_with_organisation_file(
+        &self,
+        path: &Path,
+        content: &str,
+    ) -> Result<OrganisationPolicy, ConfigError> {
+        let table = parse_file(path, content)?;
+
+        match reader::read_organisation_policy_file(&table, self) {
+            (policy, problems) if problems.is_empty() => Ok(policy),
+            (_, problems) => Err(ConfigError::Invalid(problems)),
+        }
+    }
+}
+
+fn parse_file(path: &Path, content: &str) -> Result<Table, ConfigError> {
+    content
+        .parse::<Table>()
+        .map_err(|error| ConfigError::InvalidFile(path.to_owned(), Box::new(error)))
+}
+
+/// The policy an organisation defines for all of its repositories
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrganisationPolicy {
+    pub policy: MergePolicy,
+    /// Policy keys the policy files of repositories must not set
+    pub locked_keys: Vec<String>,
+}
+
+impl Default for MergePolicy {
+    fn default() -> Self {
+        MergePolicy {
+            branch_prefix: "ready/".to_owned(),
+            merge_strategy: MergeStrategy::FastForward,
+            required_workflows: Vec::new(),
+            accepted_conclusions: vec!["success".to_owned()],
+            delete_branch: false,
+            require_signed_commits: false,
+        }
+    }
+}
+
+/// The GitHub API the application reads from. Writes always use the REST API.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GitHubBackend {
+    #[default]
+    Rest,
+    /// Reads that take several REST requests are combined into single queries
+    /// of the GraphQL API.
+    GraphQl,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Moves the default branch to the single commit of the ready branch
+    FastForward,
+    /// Creates a merge commit on the default branch
+    Merge,
+}
+
+/// A single invalid or missing value of the configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    /// Dotted path of the key, e.g. `server.port`
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not read configuration file {0}")]
+    UnreadableFile(PathBuf, #[source] io::Error),
+
+    #[error("Configuration file {0} is not valid TOML")]
+    InvalidFile(PathBuf, #[source] Box<toml::de::Error>),
+
+    #[error("Invalid configuration\n{}", format_problems(.0))]
+    Invalid(Vec<ConfigProblem>),
+}
+
+fn format_problems(problems: &[ConfigProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("  - {problem}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
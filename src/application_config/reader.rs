@@ -0,0 +1,773 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{collections::HashMap, fs, net::IpAddr, time::Duration};
+
+use reqwest::Url;
+use toml::{Table, Value};
+
+use super::{
+    ApplicationConfig, ConfigProblem, GitHubBackend, HttpClientConfig, MergePolicy, MergeStrategy,
+    OrganisationPolicy, PrivateKeySource, ServerConfig, TlsConfig,
+};
+
+const DEFAULT_GITHUB_BASE_URL: &str = "https://api.github.com";
+const DEFAULT_GITHUB_API_VERSION: &str = "2022-11-28";
+
+const ROOT_KEYS: &[&str] = &[
+    "github",
+    "server",
+    "admin",
+    "workflow_runs",
+    "merge_policy",
+    "repositories",
+];
+const GITHUB_KEYS: &[&str] = &[
+    "base_url",
+    "api_version",
+    "backend",
+    "http",
+    "webhook_secret",
+    "client_id",
+    "private_key",
+    "private_key_file",
+];
+const HTTP_KEYS: &[&str] = &[
+    "connect_timeout",
+    "request_timeout",
+    "pool_idle_timeout",
+    "pool_max_idle_per_host",
+    "proxy",
+    "no_proxy",
+    "ca_certificate_file",
+    "client_certificate",
+];
+const SERVER_KEYS: &[&str] = &["listen_address", "port", "tls"];
+const ADMIN_KEYS: &[&str] = &["token"];
+const TLS_KEYS: &[&str] = &["certificate_file", "key_file"];
+const WORKFLOW_RUNS_KEYS: &[&str] = &["allowed_trigger_events"];
+const POLICY_KEYS: &[&str] = &[
+    "branch_prefix",
+    "merge_strategy",
+    "required_workflows",
+    "accepted_conclusions",
+    "delete_branch",
+    "require_signed_commits",
+];
+
+const ORGANISATION_POLICY_KEYS: &[&str] = &[
+    "branch_prefix",
+    "merge_strategy",
+    "required_workflows",
+    "accepted_conclusions",
+    "delete_branch",
+    "require_signed_commits",
+    "locked",
+];
+
+enum Kind {
+    String,
+    /// A string that can also be read from the file named by the variable
+    /// with a `_FILE` suffix, e.g. a Kubernetes secret or systemd credential
+    Secret,
+    Integer,
+    List,
+}
+
+/// Environment variables that override a key of the configuration file.
+/// Lists are given as comma separated values.
+const ENV_OVERRIDES: &[(&str, &str, Kind)] = &[
+    ("GITHUB_BASE_URL", "github.base_url", Kind::String),
+    ("GITHUB_API_VERSION", "github.api_version", Kind::String),
+    ("GITHUB_BACKEND", "github.backend", Kind::String),
+    ("GITHUB_PROXY", "github.http.proxy", Kind::String),
+    ("GITHUB_NO_PROXY", "github.http.no_proxy", Kind::List),
+    (
+        "GITHUB_CA_CERTIFICATE_FILE",
+        "github.http.ca_certificate_file",
+        Kind::List,
+    ),
+    (
+        "GITHUB_REQUEST_TIMEOUT",
+        "github.http.request_timeout",
+        Kind::Integer,
+    ),
+    (
+        "GITHUB_WEBHOOK_SECRET",
+        "github.webhook_secret",
+        Kind::Secret,
+    ),
+    ("GITHUB_CLIENT_ID", "github.client_id", Kind::String),
+    ("GITHUB_PRIVATE_KEY", "github.private_key", Kind::Secret),
+    ("PRIVATE_KEY_FILE", "github.private_key_file", Kind::List),
+    (
+        "KORITSU_LISTEN_ADDRESS",
+        "server.listen_address",
+        Kind::String,
+    ),
+    ("KORITSU_PORT", "server.port", Kind::Integer),
+    (
+        "KORITSU_TLS_CERTIFICATE_FILE",
+        "server.tls.certificate_file",
+        Kind::String,
+    ),
+    ("KORITSU_TLS_KEY_FILE", "server.tls.key_file", Kind::String),
+    ("KORITSU_ADMIN_TOKEN", "admin.token", Kind::Secret),
+    (
+        "KORITSU_ALLOWED_TRIGGER_EVENTS",
+        "workflow_runs.allowed_trigger_events",
+        Kind::List,
+    ),
+    (
+        "KORITSU_REQUIRED_WORKFLOWS",
+        "merge_policy.required_workflows",
+        Kind::List,
+    ),
+    (
+        "KORITSU_ACCEPTED_CONCLUSIONS",
+        "merge_policy.accepted_conclusions",
+        Kind::List,
+    ),
+];
+
+/// Keys that configure the same value in different ways. Setting one of them
+/// from the environment removes the other one from the file.
+const ALTERNATIVE_KEYS: &[(&str, &str)] = &[("github.private_key", "github.private_key_file")];
+
+/// Turns the TOML document into an [`ApplicationConfig`] and collects every
+/// problem on the way instead of stopping at the first one.
+pub struct ConfigReader {
+    table: Table,
+    problems: Vec<ConfigProblem>,
+}
+
+impl ConfigReader {
+    pub fn new(table: Table) -> Self {
+        Self {
+            table,
+            problems: Vec::new(),
+        }
+    }
+
+    pub fn apply_env_overrides(&mut self, env: impl Fn(&str) -> Option<String>) {
+        for (variable, key, kind) in ENV_OVERRIDES {
+            let value = match kind {
+                Kind::Secret => self.secret(variable, key, &env),
+                _ => env(variable),
+            };
+            let Some(value) = value else {
+                continue;
+            };
+
+            let value = match kind {
+                Kind::String | Kind::Secret => Value::String(value),
+                Kind::Integer => match value.trim().parse() {
+                    Ok(number) => Value::Integer(number),
+                    Err(_) => {
+                        self.problems.push(ConfigProblem {
+                            key: key.to_string(),
+                            message: format!("{variable} must be an integer, got \"{value}\""),
+                        });
+                        continue;
+                    }
+                },
+                Kind::List => Value::Array(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(|item| Value::String(item.to_owned()))
+                        .collect(),
+                ),
+            };
+
+            self.set(key, value);
+
+            for (first, second) in ALTERNATIVE_KEYS {
+                if key == first {
+                    self.remove(second);
+                } else if key == second {
+                    self.remove(first);
+                }
+            }
+        }
+    }
+
+    /// Reads the value of the variable or the content of the file named by
+    /// the variable with the `_FILE` suffix. Trailing line breaks of the file
+    /// are removed.
+    fn secret(
+        &mut self,
+        variable: &str,
+        key: &str,
+        env: &impl Fn(&str) -> Option<String>,
+    ) -> Option<String> {
+        let file_variable = format!("{variable}_FILE");
+
+        let message = match (env(variable), env(&file_variable)) {
+            (Some(_), Some(_)) => format!("only one of {variable} and {file_variable} can be set"),
+            (Some(value), None) => return Some(value),
+            (None, None) => return None,
+            (None, Some(path)) => match fs::read_to_string(&path) {
+                Ok(content) => return Some(content.trim_end_matches(['\r', '\n']).to_owned()),
+                Err(error) => format!("could not read {path} named by {file_variable}: {error}"),
+            },
+        };
+
+        self.problems.push(ConfigProblem {
+            key: key.to_owned(),
+            message,
+        });
+        None
+    }
+
+    fn remove(&mut self, key: &str) {
+        let mut segments: Vec<&str> = key.split('.').collect();
+        let last = segments.pop().expect("keys are never empty");
+
+        let mut table = &mut self.table;
+        for segment in segments {
+            match table.get_mut(segment).and_then(Value::as_table_mut) {
+                Some(child) => table = child,
+                None => return,
+            }
+        }
+
+        table.remove(last);
+    }
+
+    fn set(&mut self, key: &str, value: Value) {
+        let mut segments: Vec<&str> = key.split('.').collect();
+        let last = segments.pop().expect("keys are never empty");
+
+        let mut table = &mut self.table;
+        for segment in segments {
+            let entry = table
+                .entry(segment)
+                .or_insert_with(|| Value::Table(Table::new()));
+
+            if !entry.is_table() {
+                *entry = Value::Table(Table::new());
+            }
+
+            table = entry.as_table_mut().expect("entry is a table");
+        }
+
+        table.insert(last.to_owned(), value);
+    }
+
+    pub fn read(&mut self) -> ApplicationConfig {
+        let problems = &mut self.problems;
+
+        let root = Section::root(&self.table, ROOT_KEYS, problems);
+        let github = root.child("github", GITHUB_KEYS, problems);
+        let server = root.child("server", SERVER_KEYS, problems);
+        let admin = root.child("admin", ADMIN_KEYS, problems);
+        let workflow_runs = root.child("workflow_runs", WORKFLOW_RUNS_KEYS, problems);
+        let merge_policy = root.child("merge_policy", POLICY_KEYS, problems);
+
+        ApplicationConfig {
+            github_base_url: github
+                .base_url("base_url", problems)
+                .unwrap_or_else(|| DEFAULT_GITHUB_BASE_URL.to_owned()),
+            github_api_version: match github.string("api_version", problems) {
+                Some(version) if version.is_empty() => None,
+                Some(version) => Some(version),
+                None => Some(DEFAULT_GITHUB_API_VERSION.to_owned()),
+            },
+            github_backend: github.backend("backend", problems).unwrap_or_default(),
+            github_http: read_http_client(&github, problems),
+            github_webhook_secret: github.required_string("webhook_secret", problems),
+            client_id: github.required_string("client_id", problems),
+            private_keys: read_private_keys(&github, problems),
+            server: read_server(&server, problems),
+            admin_token: read_admin_token(&admin, problems),
+            allowed_trigger_events: workflow_runs
+                .string_list("allowed_trigger_events", problems)
+                .unwrap_or_else(|| vec!["push".to_owned()]),
+            default_policy: read_policy(&merge_policy, &MergePolicy::default(), problems),
+            repository_policies: read_repository_policies(&root, problems),
+        }
+    }
+
+    pub fn into_problems(self) -> Vec<ConfigProblem> {
+        self.problems
+    }
+}
+
+fn read_private_keys(github: &Section, problems: &mut Vec<ConfigProblem>) -> Vec<PrivateKeySource> {
+    let keys = github.string_or_list("private_key", problems);
+    let files = github.string_or_list("private_key_file", problems);
+
+    match (keys, files) {
+        (Some(keys), None) => keys.into_iter().map(PrivateKeySource::Inline).collect(),
+        (None, Some(files)) => files
+            .into_iter()
+            .map(|file| PrivateKeySource::File(file.into()))
+            .collect(),
+        (Some(_), Some(_)) => {
+            problems.push(problem(
+                &github.key_path("private_key"),
+                "can not be set together with private_key_file",
+            ));
+            Vec::new()
+        }
+        (None, None) => {
+            problems.push(problem(
+                &github.key_path("private_key_file"),
+                "or private_key is required",
+            ));
+            Vec::new()
+        }
+    }
+}
+
+fn read_http_client(github: &Section, problems: &mut Vec<ConfigProblem>) -> HttpClientConfig {
+    let defaults = HttpClientConfig::default();
+    let http = github.child("http", HTTP_KEYS, problems);
+    let client_certificate = http.child("client_certificate", TLS_KEYS, problems);
+
+    HttpClientConfig {
+        connect_timeout: http
+            .seconds("connect_timeout", problems)
+            .unwrap_or(defaults.connect_timeout),
+        request_timeout: http
+            .seconds("request_timeout", problems)
+            .unwrap_or(defaults.request_timeout),
+        pool_idle_timeout: http
+            .seconds("pool_idle_timeout", problems)
+            .unwrap_or(defaults.pool_idle_timeout),
+        pool_max_idle_per_host: http
+            .count("pool_max_idle_per_host", problems)
+            .unwrap_or(defaults.pool_max_idle_per_host),
+        proxy: http.http_url("proxy", problems),
+        no_proxy: http
+            .string_list("no_proxy", problems)
+            .unwrap_or(defaults.no_proxy),
+        ca_certificate_files: http
+            .string_or_list("ca_certificate_file", problems)
+            .map(|files| files.into_iter().map(Into::into).collect())
+            .unwrap_or(defaults.ca_certificate_files),
+        client_certificate: client_certificate.table.map(|_| TlsConfig {
+            certificate_file: client_certificate
+                .required_string("certificate_file", problems)
+                .into(),
+            key_file: client_certificate
+                .required_string("key_file", problems)
+                .into(),
+        }),
+    }
+}
+
+fn read_admin_token(admin: &Section, problems: &mut Vec<ConfigProblem>) -> Option<String> {
+    let token = admin.string("token", problems)?;
+
+    if token.is_empty() {
+        problems.push(problem(&admin.key_path("token"), "must not be empty"));
+        return None;
+    }
+
+    Some(token)
+}
+
+fn read_server(server: &Section, problems: &mut Vec<ConfigProblem>) -> ServerConfig {
+    let defaults = ServerConfig::default();
+    let tls = server.child("tls", TLS_KEYS, problems);
+
+    ServerConfig {
+        listen_address: server
+            .ip_address("listen_address", problems)
+            .unwrap_or(defaults.listen_address),
+        port: server.port("port", problems).unwrap_or(defaults.port),
+        tls: tls.table.map(|_| TlsConfig {
+            certificate_file: tls.required_string("certificate_file", problems).into(),
+            key_file: tls.required_string("key_file", problems).into(),
+        }),
+    }
+}
+
+/// Reads the keys of a policy file like `.github/koritsu.toml`
+pub fn read_policy_file(
+    table: &Table,
+    base: &MergePolicy,
+    locked_keys: &[String],
+) -> (MergePolicy, Vec<ConfigProblem>) {
+    let mut problems = Vec::new();
+    let root = Section::root(table, POLICY_KEYS, &mut problems);
+
+    let overridden_keys = table
+        .keys()
+        .filter(|key| locked_keys.contains(key))
+        .collect::<Vec<_>>();
+    for key in overridden_keys {
+        problems.push(problem(
+            &root.key_path(key),
+            "is locked by the organisation policy",
+        ));
+    }
+
+    let policy = read_policy(&root, base, &mut problems);
+    (policy, problems)
+}
+
+pub fn read_organisation_policy_file(
+    table: &Table,
+    base: &MergePolicy,
+) -> (OrganisationPolicy, Vec<ConfigProblem>) {
+    let mut problems = Vec::new();
+    let root = Section::root(table, ORGANISATION_POLICY_KEYS, &mut problems);
+
+    let locked_keys = root
+        .string_list("locked", &mut problems)
+        .unwrap_or_default();
+    for key in &locked_keys {
+        if !POLICY_KEYS.contains(&key.as_str()) {
+            let message = format!("\"{key}\" is not a policy key");
+            problems.push(problem(&root.key_path("locked"), &message));
+        }
+    }
+
+    let policy = OrganisationPolicy {
+        policy: read_policy(&root, base, &mut problems),
+        locked_keys,
+    };
+    (policy, problems)
+}
+
+fn read_policy(
+    policy: &Section,
+    base: &MergePolicy,
+    problems: &mut Vec<ConfigProblem>,
+) -> MergePolicy {
+    MergePolicy {
+        branch_prefix: policy
+            .branch_prefix("branch_prefix", problems)
+            .unwrap_or_else(|| base.branch_prefix.clone()),
+        merge_strategy: policy
+            .merge_strategy("merge_strategy", problems)
+            .unwrap_or(base.merge_strategy),
+        required_workflows: policy
+            .string_list("required_workflows", problems)
+            .unwrap_or_else(|| base.required_workflows.clone()),
+        accepted_conclusions: policy
+            .string_list("accepted_conclusions", problems)
+            .unwrap_or_else(|| base.accepted_conclusions.clone()),
+        delete_branch: policy
+            .boolean("delete_branch", problems)
+            .unwrap_or(base.delete_branch),
+        require_signed_commits: policy
+            .boolean("require_signed_commits", problems)
+            .unwrap_or(base.require_signed_commits),
+    }
+}
+
+fn read_repository_policies(
+    root: &Section,
+    problems: &mut Vec<ConfigProblem>,
+) -> HashMap<String, MergePolicy> {
+    let repositories = root.child("repositories", &[], problems);
+
+    let Some(table) = repositories.table else {
+        return HashMap::new();
+    };
+
+    table
+        .keys()
+        .filter_map(|repository_name| {
+            let policy = repositories.child(repository_name, POLICY_KEYS, problems);
+            policy.table.map(|_| {
+                let base = MergePolicy::default();
+                (
+                    repository_name.clone(),
+                    read_policy(&policy, &base, problems),
+                )
+            })
+        })
+        .collect()
+}
+
+/// A table of the configuration together with its key path for problem
+/// reports. Missing tables have no content.
+struct Section<'a> {
+    table: Option<&'a Table>,
+    path: String,
+}
+
+impl<'a> Section<'a> {
+    fn root(table: &'a Table, allowed: &[&str], problems: &mut Vec<ConfigProblem>) -> Self {
+        let section = Section {
+            table: Some(table),
+            path: String::new(),
+        };
+        section.check_keys(allowed, problems);
+        section
+    }
+
+    /// Opens a nested table. An empty list of allowed keys accepts any key.
+    fn child(&self, key: &str, allowed: &[&str], problems: &mut Vec<ConfigProblem>) -> Self {
+        let path = self.key_path(key);
+
+        let table = match self.value(key) {
+            Some(Value::Table(table)) => Some(table),
+            Some(_) => {
+                problems.push(problem(&path, "must be a table"));
+                None
+            }
+            None => None,
+        };
+
+        let section = Section { table, path };
+        if !allowed.is_empty() {
+            section.check_keys(allowed, problems);
+        }
+        section
+    }
+
+    fn check_keys(&self, allowed: &[&str], problems: &mut Vec<ConfigProblem>) {
+        let unknown_keys = self
+            .table
+            .into_iter()
+            .flat_map(|table| table.keys())
+            .filter(|key| !allowed.contains(&key.as_str()));
+
+        for key in unknown_keys {
+            problems.push(problem(&self.key_path(key), "is not a known key"));
+        }
+    }
+
+    fn value(&self, key: &str) -> Option<&'a Value> {
+        self.table.and_then(|table| table.get(key))
+    }
+
+    /// Keys like repository names are quoted, as they would be in TOML
+    fn key_path(&self, key: &str) -> String {
+        let is_bare = key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        let key = if is_bare {
+            key.to_owned()
+        } else {
+            format!("\"{key}\"")
+        };
+
+        if self.path.is_empty() {
+            key
+        } else {
+            format!("{}.{key}", self.path)
+        }
+    }
+
+    fn string(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<String> {
+        match self.value(key)? {
+            Value::String(value) => Some(value.clone()),
+            _ => {
+                problems.push(problem(&self.key_path(key), "must be a string"));
+                None
+            }
+        }
+    }
+
+    fn required_string(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> String {
+        if self.value(key).is_none() {
+            problems.push(problem(&self.key_path(key), "is required"));
+        }
+
+        self.string(key, problems).unwrap_or_default()
+    }
+
+    fn boolean(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<bool> {
+        match self.value(key)? {
+            Value::Boolean(value) => Some(*value),
+            _ => {
+                problems.push(problem(&self.key_path(key), "must be true or false"));
+                None
+            }
+        }
+    }
+
+    fn branch_prefix(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<String> {
+        let prefix = self.string(key, problems)?;
+
+        if prefix.is_empty() {
+            problems.push(problem(&self.key_path(key), "must not be empty"));
+            return None;
+        }
+
+        Some(prefix)
+    }
+
+    fn merge_strategy(
+        &self,
+        key: &str,
+        problems: &mut Vec<ConfigProblem>,
+    ) -> Option<MergeStrategy> {
+        match self.string(key, problems)?.as_str() {
+            "fast_forward" => Some(MergeStrategy::FastForward),
+            "merge" => Some(MergeStrategy::Merge),
+            other => {
+                let message = format!("\"{other}\" must be one of \"fast_forward\" or \"merge\"");
+                problems.push(problem(&self.key_path(key), &message));
+                None
+            }
+        }
+    }
+
+    fn backend(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<GitHubBackend> {
+        match self.string(key, problems)?.as_str() {
+            "rest" => Some(GitHubBackend::Rest),
+            "graphql" => Some(GitHubBackend::GraphQl),
+            other => {
+                let message = format!("\"{other}\" must be one of \"rest\" or \"graphql\"");
+                problems.push(problem(&self.key_path(key), &message));
+                None
+            }
+        }
+    }
+
+    fn string_list(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<Vec<String>> {
+        let values = match self.value(key)? {
+            Value::Array(values) => values,
+            _ => {
+                problems.push(problem(&self.key_path(key), "must be a list of strings"));
+                return None;
+            }
+        };
+
+        let strings: Option<Vec<String>> = values
+            .iter()
+            .map(|value| value.as_str().map(str::to_owned))
+            .collect();
+
+        if strings.is_none() {
+            problems.push(problem(&self.key_path(key), "must be a list of strings"));
+        }
+
+        strings
+    }
+
+    /// Accepts a single string as a list with one element
+    fn string_or_list(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<Vec<String>> {
+        let values = match self.value(key)? {
+            Value::String(value) => return Some(vec![value.clone()]),
+            Value::Array(values) => values,
+            _ => {
+                problems.push(problem(
+                    &self.key_path(key),
+                    "must be a string or a list of strings",
+                ));
+                return None;
+            }
+        };
+
+        let strings: Option<Vec<String>> = values
+            .iter()
+            .map(|value| value.as_str().map(str::to_owned))
+            .collect();
+
+        match strings {
+            Some(strings) if strings.is_empty() => {
+                problems.push(problem(&self.key_path(key), "must not be empty"));
+                None
+            }
+            Some(strings) => Some(strings),
+            None => {
+                problems.push(problem(
+                    &self.key_path(key),
+                    "must be a string or a list of strings",
+                ));
+                None
+            }
+        }
+    }
+
+    /// Trailing slashes are removed, because paths are appended to the URL
+    fn base_url(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<String> {
+        self.http_url(key, problems)
+            .map(|url| url.trim_end_matches('/').to_owned())
+    }
+
+    fn http_url(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<String> {
+        let value = self.string(key, problems)?;
+
+        match Url::parse(&value) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Some(value),
+            _ => {
+                let message = format!("\"{value}\" is not an http or https URL");
+                problems.push(problem(&self.key_path(key), &message));
+                None
+            }
+        }
+    }
+
+    /// A positive number of seconds
+    fn seconds(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<Duration> {
+        let seconds = match self.value(key)? {
+            Value::Integer(value) => u64::try_from(*value).ok().filter(|seconds| *seconds != 0),
+            _ => None,
+        };
+
+        if seconds.is_none() {
+            problems.push(problem(
+                &self.key_path(key),
+                "must be a positive number of seconds",
+            ));
+        }
+
+        seconds.map(Duration::from_secs)
+    }
+
+    fn count(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<usize> {
+        let count = match self.value(key)? {
+            Value::Integer(value) => usize::try_from(*value).ok(),
+            _ => None,
+        };
+
+        if count.is_none() {
+            problems.push(problem(&self.key_path(key), "must be zero or more"));
+        }
+
+        count
+    }
+
+    fn ip_address(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<IpAddr> {
+        let value = self.string(key, problems)?;
+
+        match value.parse() {
+            Ok(address) => Some(address),
+            Err(_) => {
+                let message = format!("\"{value}\" is not a valid IP address");
+                problems.push(problem(&self.key_path(key), &message));
+                None
+            }
+        }
+    }
+
+    fn port(&self, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<u16> {
+        let port = match self.value(key)? {
+            Value::Integer(value) => u16::try_from(*value).ok().filter(|port| *port != 0),
+            _ => None,
+        };
+
+        if port.is_none() {
+            problems.push(problem(
+                &self.key_path(key),
+                "must be a port number between 1 and 65535",
+            ));
+        }
+
+        port
+    }
+}
+
+fn problem(key: &str, message: &str) -> ConfigProblem {
+    ConfigProblem {
+        key: key.to_owned(),
+        message: message.to_owned(),
+    }
+}
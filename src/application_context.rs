@@ -7,34 +7,257 @@
  * received a copy of the license along with this program.
  */
 
+use std::{error::Error, sync::Arc};
+
+use arc_swap::ArcSwap;
+
 use crate::{
     ApplicationConfig,
-    github_api::{ApiError, AuthenticationMethod, GitHubApi, GitHubApiProvider},
+    github_api::{
+        ApiError, AuthenticationMethod, BranchHeadRequest, FileContentRequest, GitHubApi,
+        GitHubApiProvider, InstallationDetails, RateLimitStatus, ResponseCacheStats, TokenScope,
+    },
+    installations::InstallationRegistry,
+    ready_branches::ReadyBranchRegistry,
+    repository_policies::{
+        ORGANISATION_POLICY_FILE, PolicyFileCache, REPOSITORY_POLICY_FILE, RepositoryPolicy,
+        organisation_policy_repository,
+    },
 };
 
-pub struct ApplicationContext<ApiProvider> {
-    config: ApplicationConfig,
-    github_api_provider: ApiProvider,
+/// The configuration together with the credentials loaded from it, replaced
+/// as one when the configuration is reloaded
+struct Settings<Credentials> {
+    config: Arc<ApplicationConfig>,
+    credentials: Arc<Credentials>,
 }
 
-impl<ApiProvider> ApplicationContext<ApiProvider> {
-    pub fn config(&self) -> &ApplicationConfig {
-        &self.config
-    }
+pub struct ApplicationContext<ApiProvider: GitHubApiProvider> {
+    settings: ArcSwap<Settings<ApiProvider::Credentials>>,
+    github_api_provider: ApiProvider,
+    ready_branches: ReadyBranchRegistry,
+    installations: InstallationRegistry,
+    policy_files: PolicyFileCache,
 }
 
 impl<ApiProvider: GitHubApiProvider> ApplicationContext<ApiProvider> {
-    pub fn new(config: ApplicationConfig, github_api_provider: ApiProvider) -> Self {
+    pub fn new(
+        config: ApplicationConfig,
+        github_api_provider: ApiProvider,
+        credentials: Arc<ApiProvider::Credentials>,
+    ) -> Self {
         Self {
-            config,
+            settings: ArcSwap::from_pointee(Settings {
+                config: Arc::new(config),
+                credentials,
+            }),
             github_api_provider,
+            ready_branches: ReadyBranchRegistry::default(),
+            installations: InstallationRegistry::default(),
+            policy_files: PolicyFileCache::default(),
         }
     }
 
+    pub fn config(&self) -> Arc<ApplicationConfig> {
+        self.settings.load().config.clone()
+    }
+
+    pub fn ready_branches(&self) -> &ReadyBranchRegistry {
+        &self.ready_branches
+    }
+
+    pub fn installations(&self) -> &InstallationRegistry {
+        &self.installations
+    }
+
     pub async fn github_api(
         &self,
         auth_method: AuthenticationMethod,
     ) -> Result<impl GitHubApi, ApiError> {
-        self.github_api_provider.get_api(auth_method).await
+        let credentials = self.settings.load().credentials.clone();
+        self.github_api_provider
+            .get_api(credentials, auth_method)
+            .await
+    }
+
+    pub fn rate_limits(&self) -> Vec<RateLimitStatus> {
+        self.github_api_provider.rate_limits()
+    }
+
+    pub fn response_cache_stats(&self) -> ResponseCacheStats {
+        self.github_api_provider.response_cache_stats()
+    }
+
+    pub async fn installation_details(
+        &self,
+        installation_id: usize,
+    ) -> Result<Option<InstallationDetails>, ApiError> {
+        let credentials = self.settings.load().credentials.clone();
+        self.github_api_provider
+            .get_installation(&credentials, installation_id)
+            .await
+    }
+
+    /// Determines the policy of a repository from the global configuration,
+    /// the policy file of its organisation and the policy file on the default
+    /// branch of the repository. The organisation file is read with a token of
+    /// the installation limited to the `.github` repository, because the
+    /// token for the event does not include it.
+    pub async fn repository_policy(
+        &self,
+        installation_id: usize,
+        github_api: &impl GitHubApi,
+        repository_name: &str,
+        default_branch: &str,
+    ) -> Result<RepositoryPolicy, ApiError> {
+        let owner = repository_name
+            .split_once('/')
+            .map_or(repository_name, |(owner, _)| owner);
+
+        let organisation_file = self
+            .organisation_policy_file(installation_id, owner)
+            .await?;
+        let repository_file = self
+            .repository_policy_file(github_api, repository_name, default_branch)
+            .await?;
+
+        let policy = RepositoryPolicy::resolve(
+            self.config().policy_for(repository_name),
+            owner,
+            organisation_file.as_deref(),
+            repository_file.as_deref(),
+        );
+
+        self.policy_files
+            .insert_branch_prefix(repository_name, &policy.policy.branch_prefix);
+
+        if let Some(problem) = &policy.file_problem {
+            tracing::warn!(
+                repository_name,
+                file = problem.file,
+                problem = problem.description,
+                "Invalid policy file",
+            );
+        }
+
+        Ok(policy)
+    }
+
+    /// Whether the branch can be a ready branch of the repository, decided
+    /// without a token or any request by the prefix of the policy resolved
+    /// last. Every branch can be one as long as no policy is known.
+    pub fn may_be_ready_branch(&self, repository_name: &str, branch: &str) -> bool {
+        self.policy_files
+            .branch_prefix(repository_name)
+            .is_none_or(|branch_prefix| branch.starts_with(&branch_prefix))
+    }
+
+    /// A push to the default branch may change the policy file and with it
+    /// the branch prefix.
+    pub fn forget_branch_prefix(&self, repository_name: &str) {
+        self.policy_files.forget_branch_prefix(repository_name);
+    }
+
+    async fn organisation_policy_file(
+        &self,
+        installation_id: usize,
+        owner: &str,
+    ) -> Result<Option<String>, ApiError> {
+        if let Some(content) = self.policy_files.organisation_file(owner) {
+            return Ok(content);
+        }
+
+        let repository_name = organisation_policy_repository(owner);
+        let auth_method = AuthenticationMethod::AppInstallation {
+            installation_id,
+            scope: Some(
+                TokenScope::repository(&repository_name).with_permission("contents", "read"),
+            ),
+        };
+
+        // GitHub refuses tokens for repositories the installation can not
+        // access, which means there is no organisation policy for the app
+        let content = match self.github_api(auth_method).await {
+            Ok(github_api) => {
+                github_api
+                    .get_file_content(FileContentRequest {
+                        repository_name,
+                        path: ORGANISATION_POLICY_FILE.to_owned(),
+                        reference: None,
+                    })
+                    .await?
+            }
+            Err(ApiError::Validation(details)) => {
+                tracing::debug!(
+                    repository_name,
+                    %details,
+                    "Installation can not access the organisation policy repository",
+                );
+                None
+            }
+            Err(error) => return Err(error),
+        };
+
+        self.policy_files
+            .insert_organisation_file(owner, content.clone());
+
+        Ok(content)
+    }
+
+    async fn repository_policy_file(
+        &self,
+        github_api: &impl GitHubApi,
+        repository_name: &str,
+        default_branch: &str,
+    ) -> Result<Option<String>, ApiError> {
+        let commit_sha = github_api
+            .get_branch_head(BranchHeadRequest {
+                repository_name: repository_name.to_owned(),
+                branch: default_branch.to_owned(),
+            })
+            .await?;
+
+        if let Some(content) = self
+            .policy_files
+            .repository_file(repository_name, &commit_sha)
+        {
+            return Ok(content);
+        }
+
+        let content = github_api
+            .get_file_content(FileContentRequest {
+                repository_name: repository_name.to_owned(),
+                path: REPOSITORY_POLICY_FILE.to_owned(),
+                reference: Some(commit_sha.clone()),
+            })
+            .await?;
+
+        self.policy_files
+            .insert_repository_file(repository_name, &commit_sha, content.clone());
+
+        Ok(content)
+    }
+
+    /// Switches to a reloaded configuration together with the credentials it
+    /// names, so that no request sees one without the other. Nothing changes
+    /// if the credentials can not be loaded.
+    pub fn update_config(
+        &self,
+        config: ApplicationConfig,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let credentials = self.github_api_provider.load_credentials(&config)?;
+        self.settings.store(Arc::new(Settings {
+            config: Arc::new(config),
+            credentials: Arc::new(credentials),
+        }));
+        self.policy_files.forget_branch_prefixes();
+        Ok(())
+    }
+
+    /// Removes all state that belongs to the installation.
+    pub fn purge_installation(&self, installation_id: usize) {
+        self.ready_branches.remove_installation(installation_id);
+        self.github_api_provider
+            .forget_installation(installation_id);
     }
 }
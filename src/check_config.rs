@@ -0,0 +1,246 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{error::Error, fmt, sync::Arc};
+
+use crate::{
+    ApplicationConfig, GitHubBackend,
+    application_context::ApplicationContext,
+    github_api::{
+        ApiError, AppCredentials, AuthenticationMethod, GitHubApi, GitHubApiProvider,
+        GitHubGraphQlApiProvider, GitHubRestApiProvider, InstallationDetails, KeyCheck,
+    },
+};
+
+/// A single check of the `check-config` command
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub subject: String,
+    /// Why the check failed
+    pub problem: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    pub checks: Vec<Check>,
+}
+
+impl CheckReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.problem.is_none())
+    }
+
+    fn pass(&mut self, subject: String) {
+        self.checks.push(Check {
+            subject,
+            problem: None,
+        });
+    }
+
+    fn fail(&mut self, subject: String, problem: String) {
+        self.checks.push(Check {
+            subject,
+            problem: Some(problem),
+        });
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.problem {
+                None => writeln!(f, "ok     {}", check.subject)?,
+                Some(problem) => {
+                    writeln!(f, "FAILED {}", check.subject)?;
+                    for line in problem.lines() {
+                        writeln!(f, "         {line}")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The providers of the GitHub API backends. Both check the private keys with
+/// the REST API.
+trait CheckedProvider: GitHubApiProvider<Credentials = AppCredentials> {
+    async fn key_checks(&self, credentials: &AppCredentials) -> Vec<KeyCheck>;
+}
+
+impl CheckedProvider for GitHubRestApiProvider {
+    async fn key_checks(&self, credentials: &AppCredentials) -> Vec<KeyCheck> {
+        self.check_keys(credentials).await
+    }
+}
+
+impl CheckedProvider for GitHubGraphQlApiProvider {
+    async fn key_checks(&self, credentials: &AppCredentials) -> Vec<KeyCheck> {
+        self.check_keys(credentials).await
+    }
+}
+
+/// Checks that the application can authenticate with the configuration and
+/// that the policy files of all repositories it is installed on are valid.
+/// The checks use the GitHub API backend the configuration selects. Checks
+/// that depend on a failed check are skipped.
+pub async fn check_config(config: ApplicationConfig) -> CheckReport {
+    let mut report = CheckReport::default();
+
+    match config.github_backend {
+        GitHubBackend::Rest => match GitHubRestApiProvider::new(&config) {
+            Ok(provider) => check_with_provider(&mut report, config, provider).await,
+            Err(error) => report.fail("HTTP client can be created".to_owned(), describe(&*error)),
+        },
+        GitHubBackend::GraphQl => match GitHubGraphQlApiProvider::new(&config) {
+            Ok(provider) => check_with_provider(&mut report, config, provider).await,
+            Err(error) => report.fail("HTTP client can be created".to_owned(), describe(&*error)),
+        },
+    }
+
+    report
+}
+
+async fn check_with_provider<ApiProvider: CheckedProvider>(
+    report: &mut CheckReport,
+    config: ApplicationConfig,
+    github_api_provider: ApiProvider,
+) {
+    let credentials = match github_api_provider.load_credentials(&config) {
+        Ok(credentials) => {
+            report.pass("Private keys can be loaded".to_owned());
+            Arc::new(credentials)
+        }
+        Err(error) => {
+            report.fail("Private keys can be loaded".to_owned(), describe(&*error));
+            return;
+        }
+    };
+
+    let key_checks = github_api_provider.key_checks(&credentials).await;
+    let any_key_accepted = key_checks.iter().any(|check| check.outcome.is_ok());
+    for check in key_checks {
+        report_key_check(report, check);
+    }
+    if !any_key_accepted {
+        return;
+    }
+
+    let installations = match list_installations(&github_api_provider, &credentials).await {
+        Ok(installations) => {
+            report.pass(format!("Listed {} installations", installations.len()));
+            installations
+        }
+        Err(error) => {
+            report.fail("Listing installations".to_owned(), error.to_string());
+            return;
+        }
+    };
+
+    let app_context = ApplicationContext::new(config, github_api_provider, credentials);
+    for installation in installations {
+        check_installation(report, &app_context, installation).await;
+    }
+}
+
+async fn list_installations<ApiProvider: CheckedProvider>(
+    github_api_provider: &ApiProvider,
+    credentials: &Arc<AppCredentials>,
+) -> Result<Vec<InstallationDetails>, ApiError> {
+    github_api_provider
+        .get_api(credentials.clone(), AuthenticationMethod::App)
+        .await?
+        .list_installations()
+        .await
+}
+
+fn report_key_check(report: &mut CheckReport, check: KeyCheck) {
+    let subject = format!("Private key {} ({})", check.name, check.fingerprint);
+
+    match check.outcome {
+        Ok(app_name) => report.pass(format!("{subject} is accepted for {app_name}")),
+        Err(problem) => report.fail(subject, problem),
+    }
+}
+
+async fn check_installation<ApiProvider: GitHubApiProvider>(
+    report: &mut CheckReport,
+    app_context: &ApplicationContext<ApiProvider>,
+    installation: InstallationDetails,
+) {
+    let subject = format!(
+        "Installation {} of {}",
+        installation.id, installation.account
+    );
+
+    if installation.suspended {
+        report.pass(format!("{subject} is suspended"));
+        return;
+    }
+
+    let auth_method = AuthenticationMethod::AppInstallation {
+        installation_id: installation.id,
+        scope: None,
+    };
+    let github_api = match app_context.github_api(auth_method).await {
+        Ok(github_api) => github_api,
+        Err(error) => {
+            report.fail(subject, error.to_string());
+            return;
+        }
+    };
+
+    let repositories = match github_api.list_installation_repositories().await {
+        Ok(repositories) => repositories,
+        Err(error) => {
+            report.fail(subject, error.to_string());
+            return;
+        }
+    };
+    report.pass(format!(
+        "{subject} can access {} repositories",
+        repositories.len()
+    ));
+
+    for repository in repositories {
+        let subject = format!("Policy of {}", repository.full_name);
+
+        match app_context
+            .repository_policy(
+                installation.id,
+                &github_api,
+                &repository.full_name,
+                &repository.default_branch,
+            )
+            .await
+        {
+            Ok(policy) => match policy.file_problem {
+                None => report.pass(subject),
+                Some(problem) => report.fail(
+                    subject,
+                    format!("Invalid {}\n{}", problem.file, problem.description),
+                ),
+            },
+            Err(error) => report.fail(subject, error.to_string()),
+        }
+    }
+}
+
+fn describe(error: &dyn Error) -> String {
+    let mut description = error.to_string();
+
+    let mut source = error.source();
+    while let Some(cause) = source {
+        description.push_str(&format!("\n{cause}"));
+        source = cause.source();
+    }
+
+    description
+}
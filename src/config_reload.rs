@@ -0,0 +1,184 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    env,
+    error::Error,
+    future,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use thiserror::Error;
+use tokio::signal::unix::{Signal, SignalKind, signal};
+
+use crate::{
+    ApplicationConfig, CONFIG_FILE_VARIABLE, ConfigError, PrivateKeySource,
+    application_context::ApplicationContext, github_api::GitHubApiProvider,
+};
+
+/// How often the configuration and private key files are checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum ReloadError {
+    #[error("Could not load application configuration")]
+    Configuration(#[from] ConfigError),
+
+    #[error("Could not load the GitHub App credentials")]
+    Credentials(#[source] Box<dyn Error + Send + Sync>),
+}
+
+/// Replaces the configuration and the credentials of a running application
+/// without dropping events that are in flight.
+pub struct ConfigReloader<ApiProvider: GitHubApiProvider> {
+    app_context: Arc<ApplicationContext<ApiProvider>>,
+}
+
+impl<ApiProvider: GitHubApiProvider> Clone for ConfigReloader<ApiProvider> {
+    fn clone(&self) -> Self {
+        Self {
+            app_context: self.app_context.clone(),
+        }
+    }
+}
+
+impl<ApiProvider: GitHubApiProvider> ConfigReloader<ApiProvider> {
+    pub(crate) fn new(app_context: Arc<ApplicationContext<ApiProvider>>) -> Self {
+        Self { app_context }
+    }
+
+    /// The configuration the application currently uses
+    pub fn config(&self) -> Arc<ApplicationConfig> {
+        self.app_context.config()
+    }
+
+    /// Switches to the configuration if the credentials it names are valid.
+    /// Otherwise the previous configuration stays in use. The server and the
+    /// GitHub client keep the settings they were started with, so those are
+    /// kept in the configuration as well.
+    pub fn apply(&self, mut config: ApplicationConfig) -> Result<(), ReloadError> {
+        let running = self.app_context.config();
+
+        let mut restart_required = Vec::new();
+        if config.server != running.server {
+            restart_required.push("server");
+        }
+        if config.github_base_url != running.github_base_url
+            || config.github_api_version != running.github_api_version
+            || config.github_backend != running.github_backend
+        {
+            restart_required.push("github.base_url, github.api_version and github.backend");
+        }
+        if config.github_http != running.github_http {
+            restart_required.push("github.http");
+        }
+        if !restart_required.is_empty() {
+            tracing::warn!(
+                ?restart_required,
+                "Changed settings take effect after a restart"
+            );
+        }
+
+        config.server = running.server.clone();
+        config.github_base_url = running.github_base_url.clone();
+        config.github_api_version = running.github_api_version.clone();
+        config.github_backend = running.github_backend;
+        config.github_http = running.github_http.clone();
+
+        self.app_context
+            .update_config(config)
+            .map_err(ReloadError::Credentials)
+    }
+
+    /// Reloads the configuration on `SIGHUP` and when the configuration file
+    /// or a private key file changes.
+    pub async fn watch(self) {
+        let config_file = env::var_os(CONFIG_FILE_VARIABLE).map(PathBuf::from);
+        self.watch_file(config_file, RELOAD_INTERVAL).await
+    }
+
+    /// Like [`ConfigReloader::watch`] with the configuration file at the path
+    /// instead of the one `KORITSU_CONFIG_FILE` names, checking the files for
+    /// changes at the interval
+    pub async fn watch_file(self, config_file: Option<PathBuf>, check_interval: Duration) {
+        let config_file = config_file.as_deref();
+        let mut hangup = signal(SignalKind::hangup())
+            .inspect_err(|error| tracing::warn!(%error, "Can not reload on SIGHUP"))
+            .ok();
+
+        let mut loaded = self.modification_times(config_file);
+        let mut interval = tokio::time::interval(check_interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let current = self.modification_times(config_file);
+                    if current == loaded {
+                        continue;
+                    }
+                    loaded = current;
+                }
+                _ = received(&mut hangup) => tracing::info!("Received SIGHUP"),
+            }
+
+            match ApplicationConfig::load_file(config_file)
+                .map_err(ReloadError::from)
+                .and_then(|config| self.apply(config))
+            {
+                Ok(()) => tracing::info!("Reloaded configuration"),
+                Err(error) => tracing::error!(
+                    error = describe(&error),
+                    "Could not reload configuration, keeping the previous one"
+                ),
+            }
+        }
+    }
+
+    fn watched_files(&self, config_file: Option<&Path>) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = config_file.map(Path::to_owned).into_iter().collect();
+
+        for key in &self.app_context.config().private_keys {
+            if let PrivateKeySource::File(path) = key {
+                files.push(path.clone());
+            }
+        }
+
+        files
+    }
+
+    fn modification_times(&self, config_file: Option<&Path>) -> Vec<Option<SystemTime>> {
+        self.watched_files(config_file)
+            .iter()
+            .map(|path| path.metadata().and_then(|meta| meta.modified()).ok())
+            .collect()
+    }
+}
+
+async fn received(hangup: &mut Option<Signal>) {
+    match hangup {
+        Some(hangup) => {
+            hangup.recv().await;
+        }
+        None => future::pending().await,
+    }
+}
+
+fn describe(error: &ReloadError) -> String {
+    let mut description = error.to_string();
+
+    let mut source = error.source();
+    while let Some(cause) = source {
+        description.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+
+    description
+}
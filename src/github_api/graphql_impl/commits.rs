@@ -0,0 +1,211 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::BranchComparison;
+use crate::github_api::BranchComparisonRequest;
+use crate::github_api::CommitIdentity;
+use crate::github_api::ComparedCommit;
+use crate::github_api::ErrorDetails;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::instrument;
+
+use super::{GraphQlClient, split_repository_name};
+
+/// The distance of both branches and the details of the commits of the head
+/// branch, including whether their signature is valid
+const COMPARE_QUERY: &str = r#"
+query($owner: String!, $name: String!, $base: String!, $head: String!, $after: String) {
+  repository(owner: $owner, name: $name) {
+    ref(qualifiedName: $base) {
+      compare(headRef: $head) {
+        aheadBy
+        behindBy
+        commits(first: 100, after: $after) {
+          nodes {
+            oid
+            message
+            author { name email user { login } }
+            committer { name email user { login } }
+            parents(first: 100) { nodes { oid } }
+            signature { isValid }
+          }
+          pageInfo { hasNextPage endCursor }
+        }
+      }
+    }
+  }
+}
+"#;
+
+pub struct GithubCommitsGraphQlApi<'a> {
+    client: GraphQlClient<'a>,
+}
+
+impl<'a> GithubCommitsGraphQlApi<'a> {
+    pub fn new(client: GraphQlClient<'a>) -> Self {
+        Self { client }
+    }
+}
+
+impl GithubCommitsGraphQlApi<'_> {
+    #[instrument(skip_all, fields(request))]
+    pub async fn compare_commits(
+        &self,
+        request: BranchComparisonRequest,
+    ) -> Result<BranchComparison, ApiError> {
+        let (owner, name) = split_repository_name(&request.repository_name)?;
+        let mut commits = Vec::new();
+        let mut after = None;
+
+        loop {
+            let data: CompareGraphQl = self
+                .client
+                .query(
+                    COMPARE_QUERY,
+                    json!({
+                        "owner": owner,
+                        "name": name,
+                        "base": format!("refs/heads/{}", request.base_branch),
+                        "head": format!("refs/heads/{}", request.head_branch),
+                        "after": after,
+                    }),
+                )
+                .await?;
+
+            let comparison = data
+                .repository
+                .reference
+                .and_then(|reference| reference.compare)
+                .ok_or_else(|| {
+                    ApiError::RepositoryNotFound(ErrorDetails::from_message(format!(
+                        "Can not compare {} with {}",
+                        request.base_branch, request.head_branch
+                    )))
+                })?;
+
+            commits.extend(comparison.commits.nodes.into_iter().map(Into::into));
+
+            let page_info = comparison.commits.page_info;
+            if !page_info.has_next_page {
+                return Ok(BranchComparison {
+                    ahead_by: comparison.ahead_by,
+                    behind_by: comparison.behind_by,
+                    commits,
+                });
+            }
+            after = page_info.end_cursor;
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CompareGraphQl {
+    repository: RepositoryGraphQl,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryGraphQl {
+    #[serde(rename = "ref")]
+    reference: Option<ReferenceGraphQl>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReferenceGraphQl {
+    compare: Option<ComparisonGraphQl>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ComparisonGraphQl {
+    ahead_by: usize,
+    behind_by: usize,
+    commits: CommitConnectionGraphQl,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitConnectionGraphQl {
+    nodes: Vec<CommitGraphQl>,
+    page_info: PageInfoGraphQl,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitGraphQl {
+    oid: String,
+    message: String,
+    author: Option<GitActorGraphQl>,
+    committer: Option<GitActorGraphQl>,
+    parents: ParentConnectionGraphQl,
+    signature: Option<SignatureGraphQl>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitActorGraphQl {
+    name: Option<String>,
+    email: Option<String>,
+    user: Option<UserGraphQl>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserGraphQl {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ParentConnectionGraphQl {
+    nodes: Vec<ParentGraphQl>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ParentGraphQl {
+    oid: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignatureGraphQl {
+    is_valid: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfoGraphQl {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+impl From<CommitGraphQl> for ComparedCommit {
+    fn from(commit: CommitGraphQl) -> Self {
+        ComparedCommit {
+            sha: commit.oid,
+            message: commit.message,
+            author: commit.author.map(Into::into),
+            committer: commit.committer.map(Into::into),
+            parents: commit
+                .parents
+                .nodes
+                .into_iter()
+                .map(|parent| parent.oid)
+                .collect(),
+            verified: commit.signature.is_some_and(|signature| signature.is_valid),
+        }
+    }
+}
+
+impl From<GitActorGraphQl> for CommitIdentity {
+    fn from(actor: GitActorGraphQl) -> Self {
+        CommitIdentity {
+            name: actor.name.unwrap_or_default(),
+            email: actor.email.unwrap_or_default(),
+            login: actor.user.map(|user| user.login),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::ErrorDetails;
+use crate::github_api::FileContentRequest;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::instrument;
+
+use super::{GraphQlClient, split_repository_name};
+
+const FILE_CONTENT_QUERY: &str = r#"
+query($owner: String!, $name: String!, $expression: String!) {
+  repository(owner: $owner, name: $name) {
+    object(expression: $expression) {
+      ... on Blob { text isTruncated }
+    }
+  }
+}
+"#;
+
+pub struct GithubContentsGraphQlApi<'a> {
+    client: GraphQlClient<'a>,
+}
+
+impl<'a> GithubContentsGraphQlApi<'a> {
+    pub fn new(client: GraphQlClient<'a>) -> Self {
+        Self { client }
+    }
+}
+
+impl GithubContentsGraphQlApi<'_> {
+    #[instrument(skip_all, fields(request))]
+    pub async fn get_file_content(
+        &self,
+        request: FileContentRequest,
+    ) -> Result<Option<String>, ApiError> {
+        let (owner, name) = split_repository_name(&request.repository_name)?;
+
+        // `HEAD` is the default branch
+        let reference = request.reference.as_deref().unwrap_or("HEAD");
+        let result = self
+            .client
+            .query::<FileContentGraphQl>(
+                FILE_CONTENT_QUERY,
+                json!({
+                    "owner": owner,
+                    "name": name,
+                    "expression": format!("{reference}:{}", request.path),
+                }),
+            )
+            .await;
+
+        // Like the REST API, a missing repository has no files
+        let blob = match result {
+            Ok(data) => data.repository.object,
+            Err(ApiError::RepositoryNotFound(_)) => None,
+            Err(error) => return Err(error),
+        };
+
+        blob.map(BlobGraphQl::text).transpose()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FileContentGraphQl {
+    repository: RepositoryGraphQl,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryGraphQl {
+    object: Option<BlobGraphQl>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlobGraphQl {
+    /// Missing for binary files and for other objects like directories
+    text: Option<String>,
+    #[serde(default)]
+    is_truncated: bool,
+}
+
+impl BlobGraphQl {
+    fn text(self) -> Result<String, ApiError> {
+        match self.text {
+            Some(_) if self.is_truncated => {
+                tracing::error!("File is too large to be read");
+                Err(ApiError::Truncated(ErrorDetails::from_message(
+                    "File is too large to be read",
+                )))
+            }
+            Some(text) => Ok(text),
+            None => {
+                tracing::error!("File content is not valid UTF-8 text");
+                Err(ApiError::Serialization(ErrorDetails::from_message(
+                    "File content is not valid UTF-8 text",
+                )))
+            }
+        }
+    }
+}
@@ -0,0 +1,318 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::error::Error;
+use std::sync::Arc;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tracing::instrument;
+
+use crate::ApplicationConfig;
+
+use super::rest_impl::endpoint::{ApiEndpoint, GitHubRequestExt};
+use super::rest_impl::error_handling::IntoErrorHandlingRequest;
+use super::rest_impl::{GitHubRestApi, Token};
+use super::{
+    ApiError, AppCredentials, AuthenticationMethod, BranchComparison, BranchComparisonRequest,
+    BranchHeadRequest, ChangedFile, CheckRunRequest, CommitStatusRequest, DeleteReferenceRequest,
+    ErrorDetails, FileContentRequest, GitHubApi, GitHubApiProvider, GitHubRestApiProvider,
+    InstallationDetails, KeyCheck, MergeBranchRequest, MergeResult, RateLimitStatus,
+    RepositorySummary, ResponseCacheStats, UpdateReferenceRequest, WorkflowRunSummary,
+    WorkflowRunsRequest,
+};
+use commits::GithubCommitsGraphQlApi;
+use contents::GithubContentsGraphQlApi;
+use refs::GithubRefsGraphQlApi;
+
+mod commits;
+mod contents;
+mod refs;
+
+/// Reads from the GraphQL API, which answers with everything a decision needs
+/// in a single query. Installation tokens, the app itself and all writes use
+/// the REST API, because the GraphQL API has no counterpart for them.
+pub struct GitHubGraphQlApiProvider {
+    rest: GitHubRestApiProvider,
+    url: String,
+}
+
+impl GitHubGraphQlApiProvider {
+    pub fn new(config: &ApplicationConfig) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            rest: GitHubRestApiProvider::new(config)?,
+            url: graphql_url(&config.github_base_url),
+        })
+    }
+
+    pub async fn check_server_version(&self) {
+        self.rest.check_server_version().await
+    }
+
+    pub async fn check_keys(&self, credentials: &AppCredentials) -> Vec<KeyCheck> {
+        self.rest.check_keys(credentials).await
+    }
+}
+
+/// GitHub Enterprise Server serves the GraphQL API at `/api/graphql` next to
+/// the REST API at `/api/v3`.
+fn graphql_url(base_url: &str) -> String {
+    match base_url.strip_suffix("/api/v3") {
+        Some(host) => format!("{host}/api/graphql"),
+        None => format!("{base_url}/graphql"),
+    }
+}
+
+impl GitHubApiProvider for GitHubGraphQlApiProvider {
+    type Credentials = AppCredentials;
+
+    fn load_credentials(
+        &self,
+        config: &ApplicationConfig,
+    ) -> Result<AppCredentials, Box<dyn Error + Send + Sync>> {
+        self.rest.load_credentials(config)
+    }
+
+    #[instrument(skip_all, fields(auth_method))]
+    async fn get_api(
+        &self,
+        credentials: Arc<AppCredentials>,
+        auth_method: AuthenticationMethod,
+    ) -> Result<impl GitHubApi, ApiError> {
+        Ok(GitHubGraphQlApi {
+            rest: self.rest.api(credentials, auth_method).await?,
+            url: &self.url,
+        })
+    }
+
+    fn forget_installation(&self, installation_id: usize) {
+        self.rest.forget_installation(installation_id)
+    }
+
+    fn rate_limits(&self) -> Vec<RateLimitStatus> {
+        self.rest.rate_limits()
+    }
+
+    fn response_cache_stats(&self) -> ResponseCacheStats {
+        self.rest.response_cache_stats()
+    }
+
+    async fn get_installation(
+        &self,
+        credentials: &AppCredentials,
+        installation_id: usize,
+    ) -> Result<Option<InstallationDetails>, ApiError> {
+        self.rest
+            .get_installation(credentials, installation_id)
+            .await
+    }
+
+    async fn get_repository_installation(
+        &self,
+        credentials: &AppCredentials,
+        repository_name: &str,
+    ) -> Result<Option<InstallationDetails>, ApiError> {
+        self.rest
+            .get_repository_installation(credentials, repository_name)
+            .await
+    }
+}
+
+struct GitHubGraphQlApi<'a> {
+    rest: GitHubRestApi<'a>,
+    url: &'a str,
+}
+
+impl GitHubGraphQlApi<'_> {
+    fn client(&self) -> GraphQlClient<'_> {
+        GraphQlClient {
+            token: &self.rest.token,
+            client: self.rest.client,
+            endpoint: self.rest.endpoint,
+            url: self.url,
+        }
+    }
+}
+
+impl GitHubApi for GitHubGraphQlApi<'_> {
+    async fn compare_commits(
+        &self,
+        request: BranchComparisonRequest,
+    ) -> Result<BranchComparison, ApiError> {
+        GithubCommitsGraphQlApi::new(self.client())
+            .compare_commits(request)
+            .await
+    }
+
+    async fn list_changed_files(
+        &self,
+        request: BranchComparisonRequest,
+    ) -> Result<Vec<ChangedFile>, ApiError> {
+        // The GraphQL API does not list the changed files of a comparison
+        self.rest.list_changed_files(request).await
+    }
+
+    async fn update_reference(&self, request: UpdateReferenceRequest) -> Result<(), ApiError> {
+        self.rest.update_reference(request).await
+    }
+
+    async fn create_commit_status(&self, request: CommitStatusRequest) -> Result<(), ApiError> {
+        self.rest.create_commit_status(request).await
+    }
+
+    async fn list_workflow_runs(
+        &self,
+        request: WorkflowRunsRequest,
+    ) -> Result<Vec<WorkflowRunSummary>, ApiError> {
+        // The GraphQL API does not know the attempts of a workflow run
+        self.rest.list_workflow_runs(request).await
+    }
+
+    async fn get_branch_head(&self, request: BranchHeadRequest) -> Result<String, ApiError> {
+        GithubRefsGraphQlApi::new(self.client())
+            .get_branch_head(request)
+            .await
+    }
+
+    async fn get_file_content(
+        &self,
+        request: FileContentRequest,
+    ) -> Result<Option<String>, ApiError> {
+        GithubContentsGraphQlApi::new(self.client())
+            .get_file_content(request)
+            .await
+    }
+
+    async fn create_check_run(&self, request: CheckRunRequest) -> Result<(), ApiError> {
+        self.rest.create_check_run(request).await
+    }
+
+    async fn merge_branch(&self, request: MergeBranchRequest) -> Result<MergeResult, ApiError> {
+        self.rest.merge_branch(request).await
+    }
+
+    async fn delete_reference(&self, request: DeleteReferenceRequest) -> Result<(), ApiError> {
+        self.rest.delete_reference(request).await
+    }
+
+    async fn list_installation_repositories(&self) -> Result<Vec<RepositorySummary>, ApiError> {
+        self.rest.list_installation_repositories().await
+    }
+
+    async fn list_installations(&self) -> Result<Vec<InstallationDetails>, ApiError> {
+        self.rest.list_installations().await
+    }
+}
+
+/// Sends queries to the GraphQL API with the access token of an installation
+pub struct GraphQlClient<'a> {
+    token: &'a Token,
+    client: &'a Client,
+    endpoint: &'a ApiEndpoint,
+    url: &'a str,
+}
+
+impl GraphQlClient<'_> {
+    /// Sends the query and returns its `data`. GitHub answers failed queries
+    /// with `200 OK` and a list of `errors`, which are classified like the
+    /// status codes of the REST API.
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: Value,
+    ) -> Result<T, ApiError> {
+        let body = serde_json::to_vec(&GraphQlRequest { query, variables })?;
+
+        // Queries only read, so they can be repeated
+        let response = self
+            .client
+            .post(self.url)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .github_headers(self.endpoint)
+            .with_access_token(self.token)
+            .without_rate_limit()
+            .safe_to_repeat(true)
+            .send()
+            .await?;
+
+        if !response.is_success() {
+            return Err(response.into_error().await);
+        }
+
+        let status = response.status();
+        let request_id = response.request_id();
+        let response: GraphQlResponse = response.json().await?;
+
+        if !response.errors.is_empty() {
+            return Err(GraphQlErrorRest::classify(
+                response.errors,
+                ErrorDetails {
+                    status: Some(status),
+                    request_id,
+                    ..ErrorDetails::default()
+                },
+            ));
+        }
+
+        Ok(serde_json::from_value(response.data)?)
+    }
+}
+
+/// Splits `owner/name`, the form repositories are named in the REST API
+pub fn split_repository_name(repository_name: &str) -> Result<(&str, &str), ApiError> {
+    repository_name.split_once('/').ok_or_else(|| {
+        ApiError::RepositoryNotFound(ErrorDetails::from_message(format!(
+            "{repository_name} is not of the form owner/name"
+        )))
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct GraphQlRequest<'a> {
+    query: &'a str,
+    variables: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlResponse {
+    #[serde(default)]
+    data: Value,
+    #[serde(default)]
+    errors: Vec<GraphQlErrorRest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlErrorRest {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    message: String,
+}
+
+impl GraphQlErrorRest {
+    /// The first error decides the kind, the others are kept as details
+    fn classify(errors: Vec<GraphQlErrorRest>, details: ErrorDetails) -> ApiError {
+        let kind = errors.first().and_then(|error| error.kind.clone());
+        let mut messages = errors.into_iter().map(|error| error.message);
+
+        let details = ErrorDetails {
+            message: messages.next(),
+            errors: messages.collect(),
+            ..details
+        };
+
+        match kind.as_deref() {
+            Some("NOT_FOUND") => ApiError::RepositoryNotFound(details),
+            Some("FORBIDDEN") => ApiError::Authorization(details),
+            Some("RATE_LIMITED") => ApiError::RateLimited(details),
+            _ => ApiError::UnexpectedStatus(details),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::BranchHeadRequest;
+use crate::github_api::ErrorDetails;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::instrument;
+
+use super::{GraphQlClient, split_repository_name};
+
+const BRANCH_HEAD_QUERY: &str = r#"
+query($owner: String!, $name: String!, $qualifiedName: String!) {
+  repository(owner: $owner, name: $name) {
+    ref(qualifiedName: $qualifiedName) {
+      target { oid }
+    }
+  }
+}
+"#;
+
+pub struct GithubRefsGraphQlApi<'a> {
+    client: GraphQlClient<'a>,
+}
+
+impl<'a> GithubRefsGraphQlApi<'a> {
+    pub fn new(client: GraphQlClient<'a>) -> Self {
+        Self { client }
+    }
+}
+
+impl GithubRefsGraphQlApi<'_> {
+    #[instrument(skip_all, fields(request))]
+    pub async fn get_branch_head(&self, request: BranchHeadRequest) -> Result<String, ApiError> {
+        let (owner, name) = split_repository_name(&request.repository_name)?;
+
+        let data: BranchHeadGraphQl = self
+            .client
+            .query(
+                BRANCH_HEAD_QUERY,
+                json!({
+                    "owner": owner,
+                    "name": name,
+                    "qualifiedName": format!("refs/heads/{}", request.branch),
+                }),
+            )
+            .await?;
+
+        data.repository
+            .reference
+            .map(|reference| reference.target.oid)
+            .ok_or_else(|| {
+                ApiError::RepositoryNotFound(ErrorDetails::from_message(format!(
+                    "Branch {} does not exist",
+                    request.branch
+                )))
+            })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BranchHeadGraphQl {
+    repository: RepositoryGraphQl,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryGraphQl {
+    #[serde(rename = "ref")]
+    reference: Option<ReferenceGraphQl>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReferenceGraphQl {
+    target: TargetGraphQl,
+}
+
+#[derive(Debug, Deserialize)]
+struct TargetGraphQl {
+    oid: String,
+}
@@ -7,32 +7,236 @@
  * received a copy of the license along with this program.
  */
 
-pub use rest_impl::GitHubRestApiProvider;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+pub use graphql_impl::GitHubGraphQlApiProvider;
+use hyper::StatusCode;
+pub use rest_impl::{AppCredentials, GitHubRestApiProvider, HttpClientError, KeyCheck};
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::ApplicationConfig;
+
+mod graphql_impl;
 mod rest_impl;
 
 pub trait GitHubApiProvider: Send + Sync {
+    /// What the provider reads from the configuration to authenticate as the
+    /// app. The application context keeps them in one snapshot with the
+    /// configuration they were loaded from.
+    type Credentials: Send + Sync + 'static;
+
+    /// Loads the credentials the configuration names, failing if they are
+    /// invalid.
+    fn load_credentials(
+        &self,
+        config: &ApplicationConfig,
+    ) -> Result<Self::Credentials, Box<dyn Error + Send + Sync>>;
+
     fn get_api(
         &self,
+        credentials: Arc<Self::Credentials>,
         auth_method: AuthenticationMethod,
     ) -> impl Future<Output = Result<impl GitHubApi, ApiError>> + Send;
+
+    /// Looks up an installation of the application. Returns `None` if GitHub
+    /// does not know the installation.
+    fn get_installation(
+        &self,
+        credentials: &Self::Credentials,
+        installation_id: usize,
+    ) -> impl Future<Output = Result<Option<InstallationDetails>, ApiError>> + Send;
+
+    /// Looks up the installation that has access to the repository, given as
+    /// `owner/name`. Returns `None` if the app is not installed on it.
+    fn get_repository_installation(
+        &self,
+        credentials: &Self::Credentials,
+        repository_name: &str,
+    ) -> impl Future<Output = Result<Option<InstallationDetails>, ApiError>> + Send;
+
+    /// Drops everything the provider keeps for the installation, because it
+    /// was removed or suspended.
+    fn forget_installation(&self, _installation_id: usize) {}
+
+    /// The latest known rate limits of the application and its installations
+    fn rate_limits(&self) -> Vec<RateLimitStatus> {
+        Vec::new()
+    }
+
+    fn response_cache_stats(&self) -> ResponseCacheStats {
+        ResponseCacheStats::default()
+    }
+}
+
+/// The size of the cache of GitHub responses that are validated with their
+/// `ETag` or `Last-Modified` header
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResponseCacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    /// Requests GitHub answered with `304 Not Modified`
+    pub hits: u64,
+}
+
+/// The rate limit GitHub applies to the application itself or to one of its
+/// installations, as reported by the latest response
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RateLimitStatus {
+    /// `None` for requests authenticated as the application
+    pub installation_id: Option<usize>,
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    /// Seconds since the Unix epoch when the remaining requests are reset
+    pub reset_at: Option<u64>,
+    /// Seconds since the Unix epoch until which requests are held back
+    /// after GitHub asked to slow down
+    pub paused_until: Option<u64>,
 }
 
 pub enum AuthenticationMethod {
-    AppInstallation { installation_id: usize },
+    /// An installation access token, which is requested and refreshed as
+    /// needed
+    AppInstallation {
+        installation_id: usize,
+        /// Limits the token to some repositories and permissions. Without a
+        /// scope the token can do everything the installation can.
+        scope: Option<TokenScope>,
+    },
+    PersonalAccessToken {
+        token: String,
+    },
+    /// The token a user granted the app through the OAuth web flow
+    OAuthUserToken {
+        token: String,
+    },
+    /// A JWT of the app itself, which only works for the endpoints of the
+    /// app like listing its installations. It is valid for two minutes.
+    App,
+}
+
+/// The repositories and permissions an installation access token is limited
+/// to. Installation access tokens are cached per scope.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TokenScope {
+    /// Names of repositories without their owner, the account of the
+    /// installation
+    pub repositories: BTreeSet<String>,
+    /// Access levels like `read` or `write` by permission like `contents`
+    pub permissions: BTreeMap<String, String>,
+}
+
+impl TokenScope {
+    /// A scope for the repository with its full name like `owner/repository`
+    pub fn repository(repository_name: &str) -> Self {
+        let name = repository_name
+            .split_once('/')
+            .map_or(repository_name, |(_, name)| name);
+
+        Self {
+            repositories: BTreeSet::from([name.to_owned()]),
+            permissions: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_permission(mut self, permission: &str, access: &str) -> Self {
+        self.permissions
+            .insert(permission.to_owned(), access.to_owned());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallationDetails {
+    pub id: usize,
+    pub account: String,
+    pub permissions: BTreeMap<String, String>,
+    pub suspended: bool,
 }
 
 pub trait GitHubApi: Send + Sync {
+    /// Compares the head branch with the base branch, including all commits
+    /// of the head branch. The changed files are read with
+    /// [`Self::list_changed_files`] by the checks that need them.
     fn compare_commits(
         &self,
         request: BranchComparisonRequest,
     ) -> impl Future<Output = Result<BranchComparison, ApiError>> + Send;
 
+    /// Lists the files changed between the merge base and the head branch.
+    /// Fails with [`ApiError::Truncated`] if GitHub does not list all of
+    /// them.
+    fn list_changed_files(
+        &self,
+        request: BranchComparisonRequest,
+    ) -> impl Future<Output = Result<Vec<ChangedFile>, ApiError>> + Send;
+
     fn update_reference(
         &self,
         request: UpdateReferenceRequest,
     ) -> impl Future<Output = Result<(), ApiError>> + Send;
+
+    fn create_commit_status(
+        &self,
+        request: CommitStatusRequest,
+    ) -> impl Future<Output = Result<(), ApiError>> + Send;
+
+    /// Lists the workflow runs of a commit. Only the latest attempt of every
+    /// run is part of the result.
+    fn list_workflow_runs(
+        &self,
+        request: WorkflowRunsRequest,
+    ) -> impl Future<Output = Result<Vec<WorkflowRunSummary>, ApiError>> + Send;
+
+    /// Returns the SHA of the commit the branch points to
+    fn get_branch_head(
+        &self,
+        request: BranchHeadRequest,
+    ) -> impl Future<Output = Result<String, ApiError>> + Send;
+
+    /// Returns the content of a file at the given reference or `None` if the
+    /// file does not exist.
+    fn get_file_content(
+        &self,
+        request: FileContentRequest,
+    ) -> impl Future<Output = Result<Option<String>, ApiError>> + Send;
+
+    fn create_check_run(
+        &self,
+        request: CheckRunRequest,
+    ) -> impl Future<Output = Result<(), ApiError>> + Send;
+
+    fn merge_branch(
+        &self,
+        request: MergeBranchRequest,
+    ) -> impl Future<Output = Result<MergeResult, ApiError>> + Send;
+
+    fn delete_reference(
+        &self,
+        request: DeleteReferenceRequest,
+    ) -> impl Future<Output = Result<(), ApiError>> + Send;
+
+    /// Lists the repositories the installation has access to
+    fn list_installation_repositories(
+        &self,
+    ) -> impl Future<Output = Result<Vec<RepositorySummary>, ApiError>> + Send;
+
+    /// Lists the installations of the app. Requires the `App` authentication
+    /// method.
+    fn list_installations(
+        &self,
+    ) -> impl Future<Output = Result<Vec<InstallationDetails>, ApiError>> + Send;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepositorySummary {
+    pub full_name: String,
+    pub default_branch: String,
 }
 
 pub struct BranchComparisonRequest {
@@ -41,9 +245,53 @@ pub struct BranchComparisonRequest {
     pub head_branch: String,
 }
 
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct BranchComparison {
     pub ahead_by: usize,
     pub behind_by: usize,
+    /// Commits of the head branch that the base branch does not contain,
+    /// oldest first
+    pub commits: Vec<ComparedCommit>,
+}
+
+impl BranchComparison {
+    pub fn unverified_commits(&self) -> impl Iterator<Item = &ComparedCommit> {
+        self.commits.iter().filter(|commit| !commit.verified)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComparedCommit {
+    pub sha: String,
+    pub message: String,
+    pub author: Option<CommitIdentity>,
+    pub committer: Option<CommitIdentity>,
+    /// SHAs of the parent commits, two or more for merge commits
+    pub parents: Vec<String>,
+    /// Whether GitHub verified the signature of the commit
+    pub verified: bool,
+}
+
+/// The author or committer recorded in a commit
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommitIdentity {
+    pub name: String,
+    pub email: String,
+    /// The GitHub account the email belongs to, if any
+    pub login: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangedFile {
+    pub filename: String,
+    /// `added`, `removed`, `modified`, `renamed`, `copied`, `changed` or
+    /// `unchanged`
+    pub status: String,
+    pub additions: u64,
+    pub deletions: u64,
+    pub changes: u64,
+    /// The name before the file was renamed
+    pub previous_filename: Option<String>,
 }
 
 pub struct UpdateReferenceRequest {
@@ -53,20 +301,205 @@ pub struct UpdateReferenceRequest {
     pub force: bool,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitStatusRequest {
+    pub repository_name: String,
+    pub sha1: String,
+    pub state: CommitState,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+pub struct WorkflowRunsRequest {
+    pub repository_name: String,
+    pub head_sha: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkflowRunSummary {
+    pub id: u64,
+    pub workflow_id: u64,
+    pub name: String,
+    pub path: String,
+    pub run_attempt: u32,
+    pub status: String,
+    pub conclusion: Option<String>,
+}
+
+pub struct BranchHeadRequest {
+    pub repository_name: String,
+    pub branch: String,
+}
+
+pub struct FileContentRequest {
+    pub repository_name: String,
+    pub path: String,
+    /// Reads the file from the default branch if not set
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckRunRequest {
+    pub repository_name: String,
+    pub head_sha: String,
+    pub conclusion: CheckConclusion,
+    pub title: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckConclusion {
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeBranchRequest {
+    pub repository_name: String,
+    pub base_branch: String,
+    pub head_branch: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeResult {
+    Merged,
+    Conflict,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteReferenceRequest {
+    pub repository_name: String,
+    pub reference: String,
+}
+
 #[derive(Error, Debug)]
 pub enum ApiError {
-    #[error("{0}")]
-    Authentication(String),
+    #[error("GitHub rejected the credentials: {0}")]
+    Authentication(ErrorDetails),
+
+    #[error("GitHub denied access: {0}")]
+    Authorization(ErrorDetails),
+
+    #[error("Not found: {0}")]
+    RepositoryNotFound(ErrorDetails),
+
+    #[error("Conflict: {0}")]
+    Conflict(ErrorDetails),
+
+    #[error("Validation failed: {0}")]
+    Validation(ErrorDetails),
+
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(ErrorDetails),
+
+    #[error("GitHub is unavailable: {0}")]
+    Server(ErrorDetails),
+
+    #[error("Could not reach GitHub: {0}")]
+    Transport(ErrorDetails),
 
-    #[error("{0}")]
-    Authorization(String),
+    #[error("Unexpected answer from GitHub: {0}")]
+    UnexpectedStatus(ErrorDetails),
 
-    #[error("{0}")]
-    RepositoryNotFound(String),
+    /// GitHub cut off a list at its limit, so the answer is incomplete
+    #[error("Truncated answer from GitHub: {0}")]
+    Truncated(ErrorDetails),
 
-    #[error(transparent)]
-    Serialization(#[from] serde_json::Error),
+    /// A request could not be built or an answer could not be read
+    #[error("Serialization failed: {0}")]
+    Serialization(ErrorDetails),
 
     #[error("Unspecific error")]
     Unspecific,
 }
+
+impl ApiError {
+    /// Whether the same request may succeed later without any change
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ApiError::RateLimited(_) | ApiError::Server(_) | ApiError::Transport(_)
+        )
+    }
+
+    /// What GitHub answered, if the request reached it
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            ApiError::Authentication(details)
+            | ApiError::Authorization(details)
+            | ApiError::RepositoryNotFound(details)
+            | ApiError::Conflict(details)
+            | ApiError::Validation(details)
+            | ApiError::RateLimited(details)
+            | ApiError::Server(details)
+            | ApiError::Transport(details)
+            | ApiError::UnexpectedStatus(details)
+            | ApiError::Truncated(details)
+            | ApiError::Serialization(details) => Some(details),
+            ApiError::Unspecific => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(error: serde_json::Error) -> Self {
+        ApiError::Serialization(ErrorDetails::from_message(error.to_string()))
+    }
+}
+
+/// The answer of GitHub to a failed request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorDetails {
+    /// `None` if the request did not get an answer
+    pub status: Option<StatusCode>,
+    /// The `X-GitHub-Request-Id` header, which GitHub support asks for
+    pub request_id: Option<String>,
+    pub message: Option<String>,
+    /// The entries of the `errors` array, e.g. the fields that are invalid
+    pub errors: Vec<String>,
+    pub documentation_url: Option<String>,
+}
+
+impl ErrorDetails {
+    pub fn from_message(message: impl Into<String>) -> Self {
+        Self {
+            message: Some(message.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_message(self, message: impl Into<String>) -> Self {
+        Self {
+            message: Some(message.into()),
+            ..self
+        }
+    }
+}
+
+impl Display for ErrorDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message.as_deref().unwrap_or("no message"))?;
+
+        if !self.errors.is_empty() {
+            write!(f, " ({})", self.errors.join("; "))?;
+        }
+        if let Some(status) = self.status {
+            write!(f, ", status {}", status.as_u16())?;
+        }
+        if let Some(request_id) = &self.request_id {
+            write!(f, ", request {request_id}")?;
+        }
+        if let Some(documentation_url) = &self.documentation_url {
+            write!(f, ", see {documentation_url}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::WorkflowRunSummary;
+use crate::github_api::WorkflowRunsRequest;
+use reqwest::Client;
+use serde::Deserialize;
+use std::ops::Deref;
+use tracing::instrument;
+
+use super::Token;
+use super::endpoint::{ApiEndpoint, GitHubRequestExt};
+use super::error_handling::IntoErrorHandlingRequest;
+
+/// Maximum page size of the API. More runs for a single commit are not
+/// expected.
+const PER_PAGE: usize = 100;
+
+pub struct GithubActionsRestApi<'a, C> {
+    token: &'a Token,
+    endpoint: &'a ApiEndpoint,
+    client: C,
+}
+
+impl<'a, C: Deref<Target = Client>> GithubActionsRestApi<'a, C> {
+    pub fn new(token: &'a Token, endpoint: &'a ApiEndpoint, client: C) -> Self {
+        Self {
+            token,
+            endpoint,
+            client,
+        }
+    }
+}
+
+impl<C: Deref<Target = Client>> GithubActionsRestApi<'_, C> {
+    #[instrument(skip_all, fields(request))]
+    pub async fn list_workflow_runs(
+        &self,
+        request: WorkflowRunsRequest,
+    ) -> Result<Vec<WorkflowRunSummary>, ApiError> {
+        let runs_url = format!(
+            "{}/repos/{}/actions/runs?head_sha={}&per_page={PER_PAGE}",
+            self.endpoint.base_url, request.repository_name, request.head_sha
+        );
+
+        let response = self
+            .client
+            .get(&runs_url)
+            .github_headers(self.endpoint)
+            .with_access_token(self.token)
+            .send()
+            .await?;
+
+        if response.is_success() {
+            response
+                .json::<WorkflowRunsRest>()
+                .await
+                .map(|runs| runs.workflow_runs.into_iter().map(Into::into).collect())
+        } else {
+            Err(response.into_error().await)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowRunsRest {
+    workflow_runs: Vec<WorkflowRunRest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowRunRest {
+    id: u64,
+    workflow_id: u64,
+    name: Option<String>,
+    path: String,
+    run_attempt: u32,
+    status: Option<String>,
+    conclusion: Option<String>,
+}
+
+impl From<WorkflowRunRest> for WorkflowRunSummary {
+    fn from(run: WorkflowRunRest) -> Self {
+        WorkflowRunSummary {
+            id: run.id,
+            workflow_id: run.workflow_id,
+            name: run.name.unwrap_or_default(),
+            path: run.path,
+            run_attempt: run.run_attempt,
+            status: run.status.unwrap_or_default(),
+            conclusion: run.conclusion,
+        }
+    }
+}
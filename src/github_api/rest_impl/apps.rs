@@ -0,0 +1,81 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::collections::BTreeMap;
+
+use crate::github_api::ApiError;
+use crate::github_api::InstallationDetails;
+use serde::Deserialize;
+use tracing::instrument;
+
+use super::{AppCredentials, GitHubRestApiProvider};
+
+const PAGE_SIZE: usize = 100;
+
+impl GitHubRestApiProvider {
+    /// Lists the installations as the app, falling back to the next key like
+    /// every request of the app
+    #[instrument(skip_all)]
+    pub(super) async fn list_installations(
+        &self,
+        credentials: &AppCredentials,
+    ) -> Result<Vec<InstallationDetails>, ApiError> {
+        let url = format!("{}/app/installations", self.endpoint.base_url);
+        let mut installations = Vec::new();
+
+        for page in 1.. {
+            let response = self
+                .send_as_app(credentials, |client| {
+                    client
+                        .get(&url)
+                        .query(&[("per_page", PAGE_SIZE), ("page", page)])
+                })
+                .await?;
+
+            if !response.is_success() {
+                return Err(response.into_error().await);
+            }
+
+            let page: Vec<InstallationRest> = response.json().await?;
+            let count = page.len();
+            installations.extend(page.into_iter().map(InstallationDetails::from));
+
+            if count < PAGE_SIZE {
+                break;
+            }
+        }
+
+        Ok(installations)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallationRest {
+    id: usize,
+    account: AccountRest,
+    #[serde(default)]
+    permissions: BTreeMap<String, String>,
+    suspended_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountRest {
+    login: String,
+}
+
+impl From<InstallationRest> for InstallationDetails {
+    fn from(installation: InstallationRest) -> Self {
+        InstallationDetails {
+            id: installation.id,
+            account: installation.account.login,
+            permissions: installation.permissions,
+            suspended: installation.suspended_at.is_some(),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::CheckConclusion;
+use crate::github_api::CheckRunRequest;
+use reqwest::Client;
+use serde::Serialize;
+use std::ops::Deref;
+use tracing::instrument;
+
+use super::Token;
+use super::endpoint::{ApiEndpoint, GitHubRequestExt};
+use super::error_handling::IntoErrorHandlingRequest;
+
+const CHECK_RUN_NAME: &str = "koritsu";
+
+pub struct GithubChecksRestApi<'a, C> {
+    token: &'a Token,
+    endpoint: &'a ApiEndpoint,
+    client: C,
+}
+
+impl<'a, C: Deref<Target = Client>> GithubChecksRestApi<'a, C> {
+    pub fn new(token: &'a Token, endpoint: &'a ApiEndpoint, client: C) -> Self {
+        Self {
+            token,
+            endpoint,
+            client,
+        }
+    }
+}
+
+impl<C: Deref<Target = Client>> GithubChecksRestApi<'_, C> {
+    #[instrument(skip_all, fields(request))]
+    pub async fn create_check_run(&self, request: CheckRunRequest) -> Result<(), ApiError> {
+        let check_runs_url = format!(
+            "{}/repos/{}/check-runs",
+            self.endpoint.base_url, request.repository_name
+        );
+
+        let request_body = serde_json::to_vec(&CheckRunRest {
+            name: CHECK_RUN_NAME,
+            head_sha: request.head_sha,
+            status: "completed",
+            conclusion: request.conclusion.into(),
+            output: CheckRunOutputRest {
+                title: request.title,
+                summary: request.summary,
+            },
+        })?;
+
+        let response = self
+            .client
+            .post(&check_runs_url)
+            .body(request_body)
+            .github_headers(self.endpoint)
+            .with_access_token(self.token)
+            .send()
+            .await?;
+
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(response.into_error().await)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CheckRunRest {
+    name: &'static str,
+    head_sha: String,
+    status: &'static str,
+    conclusion: &'static str,
+    output: CheckRunOutputRest,
+}
+
+#[derive(Debug, Serialize)]
+struct CheckRunOutputRest {
+    title: String,
+    summary: String,
+}
+
+impl From<CheckConclusion> for &'static str {
+    fn from(conclusion: CheckConclusion) -> Self {
+        match conclusion {
+            CheckConclusion::Success => "success",
+            CheckConclusion::Failure => "failure",
+        }
+    }
+}
@@ -10,86 +10,225 @@
 use crate::github_api::ApiError;
 use crate::github_api::BranchComparison;
 use crate::github_api::BranchComparisonRequest;
+use crate::github_api::ChangedFile;
+use crate::github_api::CommitIdentity;
+use crate::github_api::ComparedCommit;
+use crate::github_api::ErrorDetails;
 use reqwest::Client;
-use reqwest::StatusCode;
 use serde::Deserialize;
 use std::ops::Deref;
 use tracing::instrument;
 
-use super::BasicError;
 use super::Token;
-use super::error_handling::IntoErrorHandlingRequest;
+use super::endpoint::{ApiEndpoint, GitHubRequestExt};
+use super::error_handling::{ErrorHandlingResponse, IntoErrorHandlingRequest};
+
+/// The most commits GitHub lists per page of a comparison
+const PER_PAGE: usize = 100;
+
+/// The most changed files GitHub lists for a comparison. Longer lists are cut
+/// off without notice.
+const MAX_FILES: usize = 300;
 
 pub struct GithubCommitsRestApi<'a, C> {
     token: &'a Token,
-    base_url: &'a str,
+    endpoint: &'a ApiEndpoint,
     client: C,
 }
 
 impl<'a, C: Deref<Target = Client>> GithubCommitsRestApi<'a, C> {
-    pub fn new(token: &'a Token, base_url: &'a str, client: C) -> Self {
+    pub fn new(token: &'a Token, endpoint: &'a ApiEndpoint, client: C) -> Self {
         Self {
             token,
-            base_url,
+            endpoint,
             client,
         }
     }
 }
 
 impl<C: Deref<Target = Client>> GithubCommitsRestApi<'_, C> {
+    /// Follows the `Link` headers through all pages. Every page repeats the
+    /// distance of both branches and lists the next commits.
     #[instrument(skip_all, fields(request))]
     pub async fn compare_commits(
         &self,
         request: BranchComparisonRequest,
     ) -> Result<BranchComparison, ApiError> {
-        let compare_url = format!(
-            "{}/repos/{}/compare/{}...{}",
-            self.base_url, request.repository_name, request.base_branch, request.head_branch
-        );
+        let mut comparison = BranchComparison::default();
+        let mut url = Some(self.compare_url(&request, PER_PAGE));
+
+        while let Some(page_url) = url {
+            let response = self.get(&page_url).await?;
+            url = response.next_page();
+
+            let page: BranchComparisonRest = response.json().await?;
+            comparison.ahead_by = page.ahead_by;
+            comparison.behind_by = page.behind_by;
+            comparison
+                .commits
+                .extend(page.commits.into_iter().map(Into::into));
+        }
+
+        Ok(comparison)
+    }
+
+    /// Reads only the changed files of a comparison. GitHub lists them on the
+    /// first page only, so a page with a single commit is enough.
+    #[instrument(skip_all, fields(request))]
+    pub async fn list_changed_files(
+        &self,
+        request: &BranchComparisonRequest,
+    ) -> Result<Vec<ChangedFile>, ApiError> {
+        let page: BranchComparisonRest = self
+            .get(&self.compare_url(request, 1))
+            .await?
+            .json()
+            .await?;
 
+        complete_files(request, page.files)
+    }
+
+    fn compare_url(&self, request: &BranchComparisonRequest, per_page: usize) -> String {
+        format!(
+            "{}/repos/{}/compare/{}...{}?per_page={per_page}",
+            self.endpoint.base_url,
+            request.repository_name,
+            request.base_branch,
+            request.head_branch
+        )
+    }
+
+    async fn get(&self, url: &str) -> Result<ErrorHandlingResponse, ApiError> {
         let response = self
             .client
-            .get(&compare_url)
-            .header("User-Agent", "koritsu-app")
-            .header("Accept", "application/vnd.github+json")
-            .header("X-GitHub-Api-Version", "2022-11-28")
-            .bearer_auth(self.token)
-            .with_error_handling()
+            .get(url)
+            .github_headers(self.endpoint)
+            .with_access_token(self.token)
             .send()
             .await?;
 
         if response.is_success() {
-            response
-                .json::<BranchComparisonRest>()
-                .await
-                .map(Into::into)
+            Ok(response)
         } else {
-            let status = response.status();
-            let basic_error: BasicError = response.json().await?;
-
-            match status {
-                StatusCode::NOT_FOUND => Err(ApiError::RepositoryNotFound(
-                    basic_error
-                        .message
-                        .unwrap_or_else(|| format!("Repository {} not found", compare_url)),
-                )),
-                _ => Err(ApiError::Unspecific),
-            }
+            Err(response.into_error().await)
         }
     }
 }
 
+/// Refuses lists GitHub cut off, because a policy must not pass because of the
+/// files it did not see
+fn complete_files(
+    request: &BranchComparisonRequest,
+    files: Vec<ChangedFileRest>,
+) -> Result<Vec<ChangedFile>, ApiError> {
+    if files.len() > MAX_FILES {
+        return Err(ApiError::Truncated(ErrorDetails::from_message(format!(
+            "GitHub lists only the first {MAX_FILES} changed files of {}...{}",
+            request.base_branch, request.head_branch
+        ))));
+    }
+
+    Ok(files.into_iter().map(Into::into).collect())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BranchComparisonRest {
     pub ahead_by: usize,
     pub behind_by: usize,
+    #[serde(default)]
+    pub commits: Vec<ComparedCommitRest>,
+    #[serde(default)]
+    pub files: Vec<ChangedFileRest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ComparedCommitRest {
+    pub sha: String,
+    pub commit: CommitDetailsRest,
+    /// The GitHub accounts of the author and the committer, if GitHub knows
+    /// their emails
+    pub author: Option<AccountRest>,
+    pub committer: Option<AccountRest>,
+    #[serde(default)]
+    pub parents: Vec<ParentRest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommitDetailsRest {
+    pub message: String,
+    pub author: Option<GitIdentityRest>,
+    pub committer: Option<GitIdentityRest>,
+    pub verification: Option<VerificationRest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitIdentityRest {
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountRest {
+    pub login: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ParentRest {
+    pub sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerificationRest {
+    pub verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangedFileRest {
+    pub filename: String,
+    pub status: String,
+    pub additions: u64,
+    pub deletions: u64,
+    pub changes: u64,
+    pub previous_filename: Option<String>,
+}
+
+impl From<ComparedCommitRest> for ComparedCommit {
+    fn from(api_response: ComparedCommitRest) -> Self {
+        let identity = |identity: Option<GitIdentityRest>, account: Option<AccountRest>| {
+            identity.map(|identity| CommitIdentity {
+                name: identity.name,
+                email: identity.email,
+                login: account.map(|account| account.login),
+            })
+        };
+
+        ComparedCommit {
+            sha: api_response.sha,
+            message: api_response.commit.message,
+            author: identity(api_response.commit.author, api_response.author),
+            committer: identity(api_response.commit.committer, api_response.committer),
+            parents: api_response
+                .parents
+                .into_iter()
+                .map(|parent| parent.sha)
+                .collect(),
+            verified: api_response
+                .commit
+                .verification
+                .is_some_and(|verification| verification.verified),
+        }
+    }
 }
 
-impl From<BranchComparisonRest> for BranchComparison {
-    fn from(api_response: BranchComparisonRest) -> Self {
-        BranchComparison {
-            ahead_by: api_response.ahead_by,
-            behind_by: api_response.behind_by,
+impl From<ChangedFileRest> for ChangedFile {
+    fn from(api_response: ChangedFileRest) -> Self {
+        ChangedFile {
+            filename: api_response.filename,
+            status: api_response.status,
+            additions: api_response.additions,
+            deletions: api_response.deletions,
+            changes: api_response.changes,
+            previous_filename: api_response.previous_filename,
         }
     }
 }
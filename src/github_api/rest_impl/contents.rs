@@ -0,0 +1,106 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::ErrorDetails;
+use crate::github_api::FileContentRequest;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::Client;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::ops::Deref;
+use tracing::instrument;
+
+use super::Token;
+use super::endpoint::{ApiEndpoint, GitHubRequestExt};
+use super::error_handling::IntoErrorHandlingRequest;
+
+pub struct GithubContentsRestApi<'a, C> {
+    token: &'a Token,
+    endpoint: &'a ApiEndpoint,
+    client: C,
+}
+
+impl<'a, C: Deref<Target = Client>> GithubContentsRestApi<'a, C> {
+    pub fn new(token: &'a Token, endpoint: &'a ApiEndpoint, client: C) -> Self {
+        Self {
+            token,
+            endpoint,
+            client,
+        }
+    }
+}
+
+impl<C: Deref<Target = Client>> GithubContentsRestApi<'_, C> {
+    #[instrument(skip_all, fields(request))]
+    pub async fn get_file_content(
+        &self,
+        request: FileContentRequest,
+    ) -> Result<Option<String>, ApiError> {
+        let contents_url = format!(
+            "{}/repos/{}/contents/{}",
+            self.endpoint.base_url, request.repository_name, request.path
+        );
+
+        let mut http_request = self.client.get(&contents_url);
+        if let Some(reference) = &request.reference {
+            http_request = http_request.query(&[("ref", reference)]);
+        }
+
+        let response = http_request
+            .github_headers(self.endpoint)
+            .with_access_token(self.token)
+            .send()
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => {
+                let details = response.error_details();
+                let file: FileContentRest = response.json().await?;
+                file.decode(details).map(Some)
+            }
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(response.into_error().await),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FileContentRest {
+    content: String,
+    encoding: String,
+}
+
+impl FileContentRest {
+    /// `details` describe the answer the file was read from
+    fn decode(self, details: ErrorDetails) -> Result<String, ApiError> {
+        if self.encoding != "base64" {
+            tracing::error!(encoding = self.encoding, "Unsupported content encoding");
+            return Err(ApiError::Serialization(details.with_message(format!(
+                "Unsupported content encoding {}",
+                self.encoding
+            ))));
+        }
+
+        // GitHub breaks the encoded content into lines
+        let encoded: String = self.content.split_whitespace().collect();
+
+        STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| {
+                tracing::error!("File content is not valid UTF-8 text");
+                ApiError::Serialization(
+                    details.with_message("File content is not valid UTF-8 text"),
+                )
+            })
+    }
+}
@@ -0,0 +1,57 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+
+/// The REST API of github.com or of a GitHub Enterprise Server instance
+pub struct ApiEndpoint {
+    /// For GitHub Enterprise Server this includes the `/api/v3` prefix
+    pub base_url: String,
+    /// Sent as `X-GitHub-Api-Version` header. Older GitHub Enterprise Server
+    /// releases reject the header, therefore it is optional.
+    pub api_version: Option<String>,
+}
+
+/// Adds the headers every request to the GitHub REST API needs
+pub trait GitHubRequestExt {
+    fn github_headers(self, endpoint: &ApiEndpoint) -> Self;
+}
+
+impl GitHubRequestExt for RequestBuilder {
+    fn github_headers(self, endpoint: &ApiEndpoint) -> Self {
+        let request = self
+            .header("User-Agent", "koritsu-app")
+            .header("Accept", "application/vnd.github+json");
+
+        match &endpoint.api_version {
+            Some(api_version) => request.header("X-GitHub-Api-Version", api_version),
+            None => request,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetaRest {
+    /// Only reported by GitHub Enterprise Server
+    pub installed_version: Option<String>,
+}
+
+/// GitHub Enterprise Server supports the `X-GitHub-Api-Version` header since
+/// release 3.9.
+pub fn supports_api_version_header(installed_version: &str) -> bool {
+    let mut parts = installed_version
+        .split('.')
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+
+    (major, minor) >= (3, 9)
+}
@@ -7,23 +7,227 @@
  * received a copy of the license along with this program.
  */
 
-use crate::github_api::ApiError;
-use reqwest::StatusCode;
-use serde::de::DeserializeOwned;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
-pub struct ErrorHandlingRequest(reqwest::RequestBuilder);
+use super::Token;
+use super::rate_limit::{RateLimit, RateLimitBudget};
+use super::response_cache::CacheKey;
+use crate::github_api::{ApiError, ErrorDetails};
+use rand::Rng;
+use reqwest::{Method, StatusCode, header::ACCEPT};
+use serde::{Deserialize, de::DeserializeOwned};
+
+/// Repeats requests that failed because GitHub could not be reached in time
+/// or had a temporary server error, waiting exponentially longer with some
+/// jitter in between. Only requests that read or that are marked as safe to
+/// repeat are retried.
+struct RetryPolicy {
+    max_retries: u32,
+    /// How often a request waits for an exceeded rate limit before the
+    /// answer of GitHub is passed on
+    max_rate_limit_pauses: u32,
+    /// How long a request waits for the rate limit in total. Covers the
+    /// pauses of secondary rate limits and most of the hour a primary rate
+    /// limit lasts, longer waits fail with `ApiError::RateLimited`.
+    max_rate_limit_wait: Duration,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+const RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_retries: 3,
+    max_rate_limit_pauses: 5,
+    max_rate_limit_wait: Duration::from_secs(15 * 60),
+    initial_delay: Duration::from_millis(200),
+    max_delay: Duration::from_secs(5),
+};
+
+impl RetryPolicy {
+    /// The delay doubles with every retry and a random part of up to half of
+    /// it is left out, so concurrent requests do not retry in lockstep.
+    fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+
+    async fn back_off(&self, retry: &mut u32, problem: String) {
+        let delay = self.delay(*retry);
+        *retry += 1;
+        tracing::warn!(
+            problem,
+            retry,
+            ?delay,
+            "Request failed temporarily, retrying"
+        );
+        tokio::time::sleep(delay).await;
+    }
+
+    fn is_transient_status(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+
+    /// Other errors like invalid requests or redirect loops fail the same
+    /// way again
+    fn is_transient_error(error: &reqwest::Error) -> bool {
+        error.is_connect() || error.is_timeout()
+    }
+}
+
+pub struct ErrorHandlingRequest {
+    request: reqwest::RequestBuilder,
+    token: Option<Token>,
+    rate_limit: Option<Arc<RateLimitBudget>>,
+    safe_to_repeat: bool,
+}
 
 impl ErrorHandlingRequest {
+    /// Allows retrying a write request that has the same effect when it is
+    /// sent twice, also if the first one succeeded but its answer got lost.
+    /// Requests that only read are always retried.
+    pub fn safe_to_repeat(mut self, safe_to_repeat: bool) -> Self {
+        self.safe_to_repeat = safe_to_repeat;
+        self
+    }
+
+    /// Counts the request against the budget and holds it back while the
+    /// budget is exhausted.
+    pub fn with_rate_limit(mut self, rate_limit: Arc<RateLimitBudget>) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Keeps the request out of the budget, e.g. GraphQL queries that GitHub
+    /// counts against a limit of their own.
+    pub fn without_rate_limit(mut self) -> Self {
+        self.rate_limit = None;
+        self
+    }
+
     pub async fn send(self) -> Result<ErrorHandlingResponse, ApiError> {
-        self.0
-            .send()
-            .await
+        let (client, request) = self.request.build_split();
+        let mut request = request
+            .inspect_err(|error| tracing::error!(%error, "Building request failed"))
+            .map_err(|error| {
+                ApiError::Serialization(ErrorDetails::from_message(error.to_string()))
+            })?;
+
+        let cache = self
+            .token
+            .as_ref()
+            .filter(|_| request.method() == Method::GET)
+            .and_then(|token| Some((token, token.installation_id()?, token.response_cache()?)))
+            .map(|(token, installation_id, response_cache)| {
+                let key = CacheKey {
+                    installation_id,
+                    scope: token.scope().cloned(),
+                    url: request.url().to_string(),
+                    accept: request
+                        .headers()
+                        .get(ACCEPT)
+                        .and_then(|accept| accept.to_str().ok())
+                        .map(str::to_owned),
+                };
+                let cached = response_cache.get(&key);
+                if let Some(cached) = &cached {
+                    cached.add_validators(request.headers_mut());
+                }
+                (response_cache, key, cached)
+            });
+
+        let retries = if self.safe_to_repeat || is_read_only(request.method()) {
+            RETRY_POLICY.max_retries
+        } else {
+            0
+        };
+
+        let mut retry = 0;
+        let mut pauses = 0;
+        let rate_limit_deadline = Instant::now() + RETRY_POLICY.max_rate_limit_wait;
+        let response = loop {
+            if let Some(rate_limit) = &self.rate_limit {
+                rate_limit
+                    .wait(rate_limit_deadline.saturating_duration_since(Instant::now()))
+                    .await?;
+            }
+
+            let Some(attempt) = request.try_clone() else {
+                break client.execute(request).await;
+            };
+
+            match client.execute(attempt).await {
+                Ok(response) => {
+                    let now = SystemTime::now();
+                    let rate_limit = RateLimit::from_headers(response.headers());
+                    let exceeded = rate_limit.is_exceeded(response.status());
+                    if let Some(budget) = &self.rate_limit {
+                        budget.update(rate_limit, exceeded, now);
+                    }
+
+                    // GitHub did not process the request, so any request can
+                    // be repeated once the limit allows it. Longer pauses pass
+                    // the answer on as a rate limit error.
+                    let pause = rate_limit.pause(now);
+                    if exceeded
+                        && pauses < RETRY_POLICY.max_rate_limit_pauses
+                        && Instant::now() + pause <= rate_limit_deadline
+                    {
+                        pauses += 1;
+                        tracing::warn!(?pause, "GitHub rate limit exceeded, repeating request");
+                        tokio::time::sleep(pause).await;
+                    } else if RetryPolicy::is_transient_status(response.status()) && retry < retries
+                    {
+                        RETRY_POLICY
+                            .back_off(&mut retry, response.status().to_string())
+                            .await;
+                    } else {
+                        break Ok(response);
+                    }
+                }
+                Err(error) if RetryPolicy::is_transient_error(&error) && retry < retries => {
+                    RETRY_POLICY.back_off(&mut retry, error.to_string()).await;
+                }
+                Err(error) => break Err(error),
+            }
+        };
+
+        let mut response = response
             .inspect_err(|error| tracing::error!(%error, "Sending request failed"))
-            .map_err(|_| ApiError::Unspecific)
-            .map(ErrorHandlingResponse)
+            .map_err(|error| ApiError::Transport(ErrorDetails::from_message(error.to_string())))?;
+
+        if let Some((response_cache, key, cached)) = cache {
+            response = response_cache.handle(key, cached, response).await?;
+        }
+
+        if let Some(token) = self.token
+            && response.status() == StatusCode::UNAUTHORIZED
+        {
+            tracing::warn!(owner = ?token.owner(), "GitHub rejected the access token");
+            token.reject();
+        }
+
+        Ok(ErrorHandlingResponse(response))
     }
 }
 
+/// `PUT` and `DELETE` are idempotent too, but repeating them may still turn a
+/// success into an error, e.g. deleting a reference that the lost first
+/// attempt already deleted
+fn is_read_only(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
 pub struct ErrorHandlingResponse(reqwest::Response);
 
 impl ErrorHandlingResponse {
@@ -35,18 +239,56 @@ impl ErrorHandlingResponse {
         self.status().is_success()
     }
 
+    /// The `X-GitHub-Request-Id` header, which GitHub support asks for
+    pub fn request_id(&self) -> Option<String> {
+        self.0
+            .headers()
+            .get("x-github-request-id")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    }
+
+    /// The status and request id of the answer, for errors that GitHub did
+    /// not describe itself
+    pub fn error_details(&self) -> ErrorDetails {
+        ErrorDetails {
+            status: Some(self.status()),
+            request_id: self.request_id(),
+            ..ErrorDetails::default()
+        }
+    }
+
+    /// The URL of the next page from the `Link` header of a paginated answer
+    pub fn next_page(&self) -> Option<String> {
+        let links = self.0.headers().get("link")?.to_str().ok()?;
+
+        links.split(',').find_map(|link| {
+            let (url, parameters) = link.split_once(';')?;
+            parameters
+                .split(';')
+                .any(|parameter| parameter.trim() == "rel=\"next\"")
+                .then(|| url.trim().trim_matches(['<', '>']).to_owned())
+        })
+    }
+
     pub async fn json<T: DeserializeOwned>(self) -> Result<T, ApiError> {
+        let details = self.error_details();
+
         if !self.is_json_content_type() {
             let content = self
                 .0
                 .text()
                 .await
                 .inspect_err(|error| tracing::error!(%error, "Retrieving text content failed"))
-                .map_err(|_| ApiError::Unspecific)?;
+                .map_err(|error| {
+                    ApiError::Transport(details.clone().with_message(error.to_string()))
+                })?;
 
             tracing::error!(content, "Content-Type is not valid for JSON");
 
-            return Err(ApiError::Unspecific);
+            return Err(ApiError::Serialization(
+                details.with_message("Content-Type is not valid for JSON"),
+            ));
         }
 
         let body = self
@@ -54,11 +296,58 @@ impl ErrorHandlingResponse {
             .bytes()
             .await
             .inspect_err(|error| tracing::error!(%error, "Retrieving byte content failed"))
-            .map_err(|_| ApiError::Unspecific)?;
+            .map_err(|error| {
+                ApiError::Transport(details.clone().with_message(error.to_string()))
+            })?;
 
         serde_json::from_slice(&body)
             .inspect_err(|error| tracing::error!(%error, "Deserializing JSON content failed"))
-            .map_err(|_| ApiError::Unspecific)
+            .map_err(|error| ApiError::Serialization(details.with_message(error.to_string())))
+    }
+
+    /// Classifies the failed request by its status code and collects what
+    /// GitHub said about it.
+    pub async fn into_error(self) -> ApiError {
+        let status = self.status();
+        let rate_limit = RateLimit::from_headers(self.0.headers());
+        let request_id = self.request_id();
+
+        let body: GitHubErrorRest = if self.is_json_content_type() {
+            self.0
+                .bytes()
+                .await
+                .ok()
+                .and_then(|body| serde_json::from_slice(&body).ok())
+                .unwrap_or_default()
+        } else {
+            GitHubErrorRest::default()
+        };
+
+        let details = ErrorDetails {
+            status: Some(status),
+            request_id,
+            message: body.message,
+            errors: body
+                .errors
+                .into_iter()
+                .map(GitHubErrorEntryRest::describe)
+                .collect(),
+            documentation_url: body.documentation_url,
+        };
+
+        match status {
+            StatusCode::UNAUTHORIZED => ApiError::Authentication(details),
+            StatusCode::FORBIDDEN if rate_limit.is_exceeded(status) => {
+                ApiError::RateLimited(details)
+            }
+            StatusCode::FORBIDDEN => ApiError::Authorization(details),
+            StatusCode::NOT_FOUND => ApiError::RepositoryNotFound(details),
+            StatusCode::CONFLICT => ApiError::Conflict(details),
+            StatusCode::UNPROCESSABLE_ENTITY => ApiError::Validation(details),
+            StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited(details),
+            status if status.is_server_error() => ApiError::Server(details),
+            _ => ApiError::UnexpectedStatus(details),
+        }
     }
 
     fn is_json_content_type(&self) -> bool {
@@ -73,12 +362,76 @@ impl ErrorHandlingResponse {
     }
 }
 
+/// The error document GitHub answers failed requests with
+#[derive(Debug, Default, Deserialize)]
+struct GitHubErrorRest {
+    message: Option<String>,
+    #[serde(default)]
+    errors: Vec<GitHubErrorEntryRest>,
+    documentation_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum GitHubErrorEntryRest {
+    Message(String),
+    Field {
+        resource: Option<String>,
+        field: Option<String>,
+        code: Option<String>,
+        message: Option<String>,
+    },
+}
+
+impl GitHubErrorEntryRest {
+    fn describe(self) -> String {
+        match self {
+            GitHubErrorEntryRest::Message(message) => message,
+            GitHubErrorEntryRest::Field {
+                message: Some(message),
+                ..
+            } => message,
+            GitHubErrorEntryRest::Field {
+                resource,
+                field,
+                code,
+                message: None,
+            } => {
+                let subject = [resource, field].into_iter().flatten().collect::<Vec<_>>();
+                format!(
+                    "{} {}",
+                    subject.join("."),
+                    code.as_deref().unwrap_or("invalid")
+                )
+            }
+        }
+    }
+}
+
 pub trait IntoErrorHandlingRequest {
     fn with_error_handling(self) -> ErrorHandlingRequest;
+
+    /// Authenticates the request with an access token, which is rejected if
+    /// GitHub answers `401 Unauthorized`.
+    fn with_access_token(self, token: &Token) -> ErrorHandlingRequest;
 }
 
 impl IntoErrorHandlingRequest for reqwest::RequestBuilder {
     fn with_error_handling(self) -> ErrorHandlingRequest {
-        ErrorHandlingRequest(self)
+        ErrorHandlingRequest {
+            request: self,
+            token: None,
+            rate_limit: None,
+            safe_to_repeat: false,
+        }
+    }
+
+    fn with_access_token(self, token: &Token) -> ErrorHandlingRequest {
+        ErrorHandlingRequest {
+            request: self.bearer_auth(token),
+            token: Some(token.clone()),
+            rate_limit: Some(token.rate_limit()),
+            safe_to_repeat: false,
+        }
     }
 }
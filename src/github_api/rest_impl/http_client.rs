@@ -0,0 +1,88 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy};
+use thiserror::Error;
+
+use crate::HttpClientConfig;
+
+#[derive(Error, Debug)]
+pub enum HttpClientError {
+    #[error("Could not read {0}")]
+    UnreadableFile(PathBuf, #[source] io::Error),
+
+    #[error("{0} does not contain PEM encoded certificates")]
+    InvalidCaCertificate(PathBuf, #[source] Option<reqwest::Error>),
+
+    #[error("The client certificate {0} can not be used with its key")]
+    InvalidClientCertificate(PathBuf, #[source] reqwest::Error),
+
+    #[error("The proxy {0} can not be used")]
+    InvalidProxy(String, #[source] reqwest::Error),
+
+    #[error("The HTTP client can not be created")]
+    Build(#[source] reqwest::Error),
+}
+
+/// Creates the client for all requests to GitHub
+pub fn build_client(config: &HttpClientConfig) -> Result<Client, HttpClientError> {
+    let mut builder = Client::builder()
+        .connect_timeout(config.connect_timeout)
+        .timeout(config.request_timeout)
+        .pool_idle_timeout(config.pool_idle_timeout)
+        .pool_max_idle_per_host(config.pool_max_idle_per_host);
+
+    // A configured proxy replaces the one from the environment
+    if let Some(url) = &config.proxy {
+        let proxy = Proxy::all(url)
+            .map_err(|error| HttpClientError::InvalidProxy(url.clone(), error))?
+            .no_proxy(NoProxy::from_string(&config.no_proxy.join(",")));
+        builder = builder.proxy(proxy);
+    }
+
+    for file in &config.ca_certificate_files {
+        for certificate in read_certificates(file)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let Some(client_certificate) = &config.client_certificate {
+        let certificate = read(&client_certificate.certificate_file)?;
+        let key = read(&client_certificate.key_file)?;
+        let identity = Identity::from_pkcs8_pem(&certificate, &key).map_err(|error| {
+            HttpClientError::InvalidClientCertificate(
+                client_certificate.certificate_file.clone(),
+                error,
+            )
+        })?;
+        builder = builder.identity(identity);
+    }
+
+    builder.build().map_err(HttpClientError::Build)
+}
+
+fn read_certificates(file: &Path) -> Result<Vec<Certificate>, HttpClientError> {
+    match Certificate::from_pem_bundle(&read(file)?) {
+        Ok(certificates) if !certificates.is_empty() => Ok(certificates),
+        Ok(_) => Err(HttpClientError::InvalidCaCertificate(file.to_owned(), None)),
+        Err(error) => Err(HttpClientError::InvalidCaCertificate(
+            file.to_owned(),
+            Some(error),
+        )),
+    }
+}
+
+fn read(file: &Path) -> Result<Vec<u8>, HttpClientError> {
+    fs::read(file).map_err(|error| HttpClientError::UnreadableFile(file.to_owned(), error))
+}
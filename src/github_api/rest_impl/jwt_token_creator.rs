@@ -7,38 +7,93 @@
  * received a copy of the license along with this program.
  */
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
-use rsa::pkcs1::{self, DecodeRsaPrivateKey};
 use rsa::sha2::Sha256;
 use rsa::signature::{RandomizedSigner, SignatureEncoding};
 use rsa::{RsaPrivateKey, pkcs1v15::SigningKey};
 use serde_json::{Error, json};
 
+/// A private key of the GitHub App
+pub struct AppKey {
+    /// Where the key was loaded from
+    pub name: String,
+    /// The SHA-256 fingerprint GitHub shows in the settings of the app
+    pub fingerprint: String,
+    pub private_key: RsaPrivateKey,
+}
+
+struct Signer {
+    name: String,
+    fingerprint: String,
+    signing_key: SigningKey<Sha256>,
+}
+
+/// Signs JWTs for the GitHub App with one of several keys. The first key is
+/// preferred until GitHub rejects it.
 pub struct JwtTokenCreator {
     header: String,
     client_id: String,
-    signing_key: SigningKey<Sha256>,
+    signers: Vec<Signer>,
+    preferred: AtomicUsize,
 }
 
 impl JwtTokenCreator {
-    pub fn new(client_id: String, private_key_pem: &str) -> Result<Self, pkcs1::Error> {
+    pub fn new(client_id: String, keys: Vec<AppKey>) -> Self {
+        assert!(!keys.is_empty(), "the GitHub App needs a private key");
+
         let header = BASE64_URL_SAFE_NO_PAD.encode("{ \"alg\": \"RS256\", \"typ\": \"JWT\" }");
-        let private_key = RsaPrivateKey::from_pkcs1_pem(private_key_pem)?;
-        let signing_key = SigningKey::<Sha256>::new(private_key);
-        Ok(Self {
+        let signers = keys
+            .into_iter()
+            .map(|key| Signer {
+                name: key.name,
+                fingerprint: key.fingerprint,
+                signing_key: SigningKey::<Sha256>::new(key.private_key),
+            })
+            .collect();
+
+        Self {
             header,
-            signing_key,
             client_id,
-        })
+            signers,
+            preferred: AtomicUsize::new(0),
+        }
+    }
+
+    /// Indexes of all keys, starting with the preferred one
+    pub fn keys_by_preference(&self) -> impl Iterator<Item = usize> + use<> {
+        let preferred = self.preferred.load(Ordering::Relaxed);
+        let count = self.signers.len();
+        (0..count).map(move |offset| (preferred + offset) % count)
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.signers.len()
+    }
+
+    pub fn prefer(&self, key: usize) {
+        self.preferred.store(key, Ordering::Relaxed);
+    }
+
+    pub fn is_preferred(&self, key: usize) -> bool {
+        self.preferred.load(Ordering::Relaxed) == key
+    }
+
+    pub fn key_name(&self, key: usize) -> &str {
+        &self.signers[key].name
+    }
+
+    pub fn key_fingerprint(&self, key: usize) -> &str {
+        &self.signers[key].fingerprint
     }
 
-    pub fn build_token(&self) -> Result<String, Error> {
+    pub fn build_token(&self, key: usize) -> Result<String, Error> {
         let header_and_payload = format!("{}.{}", self.header, self.build_payload()?);
 
         let mut rng = rand::thread_rng();
-        let signature = self
+        let signature = self.signers[key]
             .signing_key
             .sign_with_rng(&mut rng, header_and_payload.as_bytes());
 
@@ -0,0 +1,74 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::MergeBranchRequest;
+use crate::github_api::MergeResult;
+use reqwest::Client;
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::ops::Deref;
+use tracing::instrument;
+
+use super::Token;
+use super::endpoint::{ApiEndpoint, GitHubRequestExt};
+use super::error_handling::IntoErrorHandlingRequest;
+
+pub struct GithubMergesRestApi<'a, C> {
+    token: &'a Token,
+    endpoint: &'a ApiEndpoint,
+    client: C,
+}
+
+impl<'a, C: Deref<Target = Client>> GithubMergesRestApi<'a, C> {
+    pub fn new(token: &'a Token, endpoint: &'a ApiEndpoint, client: C) -> Self {
+        Self {
+            token,
+            endpoint,
+            client,
+        }
+    }
+}
+
+impl<C: Deref<Target = Client>> GithubMergesRestApi<'_, C> {
+    #[instrument(skip_all, fields(request))]
+    pub async fn merge_branch(&self, request: MergeBranchRequest) -> Result<MergeResult, ApiError> {
+        let merges_url = format!(
+            "{}/repos/{}/merges",
+            self.endpoint.base_url, request.repository_name
+        );
+
+        let request_body = serde_json::to_vec(&MergeRest {
+            base: request.base_branch,
+            head: request.head_branch,
+        })?;
+
+        let response = self
+            .client
+            .post(&merges_url)
+            .body(request_body)
+            .github_headers(self.endpoint)
+            .with_access_token(self.token)
+            .send()
+            .await?;
+
+        match response.status() {
+            // No content means that the base already contains the head
+            StatusCode::CREATED | StatusCode::NO_CONTENT => Ok(MergeResult::Merged),
+            StatusCode::CONFLICT => Ok(MergeResult::Conflict),
+            _ => Err(response.into_error().await),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MergeRest {
+    base: String,
+    head: String,
+}
@@ -7,8 +7,10 @@
  * received a copy of the license along with this program.
  */
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
-use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use crate::ApplicationConfig;
 
@@ -16,99 +18,478 @@ use super::ApiError;
 use super::AuthenticationMethod;
 use super::BranchComparison;
 use super::BranchComparisonRequest;
+use super::BranchHeadRequest;
+use super::ChangedFile;
+use super::CheckRunRequest;
+use super::CommitStatusRequest;
+use super::DeleteReferenceRequest;
+use super::ErrorDetails;
+use super::FileContentRequest;
 use super::GitHubApi;
 use super::GitHubApiProvider;
+use super::InstallationDetails;
+use super::MergeBranchRequest;
+use super::MergeResult;
+use super::RateLimitStatus;
+use super::RepositorySummary;
+use super::ResponseCacheStats;
+use super::TokenScope;
+use super::WorkflowRunSummary;
+use super::WorkflowRunsRequest;
+use actions::GithubActionsRestApi;
+use apps::InstallationRest;
+use checks::GithubChecksRestApi;
 use commits::GithubCommitsRestApi;
-use error_handling::IntoErrorHandlingRequest;
+use contents::GithubContentsRestApi;
+use endpoint::{ApiEndpoint, GitHubRequestExt, MetaRest, supports_api_version_header};
+use error_handling::{ErrorHandlingResponse, IntoErrorHandlingRequest};
+pub use http_client::HttpClientError;
+use http_client::build_client;
 use jwt_token_creator::JwtTokenCreator;
+use merges::GithubMergesRestApi;
+use private_key::load_private_keys;
+use rate_limit::RateLimits;
+use refs::GithubRefsRestApi;
+use repositories::GithubRepositoriesRestApi;
 use reqwest::Client;
+use reqwest::RequestBuilder;
 use reqwest::StatusCode;
+use response_cache::ResponseCache;
 use serde::Deserialize;
 use serde::Serialize;
+use statuses::GithubStatusesRestApi;
+use token_cache::{AccessToken, InstallationTokenCache, TokenOwner, parse_timestamp};
 use tracing::instrument;
 
+mod actions;
+mod apps;
+mod checks;
 mod commits;
-mod error_handling;
+mod contents;
+pub(super) mod endpoint;
+pub(super) mod error_handling;
+mod http_client;
 mod jwt_token_creator;
+mod merges;
+mod private_key;
+mod rate_limit;
+mod refs;
+mod repositories;
+mod response_cache;
+mod statuses;
+mod token_cache;
 
-pub struct GitHubRestApiProvider {
+/// How long GitHub documents installation access tokens to be valid, used if
+/// the expiry in the response can not be read
+const INSTALLATION_TOKEN_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// The result of authenticating as the GitHub App with a single key
+pub struct KeyCheck {
+    pub name: String,
+    pub fingerprint: String,
+    /// The name of the app or why GitHub did not accept the key
+    pub outcome: Result<String, String>,
+}
+
+/// The private keys the GitHub App signs its JWTs with
+pub struct AppCredentials {
     token_creator: JwtTokenCreator,
+}
+
+pub struct GitHubRestApiProvider {
+    installation_tokens: InstallationTokenCache,
+    rate_limits: RateLimits,
+    response_cache: Arc<ResponseCache>,
     client: Client,
-    base_url: String,
+    endpoint: ApiEndpoint,
 }
 
 impl GitHubRestApiProvider {
     pub fn new(config: &ApplicationConfig) -> Result<Self, Box<dyn Error>> {
-        let private_key_pem = fs::read_to_string(&config.private_key_file)?;
-        let token_creator = JwtTokenCreator::new(config.client_id.clone(), &private_key_pem)?;
-
-        let client = Client::new();
-        let base_url = config.github_base_url.clone();
+        let client = build_client(&config.github_http)?;
+        let endpoint = ApiEndpoint {
+            base_url: config.github_base_url.clone(),
+            api_version: config.github_api_version.clone(),
+        };
 
         Ok(Self {
-            token_creator,
+            installation_tokens: InstallationTokenCache::default(),
+            rate_limits: RateLimits::default(),
+            response_cache: Arc::default(),
             client,
-            base_url,
+            endpoint,
         })
     }
-}
 
-impl GitHubApiProvider for GitHubRestApiProvider {
-    #[instrument(skip_all, fields(auth_method))]
-    async fn get_api(&self, auth_method: AuthenticationMethod) -> Result<impl GitHubApi, ApiError> {
-        let jwt_token = self
-            .token_creator
-            .build_token()
-            .map_err(|e| ApiError::Authentication(e.to_string()))?;
+    /// Asks the server which product and release it is and logs it. Problems
+    /// are only logged, because the server may be temporarily unavailable.
+    pub async fn check_server_version(&self) {
+        match self.server_version().await {
+            Ok(None) => tracing::info!(base_url = self.endpoint.base_url, "Using github.com"),
+            Ok(Some(version)) => {
+                tracing::info!(
+                    base_url = self.endpoint.base_url,
+                    version,
+                    "Using GitHub Enterprise Server"
+                );
+
+                if self.endpoint.api_version.is_some() && !supports_api_version_header(&version) {
+                    tracing::warn!(
+                        version,
+                        "GitHub Enterprise Server does not support the X-GitHub-Api-Version \
+                         header, set github.api_version to an empty string"
+                    );
+                }
+            }
+            Err(error) => tracing::warn!(
+                base_url = self.endpoint.base_url,
+                %error,
+                "Could not determine the GitHub server version"
+            ),
+        }
+    }
+
+    /// Authenticates as the GitHub App with every key on its own. If GitHub
+    /// rejects the preferred key, the first accepted key becomes the
+    /// preferred one.
+    #[instrument(skip_all)]
+    pub async fn check_keys(&self, credentials: &AppCredentials) -> Vec<KeyCheck> {
+        let token_creator = &credentials.token_creator;
+        let url = format!("{}/app", self.endpoint.base_url);
+        let mut checks = Vec::new();
+
+        for key in 0..token_creator.key_count() {
+            let outcome = match token_creator.build_token(key) {
+                Ok(jwt_token) => self.get_app(&url, &jwt_token).await,
+                Err(error) => Err(error.to_string()),
+            };
+
+            checks.push(KeyCheck {
+                name: token_creator.key_name(key).to_owned(),
+                fingerprint: token_creator.key_fingerprint(key).to_owned(),
+                outcome,
+            });
+        }
+
+        let accepted = |key: &usize| checks[*key].outcome.is_ok();
+        if let Some(key) = (0..checks.len()).find(accepted)
+            && !token_creator
+                .keys_by_preference()
+                .next()
+                .is_some_and(|key| accepted(&key))
+        {
+            token_creator.prefer(key);
+        }
+
+        checks
+    }
+
+    async fn get_app(&self, url: &str, jwt_token: &str) -> Result<String, String> {
+        let response = self
+            .client
+            .get(url)
+            .github_headers(&self.endpoint)
+            .bearer_auth(jwt_token)
+            .with_error_handling()
+            .send()
+            .await
+            .map_err(|error| error.to_string())?;
+
+        if response.is_success() {
+            let app: AppRest = response.json().await.map_err(|error| error.to_string())?;
+            Ok(app.name)
+        } else {
+            Err(response.into_error().await.to_string())
+        }
+    }
+
+    /// Sends a request authenticated as the GitHub App. If GitHub rejects the
+    /// JWT with `401 Unauthorized` the request is repeated with the next key,
+    /// and a key that is accepted becomes the preferred one.
+    async fn send_as_app(
+        &self,
+        credentials: &AppCredentials,
+        request: impl Fn(&Client) -> RequestBuilder,
+    ) -> Result<ErrorHandlingResponse, ApiError> {
+        let token_creator = &credentials.token_creator;
+        let mut rejected = None;
+
+        for key in token_creator.keys_by_preference() {
+            let jwt_token = token_creator.build_token(key)?;
 
-        let AuthenticationMethod::AppInstallation { installation_id } = auth_method;
+            let response = request(&self.client)
+                .github_headers(&self.endpoint)
+                .bearer_auth(&jwt_token)
+                .with_error_handling()
+                .with_rate_limit(self.rate_limits.app())
+                .send()
+                .await?;
+
+            if response.status() != StatusCode::UNAUTHORIZED {
+                if !token_creator.is_preferred(key) {
+                    tracing::warn!(
+                        key = token_creator.key_name(key),
+                        fingerprint = token_creator.key_fingerprint(key),
+                        "Switching to the next private key"
+                    );
+                    token_creator.prefer(key);
+                }
+                return Ok(response);
+            }
+
+            tracing::error!(
+                key = token_creator.key_name(key),
+                fingerprint = token_creator.key_fingerprint(key),
+                "GitHub rejected the private key, it may have been revoked"
+            );
+            rejected = Some(response);
+        }
+
+        Ok(rejected.expect("the GitHub App has at least one private key"))
+    }
+
+    /// Requests an installation access token, which is limited to the
+    /// repositories and permissions of the scope if there is one
+    #[instrument(skip_all, fields(installation_id))]
+    async fn create_installation_token(
+        &self,
+        credentials: &AppCredentials,
+        installation_id: usize,
+        scope: Option<&TokenScope>,
+    ) -> Result<AccessToken, ApiError> {
         let url = format!(
             "{}/app/installations/{installation_id}/access_tokens",
-            self.base_url
+            self.endpoint.base_url
         );
 
+        let request_body = scope
+            .map(|scope| {
+                serde_json::to_vec(&AccessTokensRestRequest {
+                    repositories: &scope.repositories,
+                    permissions: &scope.permissions,
+                })
+            })
+            .transpose()?;
+
+        let response = self
+            .send_as_app(credentials, |client| match &request_body {
+                Some(request_body) => client.post(&url).body(request_body.clone()),
+                None => client.post(&url),
+            })
+            .await?;
+
+        if response.is_success() {
+            let response: AccessTokensRestResponse = response.json().await?;
+            let expires_at = parse_timestamp(&response.expires_at).unwrap_or_else(|| {
+                tracing::warn!(
+                    expires_at = response.expires_at,
+                    "Could not read the expiry of the installation access token"
+                );
+                SystemTime::now() + INSTALLATION_TOKEN_LIFETIME
+            });
+
+            Ok(AccessToken::installation(
+                installation_id,
+                scope.cloned(),
+                response.token,
+                expires_at,
+                self.rate_limits.installation(installation_id),
+                self.response_cache.clone(),
+            ))
+        } else {
+            Err(response.into_error().await)
+        }
+    }
+
+    /// The API authenticated with the token of the method. Installation
+    /// access tokens are cached. The JWT of the app is signed with the
+    /// preferred key, but the endpoints of the app are sent through
+    /// [`Self::send_as_app`] to fall back to the other keys.
+    pub(super) async fn api(
+        &self,
+        credentials: Arc<AppCredentials>,
+        auth_method: AuthenticationMethod,
+    ) -> Result<GitHubRestApi<'_>, ApiError> {
+        let token = match auth_method {
+            AuthenticationMethod::AppInstallation {
+                installation_id,
+                scope,
+            } => {
+                self.installation_tokens
+                    .get_or_refresh(installation_id, scope.as_ref(), || {
+                        self.create_installation_token(
+                            &credentials,
+                            installation_id,
+                            scope.as_ref(),
+                        )
+                    })
+                    .await?
+            }
+            AuthenticationMethod::PersonalAccessToken { token }
+            | AuthenticationMethod::OAuthUserToken { token } => Arc::new(AccessToken::new(
+                TokenOwner::User,
+                token,
+                self.rate_limits.user(),
+            )),
+            AuthenticationMethod::App => {
+                let token_creator = &credentials.token_creator;
+                let key = token_creator
+                    .keys_by_preference()
+                    .next()
+                    .expect("the GitHub App has at least one private key");
+                Arc::new(AccessToken::new(
+                    TokenOwner::App,
+                    token_creator.build_token(key)?,
+                    self.rate_limits.app(),
+                ))
+            }
+        };
+
+        Ok(GitHubRestApi {
+            token,
+            client: &self.client,
+            endpoint: &self.endpoint,
+            provider: self,
+            credentials,
+        })
+    }
+
+    #[instrument(skip_all)]
+    async fn server_version(&self) -> Result<Option<String>, ApiError> {
+        let url = format!("{}/meta", self.endpoint.base_url);
+
         let response = self
             .client
-            .post(url)
-            .header("User-Agent", "koritsu-app")
-            .header("Accept", "application/vnd.github+json")
-            .header("X-GitHub-Api-Version", "2022-11-28")
-            .bearer_auth(&jwt_token)
+            .get(url)
+            .github_headers(&self.endpoint)
             .with_error_handling()
             .send()
             .await?;
 
         if response.is_success() {
-            response
-                .json::<AccessTokensRestResponse>()
-                .await
-                .map(|response| GitHubRestApi {
-                    token: response.token,
-                    client: &self.client,
-                    base_url: &self.base_url,
-                })
+            let meta: MetaRest = response.json().await?;
+            Ok(meta.installed_version)
         } else {
-            let basic_error: BasicError = response.json().await?;
-            Err(ApiError::Authentication(
-                basic_error
-                    .message
-                    .unwrap_or_else(|| "Could not retrieve access token".to_owned()),
-            ))
+            Err(response.into_error().await)
         }
     }
 }
 
-pub type Token = String;
+impl GitHubApiProvider for GitHubRestApiProvider {
+    type Credentials = AppCredentials;
+
+    fn load_credentials(
+        &self,
+        config: &ApplicationConfig,
+    ) -> Result<AppCredentials, Box<dyn Error + Send + Sync>> {
+        let keys = load_private_keys(&config.private_keys)?;
+        Ok(AppCredentials {
+            token_creator: JwtTokenCreator::new(config.client_id.clone(), keys),
+        })
+    }
+
+    #[instrument(skip_all, fields(auth_method))]
+    async fn get_api(
+        &self,
+        credentials: Arc<AppCredentials>,
+        auth_method: AuthenticationMethod,
+    ) -> Result<impl GitHubApi, ApiError> {
+        self.api(credentials, auth_method).await
+    }
+
+    fn forget_installation(&self, installation_id: usize) {
+        self.installation_tokens.forget(installation_id);
+        self.rate_limits.forget(installation_id);
+        self.response_cache.forget(installation_id);
+    }
+
+    fn rate_limits(&self) -> Vec<RateLimitStatus> {
+        self.rate_limits.list()
+    }
+
+    fn response_cache_stats(&self) -> ResponseCacheStats {
+        self.response_cache.stats()
+    }
+
+    #[instrument(skip_all, fields(installation_id))]
+    async fn get_installation(
+        &self,
+        credentials: &AppCredentials,
+        installation_id: usize,
+    ) -> Result<Option<InstallationDetails>, ApiError> {
+        let url = format!(
+            "{}/app/installations/{installation_id}",
+            self.endpoint.base_url
+        );
+
+        let response = self
+            .send_as_app(credentials, |client| client.get(&url))
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => response
+                .json::<InstallationRest>()
+                .await
+                .map(|installation| Some(installation.into())),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(response.into_error().await),
+        }
+    }
+
+    #[instrument(skip_all, fields(repository_name))]
+    async fn get_repository_installation(
+        &self,
+        credentials: &AppCredentials,
+        repository_name: &str,
+    ) -> Result<Option<InstallationDetails>, ApiError> {
+        let url = format!(
+            "{}/repos/{repository_name}/installation",
+            self.endpoint.base_url
+        );
+
+        let response = self
+            .send_as_app(credentials, |client| client.get(&url))
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => response
+                .json::<InstallationRest>()
+                .await
+                .map(|installation| Some(installation.into())),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(response.into_error().await),
+        }
+    }
+}
+
+pub type Token = Arc<AccessToken>;
+
+#[derive(Debug, Deserialize)]
+struct AppRest {
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AccessTokensRestRequest<'a> {
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    repositories: &'a BTreeSet<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    permissions: &'a BTreeMap<String, String>,
+}
 
 #[derive(Debug, Deserialize)]
 struct AccessTokensRestResponse {
     token: String,
+    expires_at: String,
 }
 
-struct GitHubRestApi<'a> {
-    token: Token,
-    client: &'a Client,
-    base_url: &'a str,
+pub(super) struct GitHubRestApi<'a> {
+    pub(super) token: Token,
+    pub(super) client: &'a Client,
+    pub(super) endpoint: &'a ApiEndpoint,
+    /// Sends the requests of the app
+    provider: &'a GitHubRestApiProvider,
+    credentials: Arc<AppCredentials>,
 }
 
 impl GitHubApi for GitHubRestApi<'_> {
@@ -116,13 +497,22 @@ impl GitHubApi for GitHubRestApi<'_> {
         &self,
         request: BranchComparisonRequest,
     ) -> Result<BranchComparison, ApiError> {
-        let comparison = GithubCommitsRestApi::new(&self.token, self.base_url, self.client)
+        let comparison = GithubCommitsRestApi::new(&self.token, self.endpoint, self.client)
             .compare_commits(request)
             .await?;
 
         Ok(comparison)
     }
 
+    async fn list_changed_files(
+        &self,
+        request: BranchComparisonRequest,
+    ) -> Result<Vec<ChangedFile>, ApiError> {
+        GithubCommitsRestApi::new(&self.token, self.endpoint, self.client)
+            .list_changed_files(&request)
+            .await
+    }
+
     #[instrument(skip_all, fields(request))]
     async fn update_reference(
         &self,
@@ -130,52 +520,100 @@ impl GitHubApi for GitHubRestApi<'_> {
     ) -> Result<(), ApiError> {
         let ref_update_url = format!(
             "{}/repos/{}/git/refs/{}",
-            self.base_url, request.repository_name, request.reference,
+            self.endpoint.base_url, request.repository_name, request.reference,
         );
 
+        // Without force the update only fast forwards, so repeating it after
+        // it already succeeded changes nothing
+        let force = request.force;
         let request_body = serde_json::to_vec(&UpdateReferenceRequest {
             sha: request.sha1,
-            force: request.force,
+            force,
         })?;
 
         let response = self
             .client
             .patch(&ref_update_url)
             .body(request_body)
-            .header("User-Agent", "koritsu-app")
-            .header("Accept", "application/vnd.github+json")
-            .header("X-GitHub-Api-Version", "2022-11-28")
-            .bearer_auth(&self.token)
-            .with_error_handling()
+            .github_headers(self.endpoint)
+            .with_access_token(&self.token)
+            .safe_to_repeat(!force)
             .send()
             .await?;
 
         if response.is_success() {
             Ok(())
         } else {
-            let status = response.status();
-            let basic_error: BasicError = response.json().await?;
-
-            match status {
-                StatusCode::NOT_FOUND => Err(ApiError::RepositoryNotFound(
-                    basic_error
-                        .message
-                        .unwrap_or_else(|| format!("Repository {} not found", ref_update_url)),
-                )),
-                StatusCode::FORBIDDEN => Err(ApiError::Authorization(
-                    basic_error
-                        .message
-                        .unwrap_or_else(|| "Operation was forbidden".to_string()),
-                )),
-                _ => Err(ApiError::Unspecific),
-            }
+            Err(response.into_error().await)
         }
     }
-}
 
-#[derive(Debug, Deserialize)]
-struct BasicError {
-    pub message: Option<String>,
+    async fn create_commit_status(&self, request: CommitStatusRequest) -> Result<(), ApiError> {
+        GithubStatusesRestApi::new(&self.token, self.endpoint, self.client)
+            .create_commit_status(request)
+            .await
+    }
+
+    async fn list_workflow_runs(
+        &self,
+        request: WorkflowRunsRequest,
+    ) -> Result<Vec<WorkflowRunSummary>, ApiError> {
+        GithubActionsRestApi::new(&self.token, self.endpoint, self.client)
+            .list_workflow_runs(request)
+            .await
+    }
+
+    async fn get_branch_head(&self, request: BranchHeadRequest) -> Result<String, ApiError> {
+        GithubRefsRestApi::new(&self.token, self.endpoint, self.client)
+            .get_branch_head(request)
+            .await
+    }
+
+    async fn get_file_content(
+        &self,
+        request: FileContentRequest,
+    ) -> Result<Option<String>, ApiError> {
+        GithubContentsRestApi::new(&self.token, self.endpoint, self.client)
+            .get_file_content(request)
+            .await
+    }
+
+    async fn create_check_run(&self, request: CheckRunRequest) -> Result<(), ApiError> {
+        GithubChecksRestApi::new(&self.token, self.endpoint, self.client)
+            .create_check_run(request)
+            .await
+    }
+
+    async fn merge_branch(&self, request: MergeBranchRequest) -> Result<MergeResult, ApiError> {
+        GithubMergesRestApi::new(&self.token, self.endpoint, self.client)
+            .merge_branch(request)
+            .await
+    }
+
+    async fn delete_reference(&self, request: DeleteReferenceRequest) -> Result<(), ApiError> {
+        GithubRefsRestApi::new(&self.token, self.endpoint, self.client)
+            .delete_reference(request)
+            .await
+    }
+
+    async fn list_installation_repositories(&self) -> Result<Vec<RepositorySummary>, ApiError> {
+        GithubRepositoriesRestApi::new(&self.token, self.endpoint, self.client)
+            .list_installation_repositories()
+            .await
+    }
+
+    /// Only the JWT of the app may list its installations. Other tokens are
+    /// refused before GitHub rejects them, which would discard an installation
+    /// access token.
+    async fn list_installations(&self) -> Result<Vec<InstallationDetails>, ApiError> {
+        if self.token.owner() != TokenOwner::App {
+            return Err(ApiError::Authentication(ErrorDetails::from_message(
+                "Listing installations requires the App authentication method",
+            )));
+        }
+
+        self.provider.list_installations(&self.credentials).await
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -0,0 +1,160 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{fs, io, path::PathBuf};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use rsa::{
+    RsaPrivateKey,
+    pkcs1::{self, DecodeRsaPrivateKey},
+    pkcs8::{self, DecodePrivateKey, EncodePublicKey},
+    sha2::{Digest, Sha256},
+};
+use thiserror::Error;
+
+use super::jwt_token_creator::AppKey;
+use crate::PrivateKeySource;
+
+const PKCS1_PEM_LABEL: &str = "RSA PRIVATE KEY";
+const PKCS8_PEM_LABEL: &str = "PRIVATE KEY";
+const ENCRYPTED_PKCS8_PEM_LABEL: &str = "ENCRYPTED PRIVATE KEY";
+
+#[derive(Error, Debug)]
+pub enum PrivateKeyError {
+    #[error("No private key is configured")]
+    NoKeys,
+
+    #[error("Could not read private key file {0}")]
+    UnreadableFile(PathBuf, #[source] io::Error),
+
+    #[error("The private key from {0} can not be used")]
+    InvalidKey(String, #[source] KeyFormatError),
+}
+
+#[derive(Error, Debug)]
+pub enum KeyFormatError {
+    #[error("The key is encrypted, decrypt it with `openssl pkcs8 -in <file> -out <file>`")]
+    Encrypted,
+
+    #[error("PEM block {0} is not supported, expected {PKCS1_PEM_LABEL} or {PKCS8_PEM_LABEL}")]
+    UnsupportedPemLabel(String),
+
+    #[error("The key is neither PEM encoded nor a DER encoded PKCS#1 or PKCS#8 RSA key")]
+    UnknownEncoding,
+
+    #[error("Invalid PKCS#1 RSA key")]
+    InvalidPkcs1(#[source] pkcs1::Error),
+
+    #[error("Invalid PKCS#8 RSA key")]
+    InvalidPkcs8(#[source] pkcs8::Error),
+}
+
+/// Reads the private keys of the GitHub App from their configured sources.
+pub fn load_private_keys(sources: &[PrivateKeySource]) -> Result<Vec<AppKey>, PrivateKeyError> {
+    if sources.is_empty() {
+        return Err(PrivateKeyError::NoKeys);
+    }
+
+    sources
+        .iter()
+        .enumerate()
+        .map(|(index, source)| {
+            let name = match source {
+                PrivateKeySource::Inline(_) if sources.len() > 1 => {
+                    format!("{source}[{index}]")
+                }
+                _ => source.to_string(),
+            };
+            load_private_key(source, name)
+        })
+        .collect()
+}
+
+fn load_private_key(source: &PrivateKeySource, name: String) -> Result<AppKey, PrivateKeyError> {
+    let key = match source {
+        PrivateKeySource::File(path) => fs::read(path)
+            .map_err(|error| PrivateKeyError::UnreadableFile(path.clone(), error))
+            .map(|content| decode(&content))?,
+        PrivateKeySource::Inline(value) => decode_inline(value.trim()),
+    };
+
+    match key {
+        Ok(private_key) => Ok(AppKey {
+            fingerprint: fingerprint(&private_key),
+            name,
+            private_key,
+        }),
+        Err(error) => Err(PrivateKeyError::InvalidKey(name, error)),
+    }
+}
+
+/// Formats the fingerprint like GitHub does, `SHA256:` followed by the base64
+/// encoded hash of the DER encoded public key.
+fn fingerprint(private_key: &RsaPrivateKey) -> String {
+    let public_key = private_key
+        .to_public_key()
+        .to_public_key_der()
+        .expect("RSA public keys can always be encoded");
+
+    format!(
+        "SHA256:{}",
+        BASE64_STANDARD.encode(Sha256::digest(public_key.as_bytes()))
+    )
+}
+
+/// Values from the configuration or the environment are text, therefore DER
+/// keys have to be base64 encoded.
+fn decode_inline(value: &str) -> Result<RsaPrivateKey, KeyFormatError> {
+    if value.starts_with("-----BEGIN ") {
+        return decode_pem(value);
+    }
+
+    let der = BASE64_STANDARD
+        .decode(value)
+        .map_err(|_| KeyFormatError::UnknownEncoding)?;
+    decode_der(&der)
+}
+
+fn decode(content: &[u8]) -> Result<RsaPrivateKey, KeyFormatError> {
+    match std::str::from_utf8(content) {
+        Ok(text) if text.trim_start().starts_with("-----BEGIN ") => decode_pem(text.trim()),
+        _ => decode_der(content),
+    }
+}
+
+fn decode_pem(pem: &str) -> Result<RsaPrivateKey, KeyFormatError> {
+    let label = pem
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .unwrap_or_default();
+
+    match label {
+        PKCS1_PEM_LABEL => RsaPrivateKey::from_pkcs1_pem(pem).map_err(KeyFormatError::InvalidPkcs1),
+        PKCS8_PEM_LABEL => RsaPrivateKey::from_pkcs8_pem(pem).map_err(KeyFormatError::InvalidPkcs8),
+        ENCRYPTED_PKCS8_PEM_LABEL => Err(KeyFormatError::Encrypted),
+        other => Err(KeyFormatError::UnsupportedPemLabel(other.to_owned())),
+    }
+}
+
+/// DER does not name its content, so PKCS#8 is tried first and PKCS#1 if the
+/// document is no PKCS#8 structure at all.
+fn decode_der(der: &[u8]) -> Result<RsaPrivateKey, KeyFormatError> {
+    match RsaPrivateKey::from_pkcs8_der(der) {
+        Ok(key) => Ok(key),
+        Err(pkcs8::Error::Asn1(_)) => {
+            RsaPrivateKey::from_pkcs1_der(der).map_err(|error| match error {
+                pkcs1::Error::Asn1(_) => KeyFormatError::UnknownEncoding,
+                error => KeyFormatError::InvalidPkcs1(error),
+            })
+        }
+        Err(error) => Err(KeyFormatError::InvalidPkcs8(error)),
+    }
+}
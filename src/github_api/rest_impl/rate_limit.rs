@@ -0,0 +1,240 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{StatusCode, header::HeaderMap};
+
+use crate::github_api::{ApiError, ErrorDetails, RateLimitStatus};
+
+/// GitHub asks to wait at least a minute after a secondary rate limit that
+/// does not say how long to wait.
+const SECONDARY_RATE_LIMIT_PAUSE: Duration = Duration::from_secs(60);
+
+/// The rate limit headers of a single response
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RateLimit {
+    limit: Option<u64>,
+    remaining: Option<u64>,
+    /// Seconds since the Unix epoch
+    reset: Option<u64>,
+    retry_after: Option<Duration>,
+}
+
+impl RateLimit {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+
+        Self {
+            limit: number("x-ratelimit-limit"),
+            remaining: number("x-ratelimit-remaining"),
+            reset: number("x-ratelimit-reset"),
+            retry_after: number("retry-after").map(Duration::from_secs),
+        }
+    }
+
+    /// GitHub answers with `429 Too Many Requests` or `403 Forbidden` when a
+    /// primary or secondary rate limit is exceeded.
+    pub fn is_exceeded(&self, status: StatusCode) -> bool {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => true,
+            StatusCode::FORBIDDEN => self.retry_after.is_some() || self.remaining == Some(0),
+            _ => false,
+        }
+    }
+
+    /// How long to wait before a request that exceeded the limit is repeated
+    pub fn pause(&self, now: SystemTime) -> Duration {
+        if let Some(retry_after) = self.retry_after {
+            return retry_after;
+        }
+
+        match (self.remaining, self.reset) {
+            (Some(0), Some(reset)) => until(now, reset),
+            _ => SECONDARY_RATE_LIMIT_PAUSE,
+        }
+    }
+}
+
+fn until(now: SystemTime, timestamp: u64) -> Duration {
+    (UNIX_EPOCH + Duration::from_secs(timestamp))
+        .duration_since(now)
+        .unwrap_or_default()
+}
+
+fn timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .expect("Unix epoch is always in the past")
+        .as_secs()
+}
+
+/// What is left of the rate limit of the app or of one installation, as
+/// reported by the latest response
+pub struct RateLimitBudget {
+    installation_id: Option<usize>,
+    state: Mutex<BudgetState>,
+}
+
+#[derive(Default)]
+struct BudgetState {
+    latest: RateLimit,
+    paused_until: Option<SystemTime>,
+}
+
+impl RateLimitBudget {
+    fn new(installation_id: Option<usize>) -> Self {
+        Self {
+            installation_id,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn update(&self, rate_limit: RateLimit, exceeded: bool, now: SystemTime) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("rate limit budget is never poisoned");
+
+        if rate_limit.limit.is_some() || rate_limit.remaining.is_some() {
+            state.latest = rate_limit;
+        }
+        if exceeded {
+            state.paused_until = Some(now + rate_limit.pause(now));
+        }
+    }
+
+    /// How long a request has to wait until the budget allows it
+    pub fn delay(&self, now: SystemTime) -> Duration {
+        let state = self
+            .state
+            .lock()
+            .expect("rate limit budget is never poisoned");
+
+        let paused = state
+            .paused_until
+            .and_then(|paused_until| paused_until.duration_since(now).ok())
+            .unwrap_or_default();
+        let exhausted = match (state.latest.remaining, state.latest.reset) {
+            (Some(0), Some(reset)) => until(now, reset),
+            _ => Duration::ZERO,
+        };
+
+        paused.max(exhausted)
+    }
+
+    /// Holds the request back while the budget is exhausted or GitHub asked
+    /// to pause. Refuses the request if that takes longer than `max_delay`.
+    pub async fn wait(&self, max_delay: Duration) -> Result<(), ApiError> {
+        let delay = self.delay(SystemTime::now());
+        if delay > max_delay {
+            tracing::warn!(
+                installation_id = self.installation_id,
+                ?delay,
+                "GitHub rate limit reached, refusing request"
+            );
+            return Err(ApiError::RateLimited(ErrorDetails::from_message(format!(
+                "Rate limit exhausted for another {} seconds",
+                delay.as_secs()
+            ))));
+        }
+
+        if !delay.is_zero() {
+            tracing::warn!(
+                installation_id = self.installation_id,
+                ?delay,
+                "GitHub rate limit reached, delaying request"
+            );
+            tokio::time::sleep(delay).await;
+        }
+
+        Ok(())
+    }
+
+    fn status(&self) -> RateLimitStatus {
+        let state = self
+            .state
+            .lock()
+            .expect("rate limit budget is never poisoned");
+
+        RateLimitStatus {
+            installation_id: self.installation_id,
+            limit: state.latest.limit,
+            remaining: state.latest.remaining,
+            reset_at: state.latest.reset,
+            paused_until: state
+                .paused_until
+                .filter(|paused_until| *paused_until > SystemTime::now())
+                .map(timestamp),
+        }
+    }
+}
+
+/// The budgets of the app and of every installation that sent requests
+pub struct RateLimits {
+    app: Arc<RateLimitBudget>,
+    installations: Mutex<BTreeMap<usize, Arc<RateLimitBudget>>>,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            app: Arc::new(RateLimitBudget::new(None)),
+            installations: Mutex::default(),
+        }
+    }
+}
+
+impl RateLimits {
+    pub fn app(&self) -> Arc<RateLimitBudget> {
+        self.app.clone()
+    }
+
+    pub fn installation(&self, installation_id: usize) -> Arc<RateLimitBudget> {
+        self.installations
+            .lock()
+            .expect("rate limits are never poisoned")
+            .entry(installation_id)
+            .or_insert_with(|| Arc::new(RateLimitBudget::new(Some(installation_id))))
+            .clone()
+    }
+
+    /// A budget for the token of a user, which GitHub limits on its own. It
+    /// is not listed, because the application does not know the user.
+    pub fn user(&self) -> Arc<RateLimitBudget> {
+        Arc::new(RateLimitBudget::new(None))
+    }
+
+    pub fn forget(&self, installation_id: usize) {
+        self.installations
+            .lock()
+            .expect("rate limits are never poisoned")
+            .remove(&installation_id);
+    }
+
+    pub fn list(&self) -> Vec<RateLimitStatus> {
+        let installations = self
+            .installations
+            .lock()
+            .expect("rate limits are never poisoned");
+
+        std::iter::once(&self.app)
+            .chain(installations.values())
+            .map(|budget| budget.status())
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::BranchHeadRequest;
+use crate::github_api::DeleteReferenceRequest;
+use reqwest::Client;
+use serde::Deserialize;
+use std::ops::Deref;
+use tracing::instrument;
+
+use super::Token;
+use super::endpoint::{ApiEndpoint, GitHubRequestExt};
+use super::error_handling::IntoErrorHandlingRequest;
+
+pub struct GithubRefsRestApi<'a, C> {
+    token: &'a Token,
+    endpoint: &'a ApiEndpoint,
+    client: C,
+}
+
+impl<'a, C: Deref<Target = Client>> GithubRefsRestApi<'a, C> {
+    pub fn new(token: &'a Token, endpoint: &'a ApiEndpoint, client: C) -> Self {
+        Self {
+            token,
+            endpoint,
+            client,
+        }
+    }
+}
+
+impl<C: Deref<Target = Client>> GithubRefsRestApi<'_, C> {
+    #[instrument(skip_all, fields(request))]
+    pub async fn get_branch_head(&self, request: BranchHeadRequest) -> Result<String, ApiError> {
+        let ref_url = format!(
+            "{}/repos/{}/git/ref/heads/{}",
+            self.endpoint.base_url, request.repository_name, request.branch
+        );
+
+        let response = self
+            .client
+            .get(&ref_url)
+            .github_headers(self.endpoint)
+            .with_access_token(self.token)
+            .send()
+            .await?;
+
+        if response.is_success() {
+            response
+                .json::<ReferenceRest>()
+                .await
+                .map(|reference| reference.object.sha)
+        } else {
+            Err(response.into_error().await)
+        }
+    }
+
+    #[instrument(skip_all, fields(request))]
+    pub async fn delete_reference(&self, request: DeleteReferenceRequest) -> Result<(), ApiError> {
+        let ref_url = format!(
+            "{}/repos/{}/git/refs/{}",
+            self.endpoint.base_url, request.repository_name, request.reference
+        );
+
+        let response = self
+            .client
+            .delete(&ref_url)
+            .github_headers(self.endpoint)
+            .with_access_token(self.token)
+            .send()
+            .await?;
+
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(response.into_error().await)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ReferenceRest {
+    object: ObjectRest,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectRest {
+    sha: String,
+}
@@ -0,0 +1,90 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::RepositorySummary;
+use reqwest::Client;
+use serde::Deserialize;
+use std::ops::Deref;
+use tracing::instrument;
+
+use super::Token;
+use super::endpoint::{ApiEndpoint, GitHubRequestExt};
+use super::error_handling::IntoErrorHandlingRequest;
+
+const PAGE_SIZE: usize = 100;
+
+pub struct GithubRepositoriesRestApi<'a, C> {
+    token: &'a Token,
+    endpoint: &'a ApiEndpoint,
+    client: C,
+}
+
+impl<'a, C: Deref<Target = Client>> GithubRepositoriesRestApi<'a, C> {
+    pub fn new(token: &'a Token, endpoint: &'a ApiEndpoint, client: C) -> Self {
+        Self {
+            token,
+            endpoint,
+            client,
+        }
+    }
+}
+
+impl<C: Deref<Target = Client>> GithubRepositoriesRestApi<'_, C> {
+    #[instrument(skip_all)]
+    pub async fn list_installation_repositories(&self) -> Result<Vec<RepositorySummary>, ApiError> {
+        let repositories_url = format!("{}/installation/repositories", self.endpoint.base_url);
+        let mut repositories = Vec::new();
+
+        for page in 1.. {
+            let response = self
+                .client
+                .get(&repositories_url)
+                .query(&[("per_page", PAGE_SIZE), ("page", page)])
+                .github_headers(self.endpoint)
+                .with_access_token(self.token)
+                .send()
+                .await?;
+
+            if !response.is_success() {
+                return Err(response.into_error().await);
+            }
+
+            let page: RepositoriesRest = response.json().await?;
+            let count = page.repositories.len();
+            repositories.extend(page.repositories.into_iter().map(Into::into));
+
+            if count < PAGE_SIZE {
+                break;
+            }
+        }
+
+        Ok(repositories)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoriesRest {
+    repositories: Vec<RepositoryRest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryRest {
+    full_name: String,
+    default_branch: String,
+}
+
+impl From<RepositoryRest> for RepositorySummary {
+    fn from(repository: RepositoryRest) -> Self {
+        RepositorySummary {
+            full_name: repository.full_name,
+            default_branch: repository.default_branch,
+        }
+    }
+}
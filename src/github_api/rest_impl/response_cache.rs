@@ -0,0 +1,200 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::body::Bytes;
+use reqwest::{
+    Response, StatusCode,
+    header::{ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
+
+use crate::github_api::{ApiError, ErrorDetails, ResponseCacheStats, TokenScope};
+
+const MAX_ENTRIES: usize = 2048;
+const MAX_BYTES: usize = 32 * 1024 * 1024;
+
+/// A response is cached per URL, installation and token scope, because they
+/// can see different content under the same URL, and per `Accept` header,
+/// because GitHub answers in the media type it asks for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub installation_id: usize,
+    pub scope: Option<TokenScope>,
+    pub url: String,
+    pub accept: Option<String>,
+}
+
+pub struct CachedResponse {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl CachedResponse {
+    /// Makes the request conditional, so GitHub answers `304 Not Modified`
+    /// if the cached response is still current.
+    pub fn add_validators(&self, headers: &mut HeaderMap) {
+        if let Some(etag) = &self.etag {
+            headers.insert(IF_NONE_MATCH, etag.clone());
+        } else if let Some(last_modified) = &self.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+    }
+
+    fn to_response(&self) -> Response {
+        let mut response = hyper::Response::new(self.body.clone());
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        Response::from(response)
+    }
+}
+
+struct Entry {
+    response: Arc<CachedResponse>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, Entry>,
+    bytes: usize,
+    clock: u64,
+    hits: u64,
+}
+
+/// Remembers successful responses that carry an `ETag` or `Last-Modified`
+/// header. The least recently used responses are dropped once the cache
+/// holds more than [`MAX_ENTRIES`] responses or [`MAX_BYTES`] of content.
+#[derive(Default)]
+pub struct ResponseCache {
+    state: Mutex<CacheState>,
+}
+
+impl ResponseCache {
+    pub fn get(&self, key: &CacheKey) -> Option<Arc<CachedResponse>> {
+        let mut state = self.state.lock().expect("response cache is never poisoned");
+        state.clock += 1;
+        let clock = state.clock;
+
+        state.entries.get_mut(key).map(|entry| {
+            entry.last_used = clock;
+            entry.response.clone()
+        })
+    }
+
+    /// Answers a `304 Not Modified` with the cached response and remembers
+    /// new cacheable responses.
+    pub async fn handle(
+        &self,
+        key: CacheKey,
+        cached: Option<Arc<CachedResponse>>,
+        response: Response,
+    ) -> Result<Response, ApiError> {
+        if response.status() == StatusCode::NOT_MODIFIED
+            && let Some(cached) = cached
+        {
+            self.state
+                .lock()
+                .expect("response cache is never poisoned")
+                .hits += 1;
+            return Ok(cached.to_response());
+        }
+
+        let etag = response.headers().get(ETAG).cloned();
+        let last_modified = response.headers().get(LAST_MODIFIED).cloned();
+        if !response.status().is_success() || (etag.is_none() && last_modified.is_none()) {
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await.map_err(|error| {
+            tracing::error!(%error, "Retrieving byte content failed");
+            ApiError::Transport(ErrorDetails::from_message(error.to_string()))
+        })?;
+
+        let cached = CachedResponse {
+            etag,
+            last_modified,
+            status,
+            headers,
+            body,
+        };
+        let response = cached.to_response();
+        self.insert(key, cached);
+        Ok(response)
+    }
+
+    pub fn forget(&self, installation_id: usize) {
+        let mut state = self.state.lock().expect("response cache is never poisoned");
+        let state = &mut *state;
+
+        state.entries.retain(|key, entry| {
+            let keep = key.installation_id != installation_id;
+            if !keep {
+                state.bytes -= entry.response.body.len();
+            }
+            keep
+        });
+    }
+
+    pub fn stats(&self) -> ResponseCacheStats {
+        let state = self.state.lock().expect("response cache is never poisoned");
+
+        ResponseCacheStats {
+            entries: state.entries.len(),
+            bytes: state.bytes,
+            hits: state.hits,
+        }
+    }
+
+    fn insert(&self, key: CacheKey, response: CachedResponse) {
+        let size = response.body.len();
+        if size > MAX_BYTES {
+            return;
+        }
+
+        let mut state = self.state.lock().expect("response cache is never poisoned");
+        if let Some(replaced) = state.entries.remove(&key) {
+            state.bytes -= replaced.response.body.len();
+        }
+
+        while state.entries.len() >= MAX_ENTRIES || state.bytes + size > MAX_BYTES {
+            let Some(least_recently_used) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+
+            if let Some(evicted) = state.entries.remove(&least_recently_used) {
+                state.bytes -= evicted.response.body.len();
+            }
+        }
+
+        state.clock += 1;
+        let last_used = state.clock;
+        state.bytes += size;
+        state.entries.insert(
+            key,
+            Entry {
+                response: Arc::new(response),
+                last_used,
+            },
+        );
+    }
+}
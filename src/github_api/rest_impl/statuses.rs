@@ -0,0 +1,87 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::github_api::ApiError;
+use crate::github_api::CommitState;
+use crate::github_api::CommitStatusRequest;
+use reqwest::Client;
+use serde::Serialize;
+use std::ops::Deref;
+use tracing::instrument;
+
+use super::Token;
+use super::endpoint::{ApiEndpoint, GitHubRequestExt};
+use super::error_handling::IntoErrorHandlingRequest;
+
+const STATUS_CONTEXT: &str = "koritsu";
+
+pub struct GithubStatusesRestApi<'a, C> {
+    token: &'a Token,
+    endpoint: &'a ApiEndpoint,
+    client: C,
+}
+
+impl<'a, C: Deref<Target = Client>> GithubStatusesRestApi<'a, C> {
+    pub fn new(token: &'a Token, endpoint: &'a ApiEndpoint, client: C) -> Self {
+        Self {
+            token,
+            endpoint,
+            client,
+        }
+    }
+}
+
+impl<C: Deref<Target = Client>> GithubStatusesRestApi<'_, C> {
+    #[instrument(skip_all, fields(request))]
+    pub async fn create_commit_status(&self, request: CommitStatusRequest) -> Result<(), ApiError> {
+        let status_url = format!(
+            "{}/repos/{}/statuses/{}",
+            self.endpoint.base_url, request.repository_name, request.sha1
+        );
+
+        let request_body = serde_json::to_vec(&CommitStatusRest {
+            state: request.state.into(),
+            description: request.description,
+            context: STATUS_CONTEXT,
+        })?;
+
+        let response = self
+            .client
+            .post(&status_url)
+            .body(request_body)
+            .github_headers(self.endpoint)
+            .with_access_token(self.token)
+            .send()
+            .await?;
+
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(response.into_error().await)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CommitStatusRest {
+    state: &'static str,
+    description: String,
+    context: &'static str,
+}
+
+impl From<CommitState> for &'static str {
+    fn from(state: CommitState) -> Self {
+        match state {
+            CommitState::Pending => "pending",
+            CommitState::Success => "success",
+            CommitState::Failure => "failure",
+            CommitState::Error => "error",
+        }
+    }
+}
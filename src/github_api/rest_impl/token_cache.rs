@@ -0,0 +1,217 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::rate_limit::RateLimitBudget;
+use super::response_cache::ResponseCache;
+use crate::github_api::{ApiError, TokenScope};
+
+/// Tokens are replaced this long before GitHub lets them expire, so a token
+/// does not run out while an event is handled.
+const REFRESH_AHEAD: Duration = Duration::from_secs(5 * 60);
+
+/// Who an access token authenticates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenOwner {
+    Installation(usize),
+    /// A JWT of the GitHub App itself
+    App,
+    /// A personal access token or the OAuth token of a user
+    User,
+}
+
+/// An access token, when GitHub lets it expire, and the rate limit and
+/// response cache of the installation it belongs to
+pub struct AccessToken {
+    owner: TokenOwner,
+    /// What an installation access token is limited to
+    scope: Option<TokenScope>,
+    value: String,
+    /// `None` for tokens the application does not refresh
+    expires_at: Option<SystemTime>,
+    rejected: AtomicBool,
+    rate_limit: Arc<RateLimitBudget>,
+    /// Only responses for installations are cached
+    response_cache: Option<Arc<ResponseCache>>,
+}
+
+impl AccessToken {
+    pub fn installation(
+        installation_id: usize,
+        scope: Option<TokenScope>,
+        value: String,
+        expires_at: SystemTime,
+        rate_limit: Arc<RateLimitBudget>,
+        response_cache: Arc<ResponseCache>,
+    ) -> Self {
+        Self {
+            owner: TokenOwner::Installation(installation_id),
+            scope,
+            value,
+            expires_at: Some(expires_at),
+            rejected: AtomicBool::new(false),
+            rate_limit,
+            response_cache: Some(response_cache),
+        }
+    }
+
+    /// A token of the app or a user, whose responses are not cached
+    pub fn new(owner: TokenOwner, value: String, rate_limit: Arc<RateLimitBudget>) -> Self {
+        Self {
+            owner,
+            scope: None,
+            value,
+            expires_at: None,
+            rejected: AtomicBool::new(false),
+            rate_limit,
+            response_cache: None,
+        }
+    }
+
+    pub fn owner(&self) -> TokenOwner {
+        self.owner
+    }
+
+    pub fn installation_id(&self) -> Option<usize> {
+        match self.owner {
+            TokenOwner::Installation(installation_id) => Some(installation_id),
+            TokenOwner::App | TokenOwner::User => None,
+        }
+    }
+
+    pub fn scope(&self) -> Option<&TokenScope> {
+        self.scope.as_ref()
+    }
+
+    pub fn rate_limit(&self) -> Arc<RateLimitBudget> {
+        self.rate_limit.clone()
+    }
+
+    pub fn response_cache(&self) -> Option<Arc<ResponseCache>> {
+        self.response_cache.clone()
+    }
+
+    /// Marks the token as unusable, because GitHub answered `401 Unauthorized`
+    pub fn reject(&self) {
+        self.rejected.store(true, Ordering::Relaxed);
+    }
+
+    fn is_fresh(&self, now: SystemTime) -> bool {
+        !self.rejected.load(Ordering::Relaxed)
+            && self
+                .expires_at
+                .is_some_and(|expires_at| now + REFRESH_AHEAD < expires_at)
+    }
+}
+
+impl Display for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+type CacheEntry = Arc<tokio::sync::Mutex<Option<Arc<AccessToken>>>>;
+
+/// Keeps the access token of every installation and scope until shortly
+/// before it expires. The entry is locked while its token is refreshed, so
+/// concurrent events share a single request to GitHub.
+#[derive(Default)]
+pub struct InstallationTokenCache {
+    entries: Mutex<HashMap<(usize, Option<TokenScope>), CacheEntry>>,
+}
+
+impl InstallationTokenCache {
+    pub async fn get_or_refresh<F>(
+        &self,
+        installation_id: usize,
+        scope: Option<&TokenScope>,
+        refresh: impl FnOnce() -> F,
+    ) -> Result<Arc<AccessToken>, ApiError>
+    where
+        F: Future<Output = Result<AccessToken, ApiError>>,
+    {
+        let entry = self
+            .entries
+            .lock()
+            .expect("installation token cache is never poisoned")
+            .entry((installation_id, scope.cloned()))
+            .or_default()
+            .clone();
+
+        let mut cached = entry.lock().await;
+        if let Some(token) = cached
+            .as_ref()
+            .filter(|token| token.is_fresh(SystemTime::now()))
+        {
+            return Ok(token.clone());
+        }
+
+        tracing::debug!(
+            installation_id,
+            ?scope,
+            "Requesting installation access token"
+        );
+        let token = Arc::new(refresh().await?);
+        *cached = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Drops the tokens of every scope of the installation
+    pub fn forget(&self, installation_id: usize) {
+        self.entries
+            .lock()
+            .expect("installation token cache is never poisoned")
+            .retain(|(id, _), _| *id != installation_id);
+    }
+}
+
+/// Parses the UTC timestamps GitHub uses, like `2016-07-11T22:14:10Z`.
+pub fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+    let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+
+    let mut date = date.splitn(3, '-').map(str::parse::<u64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    if !(1970..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let seconds = days_since_epoch(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Counts the days of the proleptic Gregorian calendar since 1970-01-01
+/// following <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,31 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+    pub default_branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Installation {
+    pub id: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RepositoryReference {
+    pub full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Account {
+    pub login: String,
+}
@@ -0,0 +1,142 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use serde_json::{Error as SerdeError, from_slice};
+
+use super::{
+    installation::{InstallationEvent, InstallationRepositoriesEvent},
+    ping::PingEvent,
+    push::PushEvent,
+    workflow_run::WorkflowRunEvent,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventType {
+    Ping,
+    Push,
+    WorkflowRun,
+    Installation,
+    InstallationRepositories,
+}
+
+impl EventType {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ping" => Some(EventType::Ping),
+            "push" => Some(EventType::Push),
+            "workflow_run" => Some(EventType::WorkflowRun),
+            "installation" => Some(EventType::Installation),
+            "installation_repositories" => Some(EventType::InstallationRepositories),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventType::Ping => "ping",
+            EventType::Push => "push",
+            EventType::WorkflowRun => "workflow_run",
+            EventType::Installation => "installation",
+            EventType::InstallationRepositories => "installation_repositories",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GitHubEvent {
+    Ping(PingEvent),
+    Push(PushEvent),
+    WorkflowRun(WorkflowRunEvent),
+    Installation(InstallationEvent),
+    InstallationRepositories(InstallationRepositoriesEvent),
+    /// An event the application does not know. GitHub sends those if more
+    /// events are enabled for the application than it can handle.
+    Unsupported(String),
+}
+
+impl GitHubEvent {
+    /// Builds the event from the value of the `X-GitHub-Event` header and the
+    /// JSON payload of the request.
+    pub fn from_parts(event_name: &str, payload: &[u8]) -> Result<Self, SerdeError> {
+        let Some(event_type) = EventType::from_name(event_name) else {
+            return Ok(GitHubEvent::Unsupported(event_name.to_owned()));
+        };
+
+        let event = match event_type {
+            EventType::Ping => GitHubEvent::Ping(from_slice(payload)?),
+            EventType::Push => GitHubEvent::Push(from_slice(payload)?),
+            EventType::WorkflowRun => GitHubEvent::WorkflowRun(from_slice(payload)?),
+            EventType::Installation => GitHubEvent::Installation(from_slice(payload)?),
+            EventType::InstallationRepositories => {
+                GitHubEvent::InstallationRepositories(from_slice(payload)?)
+            }
+        };
+
+        Ok(event)
+    }
+
+    pub fn event_type(&self) -> Option<EventType> {
+        match self {
+            GitHubEvent::Ping(_) => Some(EventType::Ping),
+            GitHubEvent::Push(_) => Some(EventType::Push),
+            GitHubEvent::WorkflowRun(_) => Some(EventType::WorkflowRun),
+            GitHubEvent::Installation(_) => Some(EventType::Installation),
+            GitHubEvent::InstallationRepositories(_) => Some(EventType::InstallationRepositories),
+            GitHubEvent::Unsupported(_) => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            GitHubEvent::Unsupported(name) => name,
+            _ => self
+                .event_type()
+                .map(|event_type| event_type.name())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn action(&self) -> Option<&str> {
+        match self {
+            GitHubEvent::WorkflowRun(event) => Some(&event.action),
+            GitHubEvent::Installation(event) => Some(&event.action),
+            GitHubEvent::InstallationRepositories(event) => Some(&event.action),
+            GitHubEvent::Ping(_) | GitHubEvent::Push(_) | GitHubEvent::Unsupported(_) => None,
+        }
+    }
+}
+
+/// Connects the payload types with their [`GitHubEvent`] variant so that
+/// handlers can be registered for the payload type they process.
+pub trait TypedEvent: Sized + Send + 'static {
+    const EVENT_TYPE: EventType;
+
+    fn from_event(event: GitHubEvent) -> Option<Self>;
+}
+
+macro_rules! typed_event {
+    ($payload:ty, $variant:ident) => {
+        impl TypedEvent for $payload {
+            const EVENT_TYPE: EventType = EventType::$variant;
+
+            fn from_event(event: GitHubEvent) -> Option<Self> {
+                match event {
+                    GitHubEvent::$variant(payload) => Some(payload),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+typed_event!(PingEvent, Ping);
+typed_event!(PushEvent, Push);
+typed_event!(WorkflowRunEvent, WorkflowRun);
+typed_event!(InstallationEvent, Installation);
+typed_event!(InstallationRepositoriesEvent, InstallationRepositories);
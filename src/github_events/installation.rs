@@ -0,0 +1,181 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde::Deserialize;
+
+use super::{
+    GithubEventError,
+    common::{Account, RepositoryReference},
+};
+use crate::{
+    application_context::ApplicationContext,
+    github_api::GitHubApiProvider,
+    installations::{InstallationRejection, RegisteredInstallation},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct InstallationEvent {
+    pub(super) action: String,
+    installation: InstallationPayload,
+    #[serde(default)]
+    repositories: Vec<RepositoryReference>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallationRepositoriesEvent {
+    pub(super) action: String,
+    installation: InstallationPayload,
+    repository_selection: String,
+    #[serde(default)]
+    repositories_added: Vec<RepositoryReference>,
+    #[serde(default)]
+    repositories_removed: Vec<RepositoryReference>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallationPayload {
+    id: usize,
+    account: Account,
+    repository_selection: String,
+    #[serde(default)]
+    permissions: BTreeMap<String, String>,
+    suspended_at: Option<String>,
+}
+
+pub struct InstallationHandler<ApiProvider: GitHubApiProvider> {
+    app_context: Arc<ApplicationContext<ApiProvider>>,
+}
+
+impl<ApiProvider: GitHubApiProvider> InstallationHandler<ApiProvider> {
+    pub fn new(app_context: Arc<ApplicationContext<ApiProvider>>) -> Self {
+        Self { app_context }
+    }
+
+    pub fn handle_installation_event(&self, event: InstallationEvent) {
+        let installation_id = event.installation.id;
+        let installations = self.app_context.installations();
+
+        tracing::info!(
+            installation_id,
+            account = event.installation.account.login,
+            action = event.action,
+            "Processing installation event",
+        );
+
+        match event.action.as_str() {
+            "deleted" => {
+                installations.remove(installation_id);
+                self.app_context.purge_installation(installation_id);
+            }
+            "created" => {
+                let repositories = event
+                    .repositories
+                    .into_iter()
+                    .map(|repository| repository.full_name)
+                    .collect();
+
+                let all_repositories = event.installation.repository_selection == "all";
+                let mut installation = Self::registered_installation(event.installation);
+                installation.repositories = (!all_repositories).then_some(repositories);
+                installations.register(installation);
+            }
+            _ => {
+                let mut installation = Self::registered_installation(event.installation);
+                installation.repositories = installations
+                    .get(installation_id)
+                    .and_then(|known| known.repositories);
+
+                if installation.suspended {
+                    self.app_context.purge_installation(installation_id);
+                }
+
+                installations.register(installation);
+            }
+        }
+    }
+
+    pub fn handle_installation_repositories_event(&self, event: InstallationRepositoriesEvent) {
+        let installation_id = event.installation.id;
+        let installations = self.app_context.installations();
+
+        let added = Self::repository_names(event.repositories_added);
+        let removed = Self::repository_names(event.repositories_removed);
+
+        tracing::info!(
+            installation_id,
+            action = event.action,
+            ?added,
+            ?removed,
+            "Processing installation repositories event",
+        );
+
+        if installations.get(installation_id).is_none() {
+            installations.register(Self::registered_installation(event.installation));
+        }
+
+        installations.update_repositories(
+            installation_id,
+            event.repository_selection == "all",
+            &added,
+            &removed,
+        );
+
+        for repository_name in &removed {
+            self.app_context
+                .ready_branches()
+                .remove_repository(repository_name);
+        }
+    }
+
+    fn registered_installation(installation: InstallationPayload) -> RegisteredInstallation {
+        RegisteredInstallation {
+            id: installation.id,
+            account: installation.account.login,
+            repositories: None,
+            permissions: installation.permissions,
+            suspended: installation.suspended_at.is_some(),
+        }
+    }
+
+    fn repository_names(repositories: Vec<RepositoryReference>) -> Vec<String> {
+        repositories
+            .into_iter()
+            .map(|repository| repository.full_name)
+            .collect()
+    }
+}
+
+/// Ensures that an event was sent for an installation and repository the
+/// application serves. Installations that are not in the registry yet are
+/// looked up at GitHub, because the registry is empty after a restart.
+pub async fn verify_installation<ApiProvider: GitHubApiProvider>(
+    app_context: &ApplicationContext<ApiProvider>,
+    installation_id: usize,
+    repository_name: &str,
+) -> Result<(), GithubEventError> {
+    let installations = app_context.installations();
+
+    let installation = match installations.get(installation_id) {
+        Some(installation) => installation,
+        None => {
+            let details = app_context
+                .installation_details(installation_id)
+                .await?
+                .ok_or(InstallationRejection::Unknown(installation_id))?;
+
+            let installation = RegisteredInstallation::from(details);
+            installations.register(installation.clone());
+            installation
+        }
+    };
+
+    Ok(installation.accepts(repository_name)?)
+}
@@ -11,45 +11,123 @@ use std::{fmt::Display, sync::Arc};
 use thiserror::Error;
 
 use axum::{
+    Router,
     body::Bytes,
     extract::State,
     response::{IntoResponse, Response},
+    routing::post,
 };
+use event::GitHubEvent;
 use hyper::{HeaderMap, StatusCode};
-use serde_json::{Error as SerdeError, from_slice};
+use installation::{InstallationEvent, InstallationHandler, InstallationRepositoriesEvent};
+use payload::extract_payload;
+use ping::{PingEvent, handle_ping_event};
+use push::{PushEvent, PushHandler};
+use router::{Actions, Dispatch, EventRouter};
+use serde_json::Error as SerdeError;
 use verifier::{EventSignature, EventVerifier, SignatureConversionError};
-use workflow_run::WorkflowRunHandler;
+use workflow_run::{WorkflowRunEvent, WorkflowRunHandler, WorkflowRunRejection};
 
 use crate::{
     application_context::ApplicationContext,
     github_api::{ApiError, GitHubApiProvider},
     header_map_ext::{GetStrHeaderError, HeaderMapExt},
+    installations::InstallationRejection,
     problem::Problem,
 };
 
+mod common;
+mod event;
+mod installation;
+mod payload;
+mod ping;
+mod policy;
+mod push;
+mod router;
 mod verifier;
 mod workflow_run;
 
-pub async fn event_handler<ApiProvider: GitHubApiProvider>(
-    State(app_context): State<Arc<ApplicationContext<ApiProvider>>>,
+pub fn event_routes<ApiProvider: GitHubApiProvider + 'static>(
+    app_context: Arc<ApplicationContext<ApiProvider>>,
+) -> Router {
+    let state = EventsState {
+        app_context,
+        router: Arc::new(event_router()),
+    };
+
+    Router::new()
+        .route("/github/events", post(event_handler))
+        .with_state(state)
+}
+
+fn event_router<ApiProvider: GitHubApiProvider + 'static>() -> EventRouter<ApiProvider> {
+    EventRouter::new()
+        .on(Actions::All, |_, event: PingEvent| async move {
+            handle_ping_event(event);
+            Ok(())
+        })
+        .on(Actions::All, |app_context, event: PushEvent| async move {
+            PushHandler::new(app_context).handle_event(event).await
+        })
+        .on(
+            Actions::Only(&["completed"]),
+            |app_context, event: WorkflowRunEvent| async move {
+                WorkflowRunHandler::new(app_context)
+                    .handle_event(event)
+                    .await
+            },
+        )
+        .on(
+            Actions::All,
+            |app_context, event: InstallationEvent| async move {
+                InstallationHandler::new(app_context).handle_installation_event(event);
+                Ok(())
+            },
+        )
+        .on(
+            Actions::All,
+            |app_context, event: InstallationRepositoriesEvent| async move {
+                InstallationHandler::new(app_context).handle_installation_repositories_event(event);
+                Ok(())
+            },
+        )
+}
+
+struct EventsState<ApiProvider: GitHubApiProvider> {
+    app_context: Arc<ApplicationContext<ApiProvider>>,
+    router: Arc<EventRouter<ApiProvider>>,
+}
+
+impl<ApiProvider: GitHubApiProvider> Clone for EventsState<ApiProvider> {
+    fn clone(&self) -> Self {
+        Self {
+            app_context: self.app_context.clone(),
+            router: self.router.clone(),
+        }
+    }
+}
+
+async fn event_handler<ApiProvider: GitHubApiProvider + 'static>(
+    State(state): State<EventsState<ApiProvider>>,
     headers: HeaderMap,
     body: Bytes,
-) -> Result<(), GithubEventError> {
+) -> Result<StatusCode, GithubEventError> {
     let signature_header = headers.get_str("X-Hub-Signature-256")?;
     let signature = EventSignature::from_signature_header(signature_header)?;
 
-    let verifier = EventVerifier::new(&app_context.config().github_webhook_secret);
+    let verifier = EventVerifier::new(&state.app_context.config().github_webhook_secret);
 
     if !verifier.payload_is_valid(&body, &signature) {
         return Err(GithubEventError::SignatureInvalid());
     }
 
-    if headers.get_str("X-Github-Event")? == "workflow_run" {
-        let handler = WorkflowRunHandler::new(app_context);
-        handler.handle_event(from_slice(&body)?).await?;
-    }
+    let payload = extract_payload(&headers, &body)?;
+    let event = GitHubEvent::from_parts(headers.get_str("X-Github-Event")?, &payload)?;
 
-    Ok(())
+    match state.router.dispatch(state.app_context, event).await? {
+        Dispatch::Handled => Ok(StatusCode::OK),
+        Dispatch::Unhandled => Ok(StatusCode::ACCEPTED),
+    }
 }
 
 #[derive(Error, Debug)]
@@ -63,11 +141,23 @@ pub enum GithubEventError {
     #[error("Event signature validation failed")]
     SignatureInvalid(),
 
+    #[error("Unsupported content type {0}")]
+    UnsupportedContentType(String),
+
+    #[error("Form encoded event payload is invalid")]
+    InvalidFormPayload(#[from] serde_urlencoded::de::Error),
+
     #[error("Event payload is invalid")]
     InvalidEventPayload(#[from] SerdeError),
 
     #[error("GitHub API request failed")]
     ApiRequestFailed(#[from] ApiError),
+
+    #[error("Event was rejected")]
+    InstallationRejected(#[from] InstallationRejection),
+
+    #[error("Workflow run was rejected")]
+    WorkflowRunRejected(#[from] WorkflowRunRejection),
 }
 
 impl GithubEventError {
@@ -84,34 +174,72 @@ impl GithubEventError {
             GithubEventError::SignatureInvalid() => {
                 tracing::warn!(error = %self, "{message}")
             }
-            GithubEventError::InvalidEventPayload(cause) => {
+            GithubEventError::UnsupportedContentType(_) => {
+                tracing::warn!(error = %self, "{message}")
+            }
+            GithubEventError::InvalidFormPayload(cause) => {
                 tracing::warn!(error = %self, %cause, "{message}")
             }
-            GithubEventError::ApiRequestFailed(cause @ ApiError::Unspecific) => {
-                tracing::error!(error = %self, %cause, "{message}")
+            GithubEventError::InvalidEventPayload(cause) => {
+                tracing::warn!(error = %self, %cause, "{message}")
             }
             GithubEventError::ApiRequestFailed(cause) => {
+                if api_error_status(cause).is_server_error() {
+                    tracing::error!(error = %self, %cause, "{message}")
+                } else {
+                    tracing::warn!(error = %self, %cause, "{message}")
+                }
+            }
+            GithubEventError::InstallationRejected(cause) => {
+                tracing::warn!(error = %self, %cause, "{message}")
+            }
+            GithubEventError::WorkflowRunRejected(cause) => {
                 tracing::warn!(error = %self, %cause, "{message}")
             }
         };
     }
 }
 
+/// Failures of GitHub itself are answered with `502 Bad Gateway` or `503
+/// Service Unavailable`. GitHub does not redeliver failed deliveries on its
+/// own, the status marks them for a manual redelivery. Problems of the
+/// request are passed on with the status GitHub answered. Answers GitHub
+/// truncated can not be processed, redelivering them does not help.
+fn api_error_status(error: &ApiError) -> StatusCode {
+    match error {
+        ApiError::Authorization(_) => StatusCode::FORBIDDEN,
+        ApiError::RepositoryNotFound(_) => StatusCode::NOT_FOUND,
+        ApiError::Conflict(_) => StatusCode::CONFLICT,
+        ApiError::Validation(_) | ApiError::Truncated(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        ApiError::RateLimited(_) => StatusCode::SERVICE_UNAVAILABLE,
+        ApiError::Server(_) | ApiError::Transport(_) | ApiError::UnexpectedStatus(_) => {
+            StatusCode::BAD_GATEWAY
+        }
+        ApiError::Authentication(_) | ApiError::Serialization(_) | ApiError::Unspecific => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
 impl IntoResponse for GithubEventError {
     fn into_response(self) -> Response {
         self.publish_tracing_event();
 
         let status = match self {
             GithubEventError::SignatureInvalid() => StatusCode::UNAUTHORIZED,
-            GithubEventError::ApiRequestFailed(ApiError::Unspecific) => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
+            GithubEventError::UnsupportedContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            GithubEventError::InstallationRejected(_) => StatusCode::FORBIDDEN,
+            GithubEventError::WorkflowRunRejected(_) => StatusCode::FORBIDDEN,
+            GithubEventError::ApiRequestFailed(ref cause) => api_error_status(cause),
             _ => StatusCode::BAD_REQUEST,
         };
 
         let detail: Option<&dyn Display> = match self {
+            GithubEventError::InvalidFormPayload(ref form_error) => Some(form_error),
             GithubEventError::InvalidEventPayload(ref serde_error) => Some(serde_error),
             GithubEventError::ApiRequestFailed(ref api_error) => Some(api_error),
+            GithubEventError::InstallationRejected(ref rejection) => Some(rejection),
+            GithubEventError::WorkflowRunRejected(ref rejection) => Some(rejection),
             _ => None,
         };
 
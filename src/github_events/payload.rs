@@ -0,0 +1,52 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::borrow::Cow;
+
+use hyper::HeaderMap;
+use serde::Deserialize;
+
+use super::GithubEventError;
+use crate::header_map_ext::{GetStrHeaderError, HeaderMapExt};
+
+#[derive(Deserialize)]
+struct FormPayload {
+    payload: String,
+}
+
+/// Returns the JSON payload of a webhook request. Webhooks can be configured
+/// to send the JSON document directly or as the `payload` field of a form. A
+/// request without a `Content-Type` header is taken to carry JSON.
+pub fn extract_payload<'a>(
+    headers: &HeaderMap,
+    body: &'a [u8],
+) -> Result<Cow<'a, [u8]>, GithubEventError> {
+    let content_type = match headers.get_str("Content-Type") {
+        Err(GetStrHeaderError::Missing(_)) => "application/json",
+        content_type => content_type?,
+    };
+
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    match media_type.as_str() {
+        "application/json" => Ok(Cow::Borrowed(body)),
+        "application/x-www-form-urlencoded" => {
+            let form: FormPayload = serde_urlencoded::from_bytes(body)?;
+            Ok(Cow::Owned(form.payload.into_bytes()))
+        }
+        _ => Err(GithubEventError::UnsupportedContentType(
+            content_type.to_owned(),
+        )),
+    }
+}
@@ -0,0 +1,26 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use serde::Deserialize;
+
+/// GitHub sends this event after a webhook was created or when a redelivery
+/// of the ping is requested. It only confirms that the webhook works.
+#[derive(Debug, Deserialize)]
+pub struct PingEvent {
+    zen: Option<String>,
+    hook_id: Option<usize>,
+}
+
+pub fn handle_ping_event(event: PingEvent) {
+    tracing::info!(
+        zen = event.zen,
+        hook_id = event.hook_id,
+        "Received ping event"
+    );
+}
@@ -0,0 +1,43 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use crate::{
+    application_context::ApplicationContext,
+    github_api::{ApiError, CheckConclusion, CheckRunRequest, GitHubApi, GitHubApiProvider},
+    ready_branches::ReadyBranchStatus,
+    repository_policies::PolicyFileProblem,
+};
+
+/// Blocks the ready branch with a failed check run that shows why a policy
+/// file is invalid.
+pub async fn report_invalid_policy<ApiProvider: GitHubApiProvider>(
+    app_context: &ApplicationContext<ApiProvider>,
+    github_api: &impl GitHubApi,
+    repository_name: &str,
+    branch: &str,
+    head_sha: &str,
+    problem: &PolicyFileProblem,
+) -> Result<(), ApiError> {
+    app_context.ready_branches().update_status(
+        repository_name,
+        branch,
+        head_sha,
+        ReadyBranchStatus::InvalidConfiguration,
+    );
+
+    github_api
+        .create_check_run(CheckRunRequest {
+            repository_name: repository_name.to_owned(),
+            head_sha: head_sha.to_owned(),
+            conclusion: CheckConclusion::Failure,
+            title: format!("Invalid {}", problem.file),
+            summary: problem.description.clone(),
+        })
+        .await
+}
@@ -0,0 +1,172 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use super::{
+    GithubEventError,
+    common::{Installation, Repository},
+    installation::verify_installation,
+    policy::report_invalid_policy,
+};
+use crate::{
+    application_context::ApplicationContext,
+    github_api::{
+        AuthenticationMethod, CommitState, CommitStatusRequest, GitHubApi, GitHubApiProvider,
+        TokenScope,
+    },
+    ready_branches::ReadyBranchStatus,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PushEvent {
+    #[serde(rename = "ref")]
+    reference: String,
+    after: String,
+    #[serde(default)]
+    deleted: bool,
+    #[serde(default)]
+    forced: bool,
+    repository: Repository,
+    installation: Installation,
+}
+
+pub struct PushHandler<ApiProvider: GitHubApiProvider> {
+    app_context: Arc<ApplicationContext<ApiProvider>>,
+}
+
+impl<ApiProvider: GitHubApiProvider> PushHandler<ApiProvider> {
+    pub fn new(app_context: Arc<ApplicationContext<ApiProvider>>) -> Self {
+        Self { app_context }
+    }
+
+    pub async fn handle_event(&self, event: PushEvent) -> Result<(), GithubEventError> {
+        verify_installation(
+            &self.app_context,
+            event.installation.id,
+            &event.repository.full_name,
+        )
+        .await?;
+
+        let Some(branch) = event.reference.strip_prefix("refs/heads/") else {
+            return Ok(());
+        };
+
+        let repository_name = event.repository.full_name;
+        let installation_id = event.installation.id;
+        let head_sha = event.after;
+        let ready_branches = self.app_context.ready_branches();
+
+        if branch == event.repository.default_branch {
+            self.app_context.forget_branch_prefix(&repository_name);
+        } else if !self
+            .app_context
+            .may_be_ready_branch(&repository_name, branch)
+        {
+            return Ok(());
+        }
+
+        let auth_method = AuthenticationMethod::AppInstallation {
+            installation_id,
+            scope: Some(token_scope(&repository_name)),
+        };
+        let github_api = self.app_context.github_api(auth_method).await?;
+
+        let repository_policy = self
+            .app_context
+            .repository_policy(
+                installation_id,
+                &github_api,
+                &repository_name,
+                &event.repository.default_branch,
+            )
+            .await?;
+
+        if !repository_policy.policy.is_ready_branch(branch) {
+            return Ok(());
+        }
+
+        if event.deleted {
+            if let Some(removed) = ready_branches.remove(&repository_name, branch) {
+                tracing::info!(
+                    repository_name,
+                    branch,
+                    head_sha = removed.head_sha,
+                    status = ?removed.status,
+                    "Ready branch was deleted",
+                );
+            }
+
+            return Ok(());
+        }
+
+        tracing::info!(
+            repository_name,
+            installation_id,
+            branch,
+            head_sha,
+            "Registering pushed ready branch",
+        );
+
+        let previous =
+            ready_branches.register(installation_id, &repository_name, branch, &head_sha);
+
+        let replaced_tested_commit = previous.filter(|previous| {
+            event.forced
+                && previous.head_sha != head_sha
+                && previous.status != ReadyBranchStatus::WaitingForCi
+        });
+
+        if let Some(replaced) = replaced_tested_commit {
+            tracing::warn!(
+                repository_name,
+                branch,
+                replaced_sha = replaced.head_sha,
+                status = ?replaced.status,
+                "Force push replaced an already tested commit",
+            );
+        }
+
+        if let Some(problem) = &repository_policy.file_problem {
+            report_invalid_policy(
+                &self.app_context,
+                &github_api,
+                &repository_name,
+                branch,
+                &head_sha,
+                problem,
+            )
+            .await?;
+
+            return Ok(());
+        }
+
+        github_api
+            .create_commit_status(CommitStatusRequest {
+                repository_name,
+                sha1: head_sha,
+                state: CommitState::Pending,
+                description: "Waiting for CI".to_owned(),
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// The token for a push reads the policy file and reports the state of the
+/// ready branch as commit status or, for an invalid policy, as check run.
+fn token_scope(repository_name: &str) -> TokenScope {
+    TokenScope::repository(repository_name)
+        .with_permission("contents", "read")
+        .with_permission("statuses", "write")
+        .with_permission("checks", "write")
+}
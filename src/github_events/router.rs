@@ -0,0 +1,111 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use super::{
+    GithubEventError,
+    event::{EventType, GitHubEvent, TypedEvent},
+};
+use crate::application_context::ApplicationContext;
+use crate::github_api::GitHubApiProvider;
+
+type HandlerFuture = Pin<Box<dyn Future<Output = Result<(), GithubEventError>> + Send>>;
+
+type BoxedHandler<ApiProvider> =
+    Box<dyn Fn(Arc<ApplicationContext<ApiProvider>>, GitHubEvent) -> HandlerFuture + Send + Sync>;
+
+/// The actions of an event a handler is interested in
+pub enum Actions {
+    All,
+    Only(&'static [&'static str]),
+}
+
+impl Actions {
+    fn matches(&self, action: Option<&str>) -> bool {
+        match self {
+            Actions::All => true,
+            Actions::Only(actions) => action.is_some_and(|action| actions.contains(&action)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Dispatch {
+    Handled,
+    Unhandled,
+}
+
+struct Route<ApiProvider: GitHubApiProvider> {
+    event_type: EventType,
+    actions: Actions,
+    handler: BoxedHandler<ApiProvider>,
+}
+
+/// Dispatches GitHub events to the handlers that were registered for the
+/// event type and action.
+pub struct EventRouter<ApiProvider: GitHubApiProvider> {
+    routes: Vec<Route<ApiProvider>>,
+}
+
+impl<ApiProvider: GitHubApiProvider + 'static> EventRouter<ApiProvider> {
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    pub fn on<Event, Handler, Fut>(mut self, actions: Actions, handler: Handler) -> Self
+    where
+        Event: TypedEvent,
+        Handler: Fn(Arc<ApplicationContext<ApiProvider>>, Event) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), GithubEventError>> + Send + 'static,
+    {
+        let handler: BoxedHandler<ApiProvider> = Box::new(move |app_context, event| {
+            let event = Event::from_event(event).expect("routes only receive their event type");
+            Box::pin(handler(app_context, event))
+        });
+
+        self.routes.push(Route {
+            event_type: Event::EVENT_TYPE,
+            actions,
+            handler,
+        });
+
+        self
+    }
+
+    pub async fn dispatch(
+        &self,
+        app_context: Arc<ApplicationContext<ApiProvider>>,
+        event: GitHubEvent,
+    ) -> Result<Dispatch, GithubEventError> {
+        let route = self.routes.iter().find(|route| {
+            Some(route.event_type) == event.event_type() && route.actions.matches(event.action())
+        });
+
+        let Some(route) = route else {
+            tracing::info!(
+                event = event.name(),
+                action = event.action(),
+                "No handler registered for event"
+            );
+            return Ok(Dispatch::Unhandled);
+        };
+
+        // GitHub closes the delivery after ten seconds, which drops this
+        // future. The handler runs on its own task so that it still completes,
+        // for example after waiting for the rate limit.
+        let handling = tokio::spawn((route.handler)(app_context, event));
+        match handling.await {
+            Ok(result) => result?,
+            Err(error) => std::panic::resume_unwind(error.into_panic()),
+        }
+
+        Ok(Dispatch::Handled)
+    }
+}
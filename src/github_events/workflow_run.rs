@@ -9,18 +9,29 @@
 
 use std::sync::Arc;
 
+use super::{
+    GithubEventError,
+    common::{Account, Installation, Repository, RepositoryReference},
+    installation::verify_installation,
+    policy::report_invalid_policy,
+};
 use crate::{
+    application_config::{MergePolicy, MergeStrategy},
     application_context::ApplicationContext,
     github_api::{
-        ApiError, AuthenticationMethod, BranchComparison, BranchComparisonRequest, GitHubApi,
-        GitHubApiProvider, UpdateReferenceRequest,
+        ApiError, AuthenticationMethod, BranchComparison, BranchComparisonRequest, CommitState,
+        CommitStatusRequest, DeleteReferenceRequest, GitHubApi, GitHubApiProvider,
+        MergeBranchRequest, MergeResult, TokenScope, UpdateReferenceRequest, WorkflowRunSummary,
+        WorkflowRunsRequest,
     },
+    ready_branches::ReadyBranchStatus,
 };
 use serde::Deserialize;
+use thiserror::Error;
 
 #[derive(Debug, Deserialize)]
 pub struct WorkflowRunEvent {
-    action: String,
+    pub(super) action: String,
     workflow_run: WorkflowRun,
     repository: Repository,
     installation: Installation,
@@ -28,23 +39,57 @@ pub struct WorkflowRunEvent {
 
 #[derive(Debug, Deserialize)]
 pub struct WorkflowRun {
+    id: u64,
+    name: String,
+    /// Path of the workflow file. Reusable workflows have the referenced
+    /// revision appended like `.github/workflows/ci.yml@refs/heads/main`.
+    path: String,
+    workflow_id: u64,
+    run_attempt: u32,
+    status: String,
     conclusion: Option<String>,
     head_branch: Option<String>,
     head_sha: String,
+    /// Is `null` if the repository of a fork was deleted
+    head_repository: Option<RepositoryReference>,
+    event: String,
+    actor: Option<Account>,
+    triggering_actor: Option<Account>,
+    #[serde(default)]
+    pull_requests: Vec<PullRequestReference>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct Repository {
-    full_name: String,
-    default_branch: String,
+pub struct PullRequestReference {
+    number: u64,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Installation {
-    id: usize,
+#[derive(Error, Debug)]
+pub enum WorkflowRunRejection {
+    #[error("Workflow run has no head repository")]
+    MissingHeadRepository,
+
+    #[error("Head repository {0} differs from the base repository {1}")]
+    ForeignHeadRepository(String, String),
+
+    #[error("Workflow runs triggered by {0} events are not allowed")]
+    TriggeringEventNotAllowed(String),
+}
+
+/// The result of all workflow runs the merge policy requires
+enum Verdict {
+    Passed,
+    Failed(String),
+    Pending,
 }
 
-pub struct WorkflowRunHandler<ApiProvider> {
+struct Outcome {
+    status: ReadyBranchStatus,
+    state: CommitState,
+    description: String,
+}
+
+pub struct WorkflowRunHandler<ApiProvider: GitHubApiProvider> {
     app_context: Arc<ApplicationContext<ApiProvider>>,
 }
 
@@ -53,59 +98,464 @@ impl<ApiProvider: GitHubApiProvider> WorkflowRunHandler<ApiProvider> {
         Self { app_context }
     }
 
-    pub async fn handle_event(&self, event: WorkflowRunEvent) -> Result<(), ApiError> {
-        if self.is_successful(&event) {
-            let repository_name = event.repository.full_name;
-            let installation_id = event.installation.id;
-            let default_branch = event.repository.default_branch;
-            let head_sha = event.workflow_run.head_sha;
+    pub async fn handle_event(&self, event: WorkflowRunEvent) -> Result<(), GithubEventError> {
+        verify_installation(
+            &self.app_context,
+            event.installation.id,
+            &event.repository.full_name,
+        )
+        .await?;
+
+        let repository_name = event.repository.full_name;
+        let installation_id = event.installation.id;
+        let default_branch = event.repository.default_branch;
+        let workflow_run = event.workflow_run;
+
+        let Some(head_branch) = workflow_run.head_branch.clone() else {
+            return Ok(());
+        };
+
+        if !self
+            .app_context
+            .may_be_ready_branch(&repository_name, &head_branch)
+        {
+            return Ok(());
+        }
+
+        let auth_method = AuthenticationMethod::AppInstallation {
+            installation_id,
+            scope: Some(token_scope(&repository_name)),
+        };
+        let github_api = self.app_context.github_api(auth_method).await?;
+
+        let repository_policy = self
+            .app_context
+            .repository_policy(
+                installation_id,
+                &github_api,
+                &repository_name,
+                &default_branch,
+            )
+            .await?;
+        let policy = &repository_policy.policy;
+
+        if !policy.is_ready_branch(&head_branch) {
+            return Ok(());
+        }
+
+        // Only runs on ready branches are trusted or refused, all others are
+        // none of the application's business
+        self.verify_origin(&repository_name, &workflow_run)?;
+
+        if let Some(problem) = &repository_policy.file_problem {
+            report_invalid_policy(
+                &self.app_context,
+                &github_api,
+                &repository_name,
+                &head_branch,
+                &workflow_run.head_sha,
+                problem,
+            )
+            .await?;
+
+            return Ok(());
+        }
+
+        if !policy.required_workflows.is_empty()
+            && !Self::is_required(policy, &workflow_run.name, &workflow_run.path)
+        {
+            tracing::info!(
+                repository_name,
+                workflow = workflow_run.name,
+                path = workflow_run.path,
+                "Ignoring workflow run that is not required",
+            );
+            return Ok(());
+        }
+
+        let verdict = Self::evaluate(&github_api, policy, &repository_name, &workflow_run).await?;
+        let head_sha = workflow_run.head_sha;
 
-            if let Some(head_branch) = event.workflow_run.head_branch {
+        match verdict {
+            Verdict::Passed => {}
+            Verdict::Pending => {
                 tracing::info!(
                     repository_name,
-                    installation_id,
-                    default_branch,
                     head_branch,
                     head_sha,
-                    "Processing successful workflow run event",
+                    "Waiting for further required workflow runs",
                 );
+                return Ok(());
+            }
+            Verdict::Failed(description) => {
+                let outcome = Outcome {
+                    status: ReadyBranchStatus::CiFailed,
+                    state: CommitState::Failure,
+                    description,
+                };
+                self.report_outcome(
+                    &github_api,
+                    &repository_name,
+                    &head_branch,
+                    head_sha,
+                    outcome,
+                )
+                .await?;
 
-                let auth_method = AuthenticationMethod::AppInstallation { installation_id };
-                let github_api = self.app_context.github_api(auth_method).await?;
+                return Ok(());
+            }
+        }
 
-                let branch_comparison_request = BranchComparisonRequest {
-                    repository_name: repository_name.clone(),
-                    base_branch: default_branch.clone(),
-                    head_branch,
+        tracing::info!(
+            repository_name,
+            installation_id,
+            default_branch,
+            head_branch,
+            head_sha,
+            merge_strategy = ?policy.merge_strategy,
+            "Processing successful workflow run event",
+        );
+
+        // Signature verification and fast forwards judge the same comparison
+        let mut comparison = None;
+
+        if policy.require_signed_commits {
+            let signed =
+                Self::compare(&github_api, &repository_name, &default_branch, &head_branch).await?;
+
+            if let Some(outcome) = Self::verify_signatures(&signed) {
+                self.report_outcome(
+                    &github_api,
+                    &repository_name,
+                    &head_branch,
+                    head_sha,
+                    outcome,
+                )
+                .await?;
+
+                return Ok(());
+            }
+
+            comparison = Some(signed);
+        }
+
+        let outcome = match policy.merge_strategy {
+            MergeStrategy::FastForward => {
+                let comparison = match comparison {
+                    Some(comparison) => comparison,
+                    None => {
+                        Self::compare(&github_api, &repository_name, &default_branch, &head_branch)
+                            .await?
+                    }
                 };
 
-                let BranchComparison {
-                    ahead_by,
-                    behind_by,
-                } = github_api
-                    .compare_commits(branch_comparison_request)
-                    .await?;
-
-                tracing::info!(ahead_by, behind_by, "Branch comparison was successful");
-
-                if ahead_by == 1 && behind_by == 0 {
-                    tracing::info!("Performing fast forward merge");
-                    let reference_update = UpdateReferenceRequest {
-                        repository_name,
-                        reference: format!("heads/{default_branch}"),
-                        sha1: head_sha,
-                        force: false,
-                    };
-                    github_api.update_reference(reference_update).await?;
-                }
+                Self::fast_forward(
+                    &github_api,
+                    &repository_name,
+                    &default_branch,
+                    &head_sha,
+                    &comparison,
+                )
+                .await?
+            }
+            MergeStrategy::Merge => {
+                Self::merge(&github_api, &repository_name, &default_branch, &head_branch).await?
+            }
+        };
+
+        let merged = outcome.status == ReadyBranchStatus::Merged;
+
+        self.report_outcome(
+            &github_api,
+            &repository_name,
+            &head_branch,
+            head_sha,
+            outcome,
+        )
+        .await?;
+
+        if merged && policy.delete_branch {
+            tracing::info!(repository_name, head_branch, "Deleting merged ready branch");
+            github_api
+                .delete_reference(DeleteReferenceRequest {
+                    repository_name,
+                    reference: format!("heads/{head_branch}"),
+                })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn compare(
+        github_api: &impl GitHubApi,
+        repository_name: &str,
+        default_branch: &str,
+        head_branch: &str,
+    ) -> Result<BranchComparison, ApiError> {
+        github_api
+            .compare_commits(BranchComparisonRequest {
+                repository_name: repository_name.to_owned(),
+                base_branch: default_branch.to_owned(),
+                head_branch: head_branch.to_owned(),
+            })
+            .await
+    }
+
+    /// Refuses the merge if a commit of the ready branch has no verified
+    /// signature.
+    fn verify_signatures(comparison: &BranchComparison) -> Option<Outcome> {
+        comparison
+            .unverified_commits()
+            .next()
+            .map(|commit| Outcome {
+                status: ReadyBranchStatus::NotMergeable,
+                state: CommitState::Failure,
+                description: format!(
+                    "Commit {} has no verified signature",
+                    &commit.sha[..commit.sha.len().min(7)]
+                ),
+            })
+    }
+
+    /// Moves the default branch to the head of the ready branch if the ready
+    /// branch contains exactly one commit on top of the default branch.
+    async fn fast_forward(
+        github_api: &impl GitHubApi,
+        repository_name: &str,
+        default_branch: &str,
+        head_sha: &str,
+        comparison: &BranchComparison,
+    ) -> Result<Outcome, ApiError> {
+        let BranchComparison {
+            ahead_by,
+            behind_by,
+            ..
+        } = *comparison;
+
+        tracing::info!(ahead_by, behind_by, "Branch comparison was successful");
+
+        if ahead_by == 1 && behind_by == 0 {
+            tracing::info!("Performing fast forward merge");
+            let reference_update = UpdateReferenceRequest {
+                repository_name: repository_name.to_owned(),
+                reference: format!("heads/{default_branch}"),
+                sha1: head_sha.to_owned(),
+                force: false,
+            };
+            github_api.update_reference(reference_update).await?;
+
+            Ok(Outcome {
+                status: ReadyBranchStatus::Merged,
+                state: CommitState::Success,
+                description: format!("Merged into {default_branch}"),
+            })
+        } else {
+            Ok(Outcome {
+                status: ReadyBranchStatus::NotMergeable,
+                state: CommitState::Failure,
+                description: format!("Can not fast forward {default_branch}"),
+            })
+        }
+    }
+
+    async fn merge(
+        github_api: &impl GitHubApi,
+        repository_name: &str,
+        default_branch: &str,
+        head_branch: &str,
+    ) -> Result<Outcome, ApiError> {
+        let merge_request = MergeBranchRequest {
+            repository_name: repository_name.to_owned(),
+            base_branch: default_branch.to_owned(),
+            head_branch: head_branch.to_owned(),
+        };
+
+        match github_api.merge_branch(merge_request).await? {
+            MergeResult::Merged => Ok(Outcome {
+                status: ReadyBranchStatus::Merged,
+                state: CommitState::Success,
+                description: format!("Merged into {default_branch}"),
+            }),
+            MergeResult::Conflict => Ok(Outcome {
+                status: ReadyBranchStatus::NotMergeable,
+                state: CommitState::Failure,
+                description: format!("Merge conflict with {default_branch}"),
+            }),
+        }
+    }
+
+    async fn report_outcome(
+        &self,
+        github_api: &impl GitHubApi,
+        repository_name: &str,
+        head_branch: &str,
+        head_sha: String,
+        outcome: Outcome,
+    ) -> Result<(), ApiError> {
+        self.app_context.ready_branches().update_status(
+            repository_name,
+            head_branch,
+            &head_sha,
+            outcome.status,
+        );
+
+        github_api
+            .create_commit_status(CommitStatusRequest {
+                repository_name: repository_name.to_owned(),
+                sha1: head_sha,
+                state: outcome.state,
+                description: outcome.description,
+            })
+            .await
+    }
+
+    /// Makes sure that the workflow run tested code of the repository itself.
+    /// Otherwise a fork could get a commit merged by naming its branch like a
+    /// ready branch.
+    fn verify_origin(
+        &self,
+        repository_name: &str,
+        workflow_run: &WorkflowRun,
+    ) -> Result<(), WorkflowRunRejection> {
+        let rejection = match &workflow_run.head_repository {
+            None => Some(WorkflowRunRejection::MissingHeadRepository),
+            Some(head_repository) if head_repository.full_name != repository_name => {
+                Some(WorkflowRunRejection::ForeignHeadRepository(
+                    head_repository.full_name.clone(),
+                    repository_name.to_owned(),
+                ))
             }
+            Some(_) if !self.is_allowed_trigger(&workflow_run.event) => Some(
+                WorkflowRunRejection::TriggeringEventNotAllowed(workflow_run.event.clone()),
+            ),
+            Some(_) => None,
+        };
+
+        if let Some(rejection) = rejection {
+            let login = |account: &Option<Account>| account.as_ref().map(|a| a.login.clone());
+            let pull_requests: Vec<u64> = workflow_run
+                .pull_requests
+                .iter()
+                .map(|pull_request| pull_request.number)
+                .collect();
+
+            tracing::warn!(
+                repository_name,
+                head_branch = workflow_run.head_branch,
+                head_sha = workflow_run.head_sha,
+                actor = login(&workflow_run.actor),
+                triggering_actor = login(&workflow_run.triggering_actor),
+                ?pull_requests,
+                %rejection,
+                "Refusing untrusted workflow run",
+            );
+
+            return Err(rejection);
         }
 
         Ok(())
     }
 
-    fn is_successful(&self, event: &WorkflowRunEvent) -> bool {
-        event.action == "completed"
-            && event.workflow_run.conclusion.as_deref().unwrap_or("") == "success"
+    fn is_allowed_trigger(&self, event: &str) -> bool {
+        self.app_context
+            .config()
+            .allowed_trigger_events
+            .iter()
+            .any(|allowed| allowed == event)
     }
+
+    /// Decides whether the required workflows of the policy succeeded for the
+    /// commit of the workflow run. Without required workflows the run itself
+    /// decides.
+    async fn evaluate(
+        github_api: &impl GitHubApi,
+        policy: &MergePolicy,
+        repository_name: &str,
+        workflow_run: &WorkflowRun,
+    ) -> Result<Verdict, ApiError> {
+        if !Self::is_accepted(policy, workflow_run.conclusion.as_deref()) {
+            return Ok(Verdict::Failed(format!(
+                "Workflow {} did not succeed",
+                workflow_run.name
+            )));
+        }
+
+        if policy.required_workflows.is_empty() {
+            return Ok(Verdict::Passed);
+        }
+
+        let mut runs = github_api
+            .list_workflow_runs(WorkflowRunsRequest {
+                repository_name: repository_name.to_owned(),
+                head_sha: workflow_run.head_sha.clone(),
+            })
+            .await?;
+
+        // The listing may not reflect the completion of this run yet
+        runs.retain(|run| run.id != workflow_run.id);
+        runs.push(WorkflowRunSummary {
+            id: workflow_run.id,
+            workflow_id: workflow_run.workflow_id,
+            name: workflow_run.name.clone(),
+            path: workflow_run.path.clone(),
+            run_attempt: workflow_run.run_attempt,
+            status: workflow_run.status.clone(),
+            conclusion: workflow_run.conclusion.clone(),
+        });
+
+        let mut verdict = Verdict::Passed;
+
+        for required in &policy.required_workflows {
+            let latest_run = runs
+                .iter()
+                .filter(|run| Self::matches(required, &run.name, &run.path))
+                .max_by_key(|run| (run.id, run.run_attempt));
+
+            match latest_run {
+                Some(run) if run.status != "completed" => verdict = Verdict::Pending,
+                Some(run) if !Self::is_accepted(policy, run.conclusion.as_deref()) => {
+                    return Ok(Verdict::Failed(format!(
+                        "Workflow {} did not succeed",
+                        run.name
+                    )));
+                }
+                Some(_) => {}
+                None => verdict = Verdict::Pending,
+            }
+        }
+
+        Ok(verdict)
+    }
+
+    fn is_required(policy: &MergePolicy, name: &str, path: &str) -> bool {
+        policy
+            .required_workflows
+            .iter()
+            .any(|required| Self::matches(required, name, path))
+    }
+
+    fn matches(required: &str, name: &str, path: &str) -> bool {
+        let path = path.split_once('@').map_or(path, |(path, _)| path);
+        required == name || required == path
+    }
+
+    fn is_accepted(policy: &MergePolicy, conclusion: Option<&str>) -> bool {
+        conclusion.is_some_and(|conclusion| {
+            policy
+                .accepted_conclusions
+                .iter()
+                .any(|accepted| accepted == conclusion)
+        })
+    }
+}
+
+/// The token for a workflow run moves the default branch, reports the outcome
+/// as commit status or check run and looks up the other runs of the commit.
+fn token_scope(repository_name: &str) -> TokenScope {
+    TokenScope::repository(repository_name)
+        .with_permission("contents", "write")
+        .with_permission("statuses", "write")
+        .with_permission("checks", "write")
+        .with_permission("actions", "read")
 }
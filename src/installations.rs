@@ -0,0 +1,124 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Mutex, MutexGuard},
+};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::github_api::InstallationDetails;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegisteredInstallation {
+    pub id: usize,
+    pub account: String,
+    /// `None` if the installation grants access to all repositories of the
+    /// account or if the list of repositories is not known. The latter
+    /// happens if the installation was looked up after a restart.
+    pub repositories: Option<BTreeSet<String>>,
+    pub permissions: BTreeMap<String, String>,
+    pub suspended: bool,
+}
+
+impl RegisteredInstallation {
+    pub fn accepts(&self, repository_name: &str) -> Result<(), InstallationRejection> {
+        if self.suspended {
+            return Err(InstallationRejection::Suspended(self.id));
+        }
+
+        match &self.repositories {
+            Some(repositories) if !repositories.contains(repository_name) => Err(
+                InstallationRejection::RepositoryNotInstalled(self.id, repository_name.to_owned()),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<InstallationDetails> for RegisteredInstallation {
+    fn from(details: InstallationDetails) -> Self {
+        RegisteredInstallation {
+            id: details.id,
+            account: details.account,
+            repositories: None,
+            permissions: details.permissions,
+            suspended: details.suspended,
+        }
+    }
+}
+
+/// Keeps track of the installations of the application and the repositories
+/// they grant access to.
+#[derive(Default)]
+pub struct InstallationRegistry {
+    installations: Mutex<BTreeMap<usize, RegisteredInstallation>>,
+}
+
+impl InstallationRegistry {
+    pub fn get(&self, installation_id: usize) -> Option<RegisteredInstallation> {
+        self.lock().get(&installation_id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<RegisteredInstallation> {
+        self.lock().values().cloned().collect()
+    }
+
+    pub fn register(&self, installation: RegisteredInstallation) {
+        self.lock().insert(installation.id, installation);
+    }
+
+    pub fn remove(&self, installation_id: usize) -> Option<RegisteredInstallation> {
+        self.lock().remove(&installation_id)
+    }
+
+    /// Applies a change of the installation's repository list. If the
+    /// previous list is not known the installation stays unrestricted,
+    /// because the change alone does not tell which repositories are left.
+    pub fn update_repositories(
+        &self,
+        installation_id: usize,
+        all_repositories: bool,
+        added: &[String],
+        removed: &[String],
+    ) {
+        let mut installations = self.lock();
+
+        let Some(installation) = installations.get_mut(&installation_id) else {
+            return;
+        };
+
+        if all_repositories {
+            installation.repositories = None;
+        } else if let Some(repositories) = installation.repositories.as_mut() {
+            repositories.extend(added.iter().cloned());
+            repositories.retain(|repository| !removed.contains(repository));
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<usize, RegisteredInstallation>> {
+        self.installations
+            .lock()
+            .expect("installation registry is never poisoned")
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum InstallationRejection {
+    #[error("Installation {0} is not known")]
+    Unknown(usize),
+
+    #[error("Installation {0} is suspended")]
+    Suspended(usize),
+
+    #[error("Repository {1} is not part of installation {0}")]
+    RepositoryNotInstalled(usize, String),
+}
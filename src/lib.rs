@@ -7,36 +7,100 @@
  * received a copy of the license along with this program.
  */
 
-use std::{error::Error, sync::Arc};
+use std::{error::Error, pin::Pin, sync::Arc};
 
-pub use application_config::ApplicationConfig;
+use admin::{installation_handler, installations_handler, require_admin_token};
+pub use application_config::{
+    ApplicationConfig, CONFIG_FILE_VARIABLE, ConfigError, ConfigProblem, GitHubBackend,
+    HttpClientConfig, MergePolicy, MergeStrategy, OrganisationPolicy, PrivateKeySource,
+    ServerConfig, TlsConfig,
+};
 use application_context::ApplicationContext;
-use axum::{Router, routing::post};
-use github_api::{GitHubApiProvider, GitHubRestApiProvider};
-use github_events::event_handler;
+use axum::{Router, middleware, routing::get};
+pub use check_config::{Check, CheckReport, check_config};
+pub use config_reload::{ConfigReloader, ReloadError};
+use github_api::{GitHubApiProvider, GitHubGraphQlApiProvider, GitHubRestApiProvider};
+use github_events::event_routes;
+use metrics::metrics_handler;
+pub use server::{ServerError, serve, serve_checking_tls_files};
+use status::status_handler;
 use tower_http::trace::TraceLayer;
 
 pub mod github_api;
 
+mod admin;
 mod application_config;
 mod application_context;
+mod check_config;
+mod config_reload;
 mod github_events;
 mod header_map_ext;
+mod installations;
+mod metrics;
 mod problem;
+mod ready_branches;
+mod repository_policies;
+mod server;
+mod status;
 
-pub fn build_app(config: ApplicationConfig) -> Result<Router, Box<dyn Error>> {
-    let github_api = GitHubRestApiProvider::new(&config)?;
-    Ok(build_app_with_api(config, github_api))
+/// Reloads the configuration of the running application, see
+/// [`ConfigReloader::watch`]
+pub type ConfigWatch = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Builds the application with the GitHub API backend the configuration
+/// selects.
+pub async fn build_app(config: ApplicationConfig) -> Result<(Router, ConfigWatch), Box<dyn Error>> {
+    match config.github_backend {
+        GitHubBackend::Rest => {
+            let github_api = GitHubRestApiProvider::new(&config)?;
+            github_api.check_server_version().await;
+            Ok(watched(build_app_with_api(config, github_api)?))
+        }
+        GitHubBackend::GraphQl => {
+            let github_api = GitHubGraphQlApiProvider::new(&config)?;
+            github_api.check_server_version().await;
+            Ok(watched(build_app_with_api(config, github_api)?))
+        }
+    }
+}
+
+fn watched<ApiProvider: GitHubApiProvider + 'static>(
+    (router, config_reloader): (Router, ConfigReloader<ApiProvider>),
+) -> (Router, ConfigWatch) {
+    (router, Box::pin(config_reloader.watch()))
 }
 
+/// Builds the application together with the handle that reloads its
+/// configuration. Fails if the credentials of the configuration can not be
+/// loaded.
 pub fn build_app_with_api<ApiProvider: GitHubApiProvider + 'static>(
     config: ApplicationConfig,
     github_api_provider: ApiProvider,
-) -> Router {
-    let app_context = Arc::new(ApplicationContext::new(config, github_api_provider));
+) -> Result<(Router, ConfigReloader<ApiProvider>), Box<dyn Error>> {
+    let credentials = github_api_provider
+        .load_credentials(&config)
+        .map_err(|error| error as Box<dyn Error>)?;
+    let app_context = Arc::new(ApplicationContext::new(
+        config,
+        github_api_provider,
+        Arc::new(credentials),
+    ));
+
+    let admin_routes = Router::new()
+        .route("/admin/installations", get(installations_handler))
+        .route("/admin/installations/{id}", get(installation_handler))
+        .route_layer(middleware::from_fn_with_state(
+            app_context.clone(),
+            require_admin_token::<ApiProvider>,
+        ));
+
+    let router = Router::new()
+        .route("/status", get(status_handler))
+        .route("/metrics", get(metrics_handler))
+        .merge(admin_routes)
+        .with_state(app_context.clone())
+        .merge(event_routes(app_context.clone()))
+        .layer(TraceLayer::new_for_http());
 
-    Router::new()
-        .route("/github/events", post(event_handler))
-        .with_state(app_context)
-        .layer(TraceLayer::new_for_http())
+    Ok((router, ConfigReloader::new(app_context)))
 }
@@ -7,37 +7,72 @@
  * received a copy of the license along with this program.
  */
 
-use std::net::SocketAddr;
+use std::{env, error::Error as _, process::ExitCode};
 use thiserror::Error;
 
-use koritsu_app::{ApplicationConfig, build_app};
-use tokio::net::TcpListener;
+use koritsu_app::{ApplicationConfig, ServerError, build_app, check_config, serve};
 use tracing_subscriber::{
     EnvFilter, fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt,
 };
 
 #[tokio::main]
-async fn main() -> Result<(), StartupError> {
+async fn main() -> ExitCode {
     init_tracing();
 
-    let config = ApplicationConfig::from_env()?;
-    let app = build_app(config).map_err(StartupError::ApplicationInitialization)?;
+    let result = match env::args().nth(1).as_deref() {
+        None => run().await,
+        Some("check-config") => check().await,
+        Some(command) => Err(StartupError::UnknownCommand(command.to_owned())),
+    };
 
-    let address = "127.0.0.1:8080".parse::<SocketAddr>()?;
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            report(&error);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    let listener = TcpListener::bind(address)
+async fn run() -> Result<(), StartupError> {
+    let config = ApplicationConfig::load()?;
+    let server_config = config.server.clone();
+    let (app, config_watch) = build_app(config)
         .await
-        .map_err(|error| StartupError::UnableToBindToSocket(address, error))?;
+        .map_err(StartupError::ApplicationInitialization)?;
 
-    tracing::info!("listening on {}", address);
-
-    axum::serve(listener, app)
-        .await
-        .map_err(|error| StartupError::CouldNotServeApplication(address, error))?;
+    tokio::spawn(config_watch);
+    serve(app, &server_config).await?;
 
     Ok(())
 }
 
+/// Validates the configuration against GitHub without starting the server
+async fn check() -> Result<(), StartupError> {
+    let config = ApplicationConfig::load()?;
+    let report = check_config(config).await;
+
+    print!("{report}");
+
+    if report.passed() {
+        Ok(())
+    } else {
+        Err(StartupError::CheckFailed)
+    }
+}
+
+/// Prints the error together with its causes, because the causes usually
+/// tell what needs to be fixed.
+fn report(error: &StartupError) {
+    eprintln!("Error: {error}");
+
+    let mut source = error.source();
+    while let Some(cause) = source {
+        eprintln!("Caused by: {cause}");
+        source = cause.source();
+    }
+}
+
 fn init_tracing() {
     let default_filter = |_| {
         format!(
@@ -63,17 +98,17 @@ fn init_tracing() {
 #[derive(Error, Debug)]
 enum StartupError {
     #[error("Could not load application configuration")]
-    Configuration(#[from] std::env::VarError),
+    Configuration(#[from] koritsu_app::ConfigError),
 
-    #[error(transparent)]
-    ApplicationInitialization(Box<dyn std::error::Error>),
+    #[error("Could not initialize the application")]
+    ApplicationInitialization(#[source] Box<dyn std::error::Error>),
 
-    #[error("Invalid socket address")]
-    InvalidSocketAddress(#[from] std::net::AddrParseError),
+    #[error(transparent)]
+    Server(#[from] ServerError),
 
-    #[error("Unable to bind to socket {0}")]
-    UnableToBindToSocket(SocketAddr, #[source] std::io::Error),
+    #[error("Unknown command {0}, the only command is check-config")]
+    UnknownCommand(String),
 
-    #[error("Unable to serve application at socket {0}")]
-    CouldNotServeApplication(SocketAddr, #[source] std::io::Error),
+    #[error("The configuration check failed")]
+    CheckFailed,
 }
@@ -0,0 +1,112 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{fmt::Write, sync::Arc};
+
+use axum::{extract::State, http::header, response::IntoResponse};
+
+use crate::{
+    application_context::ApplicationContext,
+    github_api::{GitHubApiProvider, RateLimitStatus, ResponseCacheStats},
+};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Serves the metrics in the Prometheus text format
+pub async fn metrics_handler<ApiProvider: GitHubApiProvider>(
+    State(app_context): State<Arc<ApplicationContext<ApiProvider>>>,
+) -> impl IntoResponse {
+    let mut metrics = String::new();
+    render_rate_limits(&mut metrics, &app_context.rate_limits());
+    render_response_cache(&mut metrics, app_context.response_cache_stats());
+
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], metrics)
+}
+
+/// Name, help text and value of a gauge
+type Gauge = (
+    &'static str,
+    &'static str,
+    fn(&RateLimitStatus) -> Option<u64>,
+);
+
+fn render_rate_limits(metrics: &mut String, rate_limits: &[RateLimitStatus]) {
+    let gauges: [Gauge; 4] = [
+        (
+            "koritsu_github_rate_limit_limit",
+            "Requests GitHub allows in the current rate limit window",
+            |status| status.limit,
+        ),
+        (
+            "koritsu_github_rate_limit_remaining",
+            "Requests left in the current rate limit window",
+            |status| status.remaining,
+        ),
+        (
+            "koritsu_github_rate_limit_reset_timestamp_seconds",
+            "When the current rate limit window ends",
+            |status| status.reset_at,
+        ),
+        (
+            "koritsu_github_rate_limit_paused_until_timestamp_seconds",
+            "Until when requests are held back because GitHub asked to slow down",
+            |status| status.paused_until,
+        ),
+    ];
+
+    for (name, help, value) in gauges {
+        writeln!(metrics, "# HELP {name} {help}").expect("writing to a string never fails");
+        writeln!(metrics, "# TYPE {name} gauge").expect("writing to a string never fails");
+
+        for status in rate_limits {
+            if let Some(value) = value(status) {
+                writeln!(metrics, "{name}{{{}}} {value}", labels(status))
+                    .expect("writing to a string never fails");
+            }
+        }
+    }
+}
+
+fn render_response_cache(metrics: &mut String, stats: ResponseCacheStats) {
+    let values = [
+        (
+            "koritsu_github_response_cache_entries",
+            "GitHub responses kept for conditional requests",
+            "gauge",
+            stats.entries as u64,
+        ),
+        (
+            "koritsu_github_response_cache_bytes",
+            "Size of the content of the cached GitHub responses",
+            "gauge",
+            stats.bytes as u64,
+        ),
+        (
+            "koritsu_github_response_cache_hits_total",
+            "Requests GitHub answered with 304 Not Modified",
+            "counter",
+            stats.hits,
+        ),
+    ];
+
+    for (name, help, kind, value) in values {
+        writeln!(metrics, "# HELP {name} {help}").expect("writing to a string never fails");
+        writeln!(metrics, "# TYPE {name} {kind}").expect("writing to a string never fails");
+        writeln!(metrics, "{name} {value}").expect("writing to a string never fails");
+    }
+}
+
+fn labels(status: &RateLimitStatus) -> String {
+    match status.installation_id {
+        Some(installation_id) => {
+            format!("subject=\"installation\",installation_id=\"{installation_id}\"")
+        }
+        None => "subject=\"app\"".to_owned(),
+    }
+}
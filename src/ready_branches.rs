@@ -0,0 +1,111 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadyBranch {
+    pub installation_id: usize,
+    pub repository_name: String,
+    pub branch: String,
+    pub head_sha: String,
+    pub status: ReadyBranchStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadyBranchStatus {
+    WaitingForCi,
+    CiFailed,
+    NotMergeable,
+    Merged,
+    /// The configuration file of the repository is invalid
+    InvalidConfiguration,
+}
+
+/// Keeps track of all ready branches the application knows about.
+///
+/// The registry only lives in memory. After a restart it gets filled again
+/// by the next push and workflow run events.
+#[derive(Default)]
+pub struct ReadyBranchRegistry {
+    branches: Mutex<BTreeMap<(String, String), ReadyBranch>>,
+}
+
+impl ReadyBranchRegistry {
+    /// Registers the branch with a new head commit and returns the previous
+    /// state of the branch if it was already known.
+    pub fn register(
+        &self,
+        installation_id: usize,
+        repository_name: &str,
+        branch: &str,
+        head_sha: &str,
+    ) -> Option<ReadyBranch> {
+        let ready_branch = ReadyBranch {
+            installation_id,
+            repository_name: repository_name.to_owned(),
+            branch: branch.to_owned(),
+            head_sha: head_sha.to_owned(),
+            status: ReadyBranchStatus::WaitingForCi,
+        };
+
+        self.lock()
+            .insert(Self::key(repository_name, branch), ready_branch)
+    }
+
+    pub fn remove(&self, repository_name: &str, branch: &str) -> Option<ReadyBranch> {
+        self.lock().remove(&Self::key(repository_name, branch))
+    }
+
+    pub fn remove_repository(&self, repository_name: &str) {
+        self.lock()
+            .retain(|(repository, _), _| repository != repository_name);
+    }
+
+    pub fn remove_installation(&self, installation_id: usize) {
+        self.lock()
+            .retain(|_, ready_branch| ready_branch.installation_id != installation_id);
+    }
+
+    /// Updates the status of a known branch. Updates for a commit that is no
+    /// longer the head of the branch are ignored, because they belong to a
+    /// workflow run that was superseded by a later push.
+    pub fn update_status(
+        &self,
+        repository_name: &str,
+        branch: &str,
+        head_sha: &str,
+        status: ReadyBranchStatus,
+    ) {
+        let mut branches = self.lock();
+
+        if let Some(ready_branch) = branches.get_mut(&Self::key(repository_name, branch))
+            && ready_branch.head_sha == head_sha
+        {
+            ready_branch.status = status;
+        }
+    }
+
+    pub fn list(&self) -> Vec<ReadyBranch> {
+        self.lock().values().cloned().collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<(String, String), ReadyBranch>> {
+        self.branches
+            .lock()
+            .expect("ready branch registry is never poisoned")
+    }
+
+    fn key(repository_name: &str, branch: &str) -> (String, String) {
+        (repository_name.to_owned(), branch.to_owned())
+    }
+}
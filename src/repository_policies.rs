@@ -0,0 +1,195 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::application_config::{ConfigError, MergePolicy, OrganisationPolicy};
+
+/// Path of the policy file inside a repository
+pub const REPOSITORY_POLICY_FILE: &str = ".github/koritsu.toml";
+
+/// Path of the policy file inside the `.github` repository of an organisation
+pub const ORGANISATION_POLICY_FILE: &str = "koritsu.toml";
+
+/// The policy file of an organisation is read again after this time, because
+/// a change in another repository does not cause an event.
+const ORGANISATION_POLICY_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Name of the repository that holds the policy file of an organisation
+pub fn organisation_policy_repository(owner: &str) -> String {
+    format!("{owner}/.github")
+}
+
+/// The policy that applies to a repository
+#[derive(Debug, Clone)]
+pub struct RepositoryPolicy {
+    pub policy: MergePolicy,
+    /// Describes why a policy file can not be used. The policy without the
+    /// file applies, but ready branches are not merged.
+    pub file_problem: Option<PolicyFileProblem>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PolicyFileProblem {
+    /// Displayed name of the invalid file
+    pub file: String,
+    pub description: String,
+}
+
+impl RepositoryPolicy {
+    /// Layers the policy file of the organisation and the policy file of the
+    /// repository on top of the configured policy.
+    pub fn resolve(
+        base: &MergePolicy,
+        owner: &str,
+        organisation_file: Option<&str>,
+        repository_file: Option<&str>,
+    ) -> Self {
+        let organisation = match organisation_file {
+            Some(content) => {
+                let file = format!(
+                    "{}/{ORGANISATION_POLICY_FILE}",
+                    organisation_policy_repository(owner)
+                );
+                match base.merged_with_organisation_file(file.as_ref(), content) {
+                    Ok(organisation) => organisation,
+                    Err(error) => return Self::invalid(base, file, &error),
+                }
+            }
+            None => OrganisationPolicy {
+                policy: base.clone(),
+                locked_keys: Vec::new(),
+            },
+        };
+
+        let Some(content) = repository_file else {
+            return Self::valid(organisation.policy);
+        };
+
+        let path = Path::new(REPOSITORY_POLICY_FILE);
+        match organisation
+            .policy
+            .merged_with_file(path, content, &organisation.locked_keys)
+        {
+            Ok(policy) => Self::valid(policy),
+            Err(error) => Self::invalid(
+                &organisation.policy,
+                REPOSITORY_POLICY_FILE.to_owned(),
+                &error,
+            ),
+        }
+    }
+
+    fn valid(policy: MergePolicy) -> Self {
+        RepositoryPolicy {
+            policy,
+            file_problem: None,
+        }
+    }
+
+    fn invalid(policy: &MergePolicy, file: String, error: &ConfigError) -> Self {
+        RepositoryPolicy {
+            policy: policy.clone(),
+            file_problem: Some(PolicyFileProblem {
+                file,
+                description: describe(error),
+            }),
+        }
+    }
+}
+
+fn describe(error: &ConfigError) -> String {
+    let mut description = error.to_string();
+
+    let mut source = error.source();
+    while let Some(cause) = source {
+        description.push_str(&format!("\n\n{cause}"));
+        source = cause.source();
+    }
+
+    description
+}
+
+/// Caches the content of policy files. The file of a repository is cached
+/// together with the commit of the default branch it was read from and read
+/// again once the default branch moves. The file of an organisation expires
+/// after a while. The branch prefix of the policy resolved last for a
+/// repository expires like the file of its organisation.
+#[derive(Default)]
+pub struct PolicyFileCache {
+    repository_files: Mutex<HashMap<String, (String, Option<String>)>>,
+    organisation_files: Mutex<HashMap<String, (Instant, Option<String>)>>,
+    branch_prefixes: Mutex<HashMap<String, (Instant, String)>>,
+}
+
+impl PolicyFileCache {
+    pub fn repository_file(
+        &self,
+        repository_name: &str,
+        commit_sha: &str,
+    ) -> Option<Option<String>> {
+        lock(&self.repository_files)
+            .get(repository_name)
+            .filter(|(cached_sha, _)| cached_sha == commit_sha)
+            .map(|(_, content)| content.clone())
+    }
+
+    pub fn insert_repository_file(
+        &self,
+        repository_name: &str,
+        commit_sha: &str,
+        content: Option<String>,
+    ) {
+        lock(&self.repository_files)
+            .insert(repository_name.to_owned(), (commit_sha.to_owned(), content));
+    }
+
+    pub fn organisation_file(&self, owner: &str) -> Option<Option<String>> {
+        lock(&self.organisation_files)
+            .get(owner)
+            .filter(|(read_at, _)| read_at.elapsed() < ORGANISATION_POLICY_TTL)
+            .map(|(_, content)| content.clone())
+    }
+
+    pub fn insert_organisation_file(&self, owner: &str, content: Option<String>) {
+        lock(&self.organisation_files).insert(owner.to_owned(), (Instant::now(), content));
+    }
+
+    pub fn branch_prefix(&self, repository_name: &str) -> Option<String> {
+        lock(&self.branch_prefixes)
+            .get(repository_name)
+            .filter(|(resolved_at, _)| resolved_at.elapsed() < ORGANISATION_POLICY_TTL)
+            .map(|(_, branch_prefix)| branch_prefix.clone())
+    }
+
+    pub fn insert_branch_prefix(&self, repository_name: &str, branch_prefix: &str) {
+        lock(&self.branch_prefixes).insert(
+            repository_name.to_owned(),
+            (Instant::now(), branch_prefix.to_owned()),
+        );
+    }
+
+    pub fn forget_branch_prefix(&self, repository_name: &str) {
+        lock(&self.branch_prefixes).remove(repository_name);
+    }
+
+    pub fn forget_branch_prefixes(&self) {
+        lock(&self.branch_prefixes).clear();
+    }
+}
+
+fn lock<T>(files: &Mutex<T>) -> MutexGuard<'_, T> {
+    files.lock().expect("policy file cache is never poisoned")
+}
@@ -0,0 +1,116 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use thiserror::Error;
+use tokio::net::TcpListener;
+
+use crate::application_config::{ServerConfig, TlsConfig};
+
+/// How often the certificate and key files are checked for changes
+const TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+pub async fn serve(app: Router, config: &ServerConfig) -> Result<(), ServerError> {
+    serve_checking_tls_files(app, config, TLS_RELOAD_INTERVAL).await
+}
+
+/// Like [`serve`], checking the certificate and key files for changes at the
+/// interval
+pub async fn serve_checking_tls_files(
+    app: Router,
+    config: &ServerConfig,
+    check_interval: Duration,
+) -> Result<(), ServerError> {
+    let address = config.socket_address();
+
+    match &config.tls {
+        None => {
+            let listener = TcpListener::bind(address)
+                .await
+                .map_err(|error| ServerError::UnableToBindToSocket(address, error))?;
+
+            tracing::info!("listening on {}", address);
+
+            axum::serve(listener, app)
+                .await
+                .map_err(|error| ServerError::CouldNotServeApplication(address, error))
+        }
+        Some(tls) => {
+            let rustls_config = RustlsConfig::from_pem_file(&tls.certificate_file, &tls.key_file)
+                .await
+                .map_err(ServerError::InvalidTlsFiles)?;
+
+            tokio::spawn(reload_on_change(
+                rustls_config.clone(),
+                tls.clone(),
+                check_interval,
+            ));
+
+            tracing::info!("listening on {} with TLS", address);
+
+            axum_server::bind_rustls(address, rustls_config)
+                .serve(app.into_make_service())
+                .await
+                .map_err(|error| ServerError::CouldNotServeApplication(address, error))
+        }
+    }
+}
+
+/// Polls the modification times of the certificate and key files and reloads
+/// them after a change. A failed reload keeps the previous certificate, e.g.
+/// if only one of the files was replaced yet.
+async fn reload_on_change(rustls_config: RustlsConfig, tls: TlsConfig, check_interval: Duration) {
+    let mut loaded = modification_times(&tls);
+    let mut interval = tokio::time::interval(check_interval);
+
+    loop {
+        interval.tick().await;
+
+        let current = modification_times(&tls);
+        if current == loaded {
+            continue;
+        }
+
+        match rustls_config
+            .reload_from_pem_file(&tls.certificate_file, &tls.key_file)
+            .await
+        {
+            Ok(()) => {
+                tracing::info!("Reloaded TLS certificate");
+                loaded = current;
+            }
+            Err(error) => tracing::warn!(%error, "Could not reload TLS certificate"),
+        }
+    }
+}
+
+fn modification_times(tls: &TlsConfig) -> (Option<SystemTime>, Option<SystemTime>) {
+    let modified = |path: &Path| path.metadata().and_then(|meta| meta.modified()).ok();
+    (modified(&tls.certificate_file), modified(&tls.key_file))
+}
+
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error("Could not load the TLS certificate or key")]
+    InvalidTlsFiles(#[source] io::Error),
+
+    #[error("Unable to bind to socket {0}")]
+    UnableToBindToSocket(SocketAddr, #[source] io::Error),
+
+    #[error("Unable to serve application at socket {0}")]
+    CouldNotServeApplication(SocketAddr, #[source] io::Error),
+}
@@ -0,0 +1,34 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use serde::Serialize;
+
+use crate::{
+    application_context::ApplicationContext,
+    github_api::{GitHubApiProvider, RateLimitStatus},
+    ready_branches::ReadyBranch,
+};
+
+pub async fn status_handler<ApiProvider: GitHubApiProvider>(
+    State(app_context): State<Arc<ApplicationContext<ApiProvider>>>,
+) -> Json<StatusResponse> {
+    Json(StatusResponse {
+        ready_branches: app_context.ready_branches().list(),
+        rate_limits: app_context.rate_limits(),
+    })
+}
+
+#[derive(Serialize)]
+pub struct StatusResponse {
+    ready_branches: Vec<ReadyBranch>,
+    rate_limits: Vec<RateLimitStatus>,
+}
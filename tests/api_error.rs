@@ -0,0 +1,162 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use axum::{
+    Json,
+    http::StatusCode,
+    routing::{get, patch},
+};
+use common::{BRANCH_HEAD_PATH, FakeGitHub, given_provider};
+use koritsu_app::github_api::{
+    ApiError, AuthenticationMethod, BranchHeadRequest, ErrorDetails, FileContentRequest, GitHubApi,
+    UpdateReferenceRequest,
+};
+use serde_json::json;
+
+mod common;
+
+#[tokio::test]
+async fn classifies_errors_and_keeps_the_details_github_sent() {
+    let provider = given_provider(&given_github().await);
+    let api = provider.get_api(installation()).await.unwrap();
+
+    let error = api
+        .update_reference(UpdateReferenceRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            reference: "heads/main".to_owned(),
+            sha1: "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15".to_owned(),
+            force: false,
+        })
+        .await
+        .unwrap_err();
+
+    assert!(!error.is_retryable());
+    let ApiError::Validation(details) = &error else {
+        panic!("expected a validation error, got {error:?}");
+    };
+    assert_eq!(
+        *details,
+        ErrorDetails {
+            status: Some(StatusCode::UNPROCESSABLE_ENTITY),
+            request_id: Some("CAFE:1337".to_owned()),
+            message: Some("Update is not a fast forward".to_owned()),
+            errors: vec!["Reference.sha missing".to_owned()],
+            documentation_url: Some(
+                "https://docs.github.com/rest/git/refs#update-a-reference".to_owned()
+            ),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "Validation failed: Update is not a fast forward (Reference.sha missing), status 422, \
+         request CAFE:1337, see https://docs.github.com/rest/git/refs#update-a-reference"
+    );
+}
+
+#[tokio::test]
+async fn keeps_the_status_and_request_id_of_answers_that_are_not_json() {
+    let github = FakeGitHub::new()
+        .route(
+            BRANCH_HEAD_PATH,
+            get(|| async {
+                (
+                    [
+                        ("content-type", "text/html"),
+                        ("x-github-request-id", "CAFE:1337"),
+                    ],
+                    "<html>Unicorn!</html>",
+                )
+            }),
+        )
+        .start()
+        .await;
+    let provider = given_provider(&github);
+    let api = provider.get_api(installation()).await.unwrap();
+
+    let error = api
+        .get_branch_head(BranchHeadRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            branch: "main".to_owned(),
+        })
+        .await
+        .unwrap_err();
+
+    assert!(!error.is_retryable());
+    assert_eq!(
+        error.to_string(),
+        "Serialization failed: Content-Type is not valid for JSON, status 200, request CAFE:1337"
+    );
+}
+
+#[tokio::test]
+async fn refuses_file_contents_in_an_unknown_encoding() {
+    let github = FakeGitHub::new()
+        .route(
+            "/repos/{owner}/{repository}/contents/{*path}",
+            get(|| async {
+                (
+                    [("x-github-request-id", "CAFE:1337")],
+                    Json(json!({"content": "", "encoding": "none"})),
+                )
+            }),
+        )
+        .start()
+        .await;
+    let provider = given_provider(&github);
+    let api = provider.get_api(installation()).await.unwrap();
+
+    let error = api
+        .get_file_content(FileContentRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            path: ".github/koritsu.toml".to_owned(),
+            reference: None,
+        })
+        .await
+        .unwrap_err();
+
+    let ApiError::Serialization(details) = &error else {
+        panic!("expected a serialization error, got {error:?}");
+    };
+    assert_eq!(details.status, Some(StatusCode::OK));
+    assert_eq!(details.request_id.as_deref(), Some("CAFE:1337"));
+    assert_eq!(
+        details.message.as_deref(),
+        Some("Unsupported content encoding none")
+    );
+}
+
+fn installation() -> AuthenticationMethod {
+    AuthenticationMethod::AppInstallation {
+        installation_id: 1337,
+        scope: None,
+    }
+}
+
+/// Starts a server that rejects every reference update like GitHub rejects
+/// an update that is not a fast forward.
+async fn given_github() -> String {
+    FakeGitHub::new()
+        .route(
+            "/repos/{owner}/{repository}/git/refs/{*reference}",
+            patch(|| async {
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    [("x-github-request-id", "CAFE:1337")],
+                    Json(json!({
+                        "message": "Update is not a fast forward",
+                        "errors": [{"resource": "Reference", "field": "sha", "code": "missing"}],
+                        "documentation_url":
+                            "https://docs.github.com/rest/git/refs#update-a-reference",
+                    })),
+                )
+            }),
+        )
+        .start()
+        .await
+}
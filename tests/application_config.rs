@@ -0,0 +1,409 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{collections::HashMap, net::Ipv4Addr, path::Path, time::Duration};
+
+use koritsu_app::{
+    ApplicationConfig, ConfigError, ConfigProblem, GitHubBackend, HttpClientConfig, MergePolicy,
+    TlsConfig,
+};
+
+const MINIMAL_CONFIG: &str = r#"
+[github]
+webhook_secret = "secret"
+client_id = "client"
+private_key_file = "/run/secrets/key.pem"
+"#;
+
+#[test]
+fn reads_the_configuration_file() {
+    let content = format!(
+        r#"{MINIMAL_CONFIG}
+[server]
+listen_address = "0.0.0.0"
+port = 8443
+
+[server.tls]
+certificate_file = "/etc/koritsu/cert.pem"
+key_file = "/etc/koritsu/key.pem"
+
+[merge_policy]
+required_workflows = ["CI"]
+
+[repositories."owner/repo"]
+accepted_conclusions = ["success", "skipped"]
+"#
+    );
+
+    let config = load(&content, &[]).unwrap();
+
+    assert_eq!(config.client_id, "client");
+    assert_eq!(config.github_base_url, "https://api.github.com");
+    assert_eq!(config.github_api_version.as_deref(), Some("2022-11-28"));
+    assert_eq!(config.server.listen_address, Ipv4Addr::UNSPECIFIED);
+    assert_eq!(config.server.port, 8443);
+    assert!(config.server.tls.is_some());
+    assert_eq!(config.default_policy.required_workflows, vec!["CI"]);
+    assert_eq!(
+        config.policy_for("owner/repo"),
+        &MergePolicy {
+            required_workflows: Vec::new(),
+            accepted_conclusions: vec!["success".to_owned(), "skipped".to_owned()],
+            ..MergePolicy::default()
+        }
+    );
+}
+
+#[test]
+fn environment_variables_override_the_file() {
+    let env = [
+        ("GITHUB_CLIENT_ID", "from-env"),
+        ("KORITSU_PORT", "9000"),
+        ("KORITSU_ALLOWED_TRIGGER_EVENTS", "push, schedule"),
+    ];
+
+    let config = load(MINIMAL_CONFIG, &env).unwrap();
+
+    assert_eq!(config.client_id, "from-env");
+    assert_eq!(config.server.port, 9000);
+    assert_eq!(config.allowed_trigger_events, vec!["push", "schedule"]);
+}
+
+#[test]
+fn supports_github_enterprise_server() {
+    let content = format!(
+        r#"{MINIMAL_CONFIG}
+base_url = "https://github.example.com/api/v3/"
+api_version = ""
+"#
+    );
+
+    let config = load(&content, &[]).unwrap();
+
+    assert_eq!(config.github_base_url, "https://github.example.com/api/v3");
+    assert_eq!(config.github_api_version, None);
+}
+
+#[test]
+fn selects_the_github_backend() {
+    assert_eq!(
+        load(MINIMAL_CONFIG, &[]).unwrap().github_backend,
+        GitHubBackend::Rest
+    );
+    assert_eq!(
+        load(MINIMAL_CONFIG, &[("GITHUB_BACKEND", "graphql")])
+            .unwrap()
+            .github_backend,
+        GitHubBackend::GraphQl
+    );
+    assert_eq!(
+        load_problems(MINIMAL_CONFIG, &[("GITHUB_BACKEND", "soap")]),
+        vec![problem(
+            "github.backend",
+            "\"soap\" must be one of \"rest\" or \"graphql\""
+        )]
+    );
+}
+
+#[test]
+fn reads_the_http_client_settings() {
+    let content = format!(
+        r#"{MINIMAL_CONFIG}
+[github.http]
+connect_timeout = 5
+request_timeout = 20
+pool_max_idle_per_host = 0
+proxy = "http://proxy.example.com:3128"
+no_proxy = ["localhost", ".internal.example.com"]
+ca_certificate_file = "/etc/koritsu/ca.pem"
+
+[github.http.client_certificate]
+certificate_file = "/etc/koritsu/client.pem"
+key_file = "/etc/koritsu/client-key.pem"
+"#
+    );
+
+    let config = load(&content, &[("GITHUB_REQUEST_TIMEOUT", "45")]).unwrap();
+
+    assert_eq!(
+        config.github_http,
+        HttpClientConfig {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(45),
+            pool_max_idle_per_host: 0,
+            proxy: Some("http://proxy.example.com:3128".to_owned()),
+            no_proxy: vec!["localhost".to_owned(), ".internal.example.com".to_owned()],
+            ca_certificate_files: vec!["/etc/koritsu/ca.pem".into()],
+            client_certificate: Some(TlsConfig {
+                certificate_file: "/etc/koritsu/client.pem".into(),
+                key_file: "/etc/koritsu/client-key.pem".into(),
+            }),
+            ..HttpClientConfig::default()
+        }
+    );
+}
+
+#[test]
+fn rejects_invalid_http_client_settings() {
+    let content = format!(
+        r#"{MINIMAL_CONFIG}
+[github.http]
+connect_timeout = 0
+pool_max_idle_per_host = -1
+proxy = "proxy.example.com"
+"#
+    );
+
+    assert_eq!(
+        load_problems(&content, &[]),
+        vec![
+            problem(
+                "github.http.connect_timeout",
+                "must be a positive number of seconds"
+            ),
+            problem("github.http.pool_max_idle_per_host", "must be zero or more"),
+            problem(
+                "github.http.proxy",
+                "\"proxy.example.com\" is not an http or https URL"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn reads_the_admin_token() {
+    assert_eq!(load(MINIMAL_CONFIG, &[]).unwrap().admin_token, None);
+    assert_eq!(
+        load(MINIMAL_CONFIG, &[("KORITSU_ADMIN_TOKEN", "s3cr3t")])
+            .unwrap()
+            .admin_token,
+        Some("s3cr3t".to_owned())
+    );
+    assert_eq!(
+        load_problems(MINIMAL_CONFIG, &[("KORITSU_ADMIN_TOKEN", "")]),
+        vec![problem("admin.token", "must not be empty")]
+    );
+}
+
+#[test]
+fn rejects_base_urls_that_are_not_http() {
+    let problems = load_problems(MINIMAL_CONFIG, &[("GITHUB_BASE_URL", "github.example.com")]);
+
+    assert_eq!(
+        problems,
+        vec![problem(
+            "github.base_url",
+            "\"github.example.com\" is not an http or https URL"
+        )]
+    );
+}
+
+#[test]
+fn accepts_several_private_keys() {
+    let content = r#"
+[github]
+webhook_secret = "secret"
+client_id = "client"
+private_key_file = ["/run/secrets/new.pem", "/run/secrets/old.pem"]
+"#;
+
+    let config = load(content, &[]).unwrap();
+
+    assert_eq!(config.private_keys.len(), 2);
+}
+
+#[test]
+fn works_without_a_configuration_file() {
+    let env = HashMap::from([
+        ("GITHUB_WEBHOOK_SECRET", "secret"),
+        ("GITHUB_CLIENT_ID", "client"),
+        ("PRIVATE_KEY_FILE", "key.pem"),
+    ]);
+
+    let config =
+        ApplicationConfig::from_sources(None, |name| env.get(name).map(|value| value.to_string()))
+            .unwrap();
+
+    assert_eq!(config.server.port, 8080);
+    assert!(config.repository_policies.is_empty());
+}
+
+#[test]
+fn reports_every_problem_with_its_key() {
+    let content = r#"
+[github]
+webhook_secret = 42
+client_id = "client"
+privat_key_file = "key.pem"
+
+[server]
+listen_address = "localhost"
+port = 70000
+
+[server.tls]
+certificate_file = "cert.pem"
+
+[repositories."owner/repo"]
+required_workflows = "CI"
+"#;
+
+    let problems = load_problems(content, &[("KORITSU_ALLOWED_TRIGGER_EVENTS", "push")]);
+
+    assert_eq!(
+        problems,
+        vec![
+            problem("github.privat_key_file", "is not a known key"),
+            problem("github.webhook_secret", "must be a string"),
+            problem("github.private_key_file", "or private_key is required"),
+            problem(
+                "server.listen_address",
+                "\"localhost\" is not a valid IP address"
+            ),
+            problem("server.port", "must be a port number between 1 and 65535"),
+            problem("server.tls.key_file", "is required"),
+            problem(
+                "repositories.\"owner/repo\".required_workflows",
+                "must be a list of strings"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn reports_invalid_environment_variables() {
+    let problems = load_problems(MINIMAL_CONFIG, &[("KORITSU_PORT", "https")]);
+
+    assert_eq!(
+        problems,
+        vec![problem(
+            "server.port",
+            "KORITSU_PORT must be an integer, got \"https\""
+        )]
+    );
+}
+
+#[test]
+fn reads_the_server_settings_from_the_environment() {
+    let config = load(
+        MINIMAL_CONFIG,
+        &[
+            ("KORITSU_LISTEN_ADDRESS", "::1"),
+            ("KORITSU_PORT", "8443"),
+            ("KORITSU_TLS_CERTIFICATE_FILE", "/etc/koritsu/cert.pem"),
+            ("KORITSU_TLS_KEY_FILE", "/etc/koritsu/key.pem"),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(config.server.socket_address().to_string(), "[::1]:8443");
+    let tls = config.server.tls.unwrap();
+    assert_eq!(tls.certificate_file, Path::new("/etc/koritsu/cert.pem"));
+    assert_eq!(tls.key_file, Path::new("/etc/koritsu/key.pem"));
+}
+
+#[test]
+fn reports_half_configured_tls_and_invalid_server_variables() {
+    let problems = load_problems(
+        MINIMAL_CONFIG,
+        &[
+            ("KORITSU_LISTEN_ADDRESS", "localhost"),
+            ("KORITSU_PORT", "0"),
+            ("KORITSU_TLS_KEY_FILE", "/etc/koritsu/key.pem"),
+        ],
+    );
+
+    assert_eq!(
+        problems,
+        vec![
+            problem(
+                "server.listen_address",
+                "\"localhost\" is not a valid IP address"
+            ),
+            problem("server.port", "must be a port number between 1 and 65535"),
+            problem("server.tls.certificate_file", "is required"),
+        ]
+    );
+}
+
+#[test]
+fn rejects_files_that_are_not_valid_toml() {
+    let result = load("[github", &[]);
+
+    assert!(matches!(result, Err(ConfigError::InvalidFile(..))));
+}
+
+#[test]
+fn layers_the_organisation_and_repository_policy_files() {
+    let organisation = MergePolicy::default()
+        .merged_with_organisation_file(
+            Path::new("koritsu.toml"),
+            r#"
+delete_branch = true
+require_signed_commits = true
+locked = ["require_signed_commits"]
+"#,
+        )
+        .unwrap();
+
+    let policy = organisation
+        .policy
+        .merged_with_file(
+            Path::new(".github/koritsu.toml"),
+            r#"branch_prefix = "ship/""#,
+            &organisation.locked_keys,
+        )
+        .unwrap();
+
+    assert_eq!(
+        policy,
+        MergePolicy {
+            branch_prefix: "ship/".to_owned(),
+            delete_branch: true,
+            require_signed_commits: true,
+            ..MergePolicy::default()
+        }
+    );
+}
+
+#[test]
+fn rejects_locked_keys_that_are_not_policy_keys() {
+    let result = MergePolicy::default()
+        .merged_with_organisation_file(Path::new("koritsu.toml"), r#"locked = ["port"]"#);
+
+    match result {
+        Err(ConfigError::Invalid(problems)) => assert_eq!(
+            problems,
+            vec![problem("locked", "\"port\" is not a policy key")]
+        ),
+        _ => panic!("organisation policy was accepted"),
+    }
+}
+
+fn load(content: &str, env: &[(&str, &str)]) -> Result<ApplicationConfig, ConfigError> {
+    let env: HashMap<&str, &str> = env.iter().copied().collect();
+
+    ApplicationConfig::from_sources(Some((Path::new("koritsu.toml"), content)), |name| {
+        env.get(name).map(|value| value.to_string())
+    })
+}
+
+fn load_problems(content: &str, env: &[(&str, &str)]) -> Vec<ConfigProblem> {
+    match load(content, env) {
+        Err(ConfigError::Invalid(problems)) => problems,
+        Err(error) => panic!("unexpected error {error}"),
+        Ok(_) => panic!("configuration was accepted"),
+    }
+}
+
+fn problem(key: &str, message: &str) -> ConfigProblem {
+    ConfigProblem {
+        key: key.to_owned(),
+        message: message.to_owned(),
+    }
+}
@@ -0,0 +1,159 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use axum::{
+    Json,
+    extract::Path,
+    http::StatusCode,
+    routing::{get, post},
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use common::{BRANCH_HEAD_SHA, FakeGitHub, given_config};
+use koritsu_app::{Check, PrivateKeySource, check_config};
+use serde_json::{Value, json};
+
+mod common;
+
+#[tokio::test]
+async fn reports_invalid_repository_policy_files() {
+    let config = given_config(&[("GITHUB_BASE_URL", &given_github().await)]);
+
+    let report = check_config(config).await;
+
+    assert!(!report.passed());
+    let key_check = &report.checks[1].subject;
+    assert!(key_check.starts_with("Private key github.private_key (SHA256:"));
+    assert!(key_check.ends_with(") is accepted for koritsu"));
+    assert_eq!(
+        report.checks[2..],
+        [
+            passed("Listed 1 installations"),
+            passed("Installation 1337 of test-owner can access 2 repositories"),
+            passed("Policy of test-owner/valid"),
+            Check {
+                subject: "Policy of test-owner/invalid".to_owned(),
+                problem: Some(
+                    "Invalid .github/koritsu.toml\n\
+                     Invalid configuration\n  - delete_branch: must be true or false"
+                        .to_owned()
+                ),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reads_the_policy_files_with_the_configured_backend() {
+    let github = given_github_answering(FakeGitHub::new().graphql(post(graphql_handler))).await;
+    let config = given_config(&[("GITHUB_BASE_URL", &github), ("GITHUB_BACKEND", "graphql")]);
+
+    let report = check_config(config).await;
+
+    assert!(!report.passed());
+    assert_eq!(
+        report.checks.last().unwrap().problem.as_deref(),
+        Some(
+            "Invalid .github/koritsu.toml\n\
+             Invalid configuration\n  - delete_branch: must be true or false"
+        )
+    );
+}
+
+#[tokio::test]
+async fn reports_private_keys_that_can_not_be_loaded() {
+    let mut config = given_config(&[("GITHUB_BASE_URL", "http://127.0.0.1:1")]);
+    config.private_keys = vec![PrivateKeySource::Inline("not a key".to_owned())];
+
+    let report = check_config(config).await;
+
+    assert!(!report.passed());
+    assert_eq!(report.checks.len(), 1);
+    assert_eq!(
+        report.to_string(),
+        "FAILED Private keys can be loaded\n\
+         \x20        The private key from github.private_key can not be used\n\
+         \x20        The key is neither PEM encoded nor a DER encoded PKCS#1 or PKCS#8 RSA key\n"
+    );
+}
+
+fn passed(subject: &str) -> Check {
+    Check {
+        subject: subject.to_owned(),
+        problem: None,
+    }
+}
+
+/// Starts a server that answers like GitHub for an installation with one
+/// repository with a valid and one with an invalid policy file.
+async fn given_github() -> String {
+    given_github_answering(FakeGitHub::new().route(
+        "/repos/{owner}/{repository}/contents/{*path}",
+        get(contents_handler),
+    ))
+    .await
+}
+
+/// Starts the fake GitHub with the installation and its repositories, but
+/// without a way to read the policy files
+async fn given_github_answering(github: FakeGitHub) -> String {
+    github
+        .route("/app", get(|| async { Json(json!({"name": "koritsu"})) }))
+        .route(
+            "/app/installations",
+            get(|| async {
+                Json(json!([{
+                    "id": 1337,
+                    "account": {"login": "test-owner"},
+                    "permissions": {"contents": "write"},
+                    "suspended_at": null,
+                }]))
+            }),
+        )
+        .route(
+            "/installation/repositories",
+            get(|| async {
+                Json(json!({"repositories": [
+                    {"full_name": "test-owner/valid", "default_branch": "main"},
+                    {"full_name": "test-owner/invalid", "default_branch": "main"},
+                ]}))
+            }),
+        )
+        .start()
+        .await
+}
+
+async fn contents_handler(
+    Path((_, repository, _)): Path<(String, String, String)>,
+) -> (StatusCode, Json<Value>) {
+    match repository.as_str() {
+        "invalid" => (
+            StatusCode::OK,
+            Json(json!({
+                "content": BASE64_STANDARD.encode("delete_branch = \"yes\""),
+                "encoding": "base64",
+            })),
+        ),
+        _ => (StatusCode::NOT_FOUND, Json(json!({"message": "Not Found"}))),
+    }
+}
+
+/// Answers the branch head and file content queries of the GraphQL API
+async fn graphql_handler(Json(request): Json<Value>) -> Json<Value> {
+    let variables = &request["variables"];
+
+    let repository = if variables["qualifiedName"].is_string() {
+        json!({"ref": {"target": {"oid": BRANCH_HEAD_SHA}}})
+    } else if variables["name"] == "invalid" {
+        json!({"object": {"text": "delete_branch = \"yes\"", "isTruncated": false}})
+    } else {
+        json!({"object": null})
+    };
+
+    Json(json!({"data": {"repository": repository}}))
+}
@@ -0,0 +1,697 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+#![allow(dead_code)] // Every integration test binary only uses a part of the helpers
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    ops::Deref,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use axum::{
+    Json, Router,
+    body::{Body, Bytes},
+    extract::Request,
+    http::StatusCode,
+    response::Response,
+    routing::{MethodRouter, RouterIntoService, get, post},
+};
+use hmac::{Hmac, Mac};
+use http_body_util::BodyExt;
+use koritsu_app::{
+    ApplicationConfig, ConfigReloader, GitHubBackend, HttpClientConfig, MergePolicy,
+    PrivateKeySource, ReloadError, ServerConfig, build_app_with_api,
+    github_api::{
+        ApiError, AppCredentials, AuthenticationMethod, BranchComparison, BranchComparisonRequest,
+        BranchHeadRequest, ChangedFile, CheckRunRequest, CommitStatusRequest, ComparedCommit,
+        DeleteReferenceRequest, ErrorDetails, FileContentRequest, GitHubApi, GitHubApiProvider,
+        GitHubRestApiProvider, InstallationDetails, MergeBranchRequest, MergeResult,
+        RateLimitStatus, RepositorySummary, TokenScope, UpdateReferenceRequest, WorkflowRunSummary,
+        WorkflowRunsRequest,
+    },
+};
+use rsa::{
+    RsaPrivateKey,
+    pkcs8::{EncodePrivateKey, LineEnding},
+};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use tokio::net::TcpListener;
+use tower::{Service, ServiceExt};
+
+/// The only installation the test GitHub API knows about
+pub const INSTALLATION_ID: usize = 1337;
+
+/// The token the `/admin` endpoints of the test application require
+pub const ADMIN_TOKEN: &str = "admin-token";
+
+/// The head of every branch of the fake GitHub
+pub const BRANCH_HEAD_SHA: &str = "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15";
+
+pub const ACCESS_TOKENS_PATH: &str = "/app/installations/{id}/access_tokens";
+pub const BRANCH_HEAD_PATH: &str = "/repos/{owner}/{repository}/git/ref/heads/{*branch}";
+
+/// A configuration read from the environment variables plus a webhook
+/// secret, a client ID and a new private key, unless the variables set them
+pub fn given_config(env: &[(&str, &str)]) -> ApplicationConfig {
+    let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+    let pem = private_key
+        .to_pkcs8_pem(LineEnding::LF)
+        .unwrap()
+        .to_string();
+    let mut env: HashMap<&str, String> = env
+        .iter()
+        .map(|(name, value)| (*name, (*value).to_owned()))
+        .collect();
+    env.entry("GITHUB_WEBHOOK_SECRET")
+        .or_insert_with(|| "secret".to_owned());
+    env.entry("GITHUB_CLIENT_ID")
+        .or_insert_with(|| "client".to_owned());
+    env.entry("GITHUB_PRIVATE_KEY").or_insert(pem);
+
+    ApplicationConfig::from_sources(None, |name| env.get(name).cloned()).unwrap()
+}
+
+/// A REST provider for the GitHub at the base URL
+pub fn given_provider(base_url: &str) -> GivenProvider {
+    GivenProvider::new(&given_config(&[("GITHUB_BASE_URL", base_url)]))
+}
+
+/// The credentials the provider loads from the configuration
+pub fn given_credentials<ApiProvider: GitHubApiProvider>(
+    provider: &ApiProvider,
+    config: &ApplicationConfig,
+) -> Arc<ApiProvider::Credentials> {
+    Arc::new(provider.load_credentials(config).unwrap())
+}
+
+/// A REST provider together with the credentials of its configuration, which
+/// the application context keeps for it in the running app
+pub struct GivenProvider {
+    provider: GitHubRestApiProvider,
+    credentials: Arc<AppCredentials>,
+}
+
+impl GivenProvider {
+    pub fn new(config: &ApplicationConfig) -> Self {
+        let provider = GitHubRestApiProvider::new(config).unwrap();
+        let credentials = given_credentials(&provider, config);
+        Self {
+            provider,
+            credentials,
+        }
+    }
+
+    pub async fn get_api(
+        &self,
+        auth_method: AuthenticationMethod,
+    ) -> Result<impl GitHubApi, ApiError> {
+        self.provider
+            .get_api(self.credentials.clone(), auth_method)
+            .await
+    }
+
+    pub async fn get_repository_installation(
+        &self,
+        repository_name: &str,
+    ) -> Result<Option<InstallationDetails>, ApiError> {
+        self.provider
+            .get_repository_installation(&self.credentials, repository_name)
+            .await
+    }
+}
+
+impl Deref for GivenProvider {
+    type Target = GitHubRestApiProvider;
+
+    fn deref(&self) -> &GitHubRestApiProvider {
+        &self.provider
+    }
+}
+
+/// Builds a server that answers like GitHub. It hands out installation access
+/// tokens and knows the head of every branch; tests add the routes they need
+/// or replace these two.
+pub struct FakeGitHub {
+    /// `/api/v3` for GitHub Enterprise Server
+    prefix: String,
+    routes: Vec<(String, MethodRouter)>,
+    graphql: Option<MethodRouter>,
+}
+
+impl FakeGitHub {
+    pub fn new() -> Self {
+        let routes = vec![
+            (
+                ACCESS_TOKENS_PATH.to_owned(),
+                post(|| async {
+                    (
+                        StatusCode::CREATED,
+                        Json(json!({"token": "token", "expires_at": "2999-01-01T00:00:00Z"})),
+                    )
+                }),
+            ),
+            (
+                BRANCH_HEAD_PATH.to_owned(),
+                get(|| async { Json(json!({"object": {"sha": BRANCH_HEAD_SHA}})) }),
+            ),
+        ];
+
+        Self {
+            prefix: String::new(),
+            routes,
+            graphql: None,
+        }
+    }
+
+    /// Serves the REST API below the prefix, like GitHub Enterprise Server
+    /// does below `/api/v3`
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_owned();
+        self
+    }
+
+    /// Adds the route or replaces the one with the same path
+    pub fn route(mut self, path: &str, method_router: MethodRouter) -> Self {
+        self.routes.retain(|(existing, _)| existing != path);
+        self.routes.push((path.to_owned(), method_router));
+        self
+    }
+
+    /// Answers GraphQL queries at `/graphql`, or at `/api/graphql` next to
+    /// the prefixed REST API
+    pub fn graphql(mut self, method_router: MethodRouter) -> Self {
+        self.graphql = Some(method_router);
+        self
+    }
+
+    /// Starts the server and returns its base URL including the prefix
+    pub async fn start(self) -> String {
+        let rest = self
+            .routes
+            .into_iter()
+            .fold(Router::new(), |router, (path, method_router)| {
+                router.route(&path, method_router)
+            });
+
+        let (mut app, graphql_path) = if self.prefix.is_empty() {
+            (rest, "/graphql")
+        } else {
+            (Router::new().nest(&self.prefix, rest), "/api/graphql")
+        };
+        if let Some(graphql) = self.graphql {
+            app = app.route(graphql_path, graphql);
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        format!("http://{address}{}", self.prefix)
+    }
+}
+
+pub struct TestClient {
+    config: ApplicationConfig,
+    service: RouterIntoService<Body>,
+    config_reloader: ConfigReloader<TestGitHubApi>,
+    api_calls: Arc<Mutex<Vec<ApiCall>>>,
+    workflow_runs: Arc<Mutex<Vec<WorkflowRunSummary>>>,
+    repository: Arc<Mutex<TestRepository>>,
+    rate_limits: Arc<Mutex<Vec<RateLimitStatus>>>,
+    token_scopes: Arc<Mutex<Vec<Option<TokenScope>>>>,
+}
+
+/// State of the repository the test GitHub API serves
+pub struct TestRepository {
+    pub default_branch_head: String,
+    /// Content of `.github/koritsu.toml`
+    pub policy_file: Option<String>,
+    pub policy_file_reads: usize,
+    /// Number of branch comparisons the application asked for
+    pub comparisons: usize,
+    /// Content of `koritsu.toml` in the `.github` repository of the owner
+    pub organisation_policy_file: Option<String>,
+    /// Whether the installation can access the `.github` repository
+    pub organisation_repository_accessible: bool,
+}
+
+impl TestClient {
+    pub fn new() -> Self {
+        Self::with_config(|_| {})
+    }
+
+    pub fn with_config(customize: impl FnOnce(&mut ApplicationConfig)) -> Self {
+        let mut config = ApplicationConfig {
+            github_base_url: String::default(),
+            github_api_version: None,
+            github_backend: GitHubBackend::Rest,
+            github_http: HttpClientConfig::default(),
+            github_webhook_secret: "secret".to_owned(),
+            client_id: String::default(),
+            private_keys: vec![PrivateKeySource::Inline(String::default())],
+            server: ServerConfig::default(),
+            admin_token: Some(ADMIN_TOKEN.to_owned()),
+            allowed_trigger_events: vec!["push".to_owned()],
+            default_policy: MergePolicy::default(),
+            repository_policies: HashMap::new(),
+        };
+        customize(&mut config);
+
+        let api_calls = Arc::new(Mutex::new(Vec::new()));
+        let workflow_runs = Arc::new(Mutex::new(Vec::new()));
+        let repository = Arc::new(Mutex::new(TestRepository {
+            default_branch_head: "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15".to_owned(),
+            policy_file: None,
+            policy_file_reads: 0,
+            comparisons: 0,
+            organisation_policy_file: None,
+            organisation_repository_accessible: true,
+        }));
+        let rate_limits = Arc::new(Mutex::new(Vec::new()));
+        let token_scopes = Arc::new(Mutex::new(Vec::new()));
+        let api = TestGitHubApi {
+            api_calls: api_calls.clone(),
+            workflow_runs: workflow_runs.clone(),
+            repository: repository.clone(),
+            rate_limits: rate_limits.clone(),
+            token_scopes: token_scopes.clone(),
+        };
+        let (router, config_reloader) = build_app_with_api(config.clone(), api).unwrap();
+
+        TestClient {
+            config,
+            service: router.into_service(),
+            config_reloader,
+            api_calls,
+            workflow_runs,
+            repository,
+            rate_limits,
+            token_scopes,
+        }
+    }
+
+    /// Reloads the application with a changed configuration. Events are
+    /// signed with the new webhook secret if the reload succeeds.
+    pub fn reload_config(
+        &mut self,
+        customize: impl FnOnce(&mut ApplicationConfig),
+    ) -> Result<(), ReloadError> {
+        let mut config = self.config.clone();
+        customize(&mut config);
+
+        self.config_reloader.apply(config.clone())?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Reloads the application like the running app does on `SIGHUP` and
+    /// when the configuration file changes, which is checked every 50 ms
+    pub fn watch_config_file(&self, config_file: PathBuf) {
+        let watch = self
+            .config_reloader
+            .clone()
+            .watch_file(Some(config_file), Duration::from_millis(50));
+        tokio::spawn(watch);
+    }
+
+    /// The configuration the application currently uses
+    pub fn current_config(&self) -> Arc<ApplicationConfig> {
+        self.config_reloader.config()
+    }
+
+    pub fn repository(&self) -> MutexGuard<'_, TestRepository> {
+        self.repository.lock().unwrap()
+    }
+
+    /// Sets the workflow runs the test GitHub API lists for every commit
+    pub fn given_workflow_runs(&self, runs: Vec<WorkflowRunSummary>) {
+        *self.workflow_runs.lock().unwrap() = runs;
+    }
+
+    /// Sets the rate limits the test GitHub API reports
+    pub fn given_rate_limits(&self, rate_limits: Vec<RateLimitStatus>) {
+        *self.rate_limits.lock().unwrap() = rate_limits;
+    }
+
+    pub async fn send_workflow_run_event(&mut self, payload: &Value) -> Response<Bytes> {
+        let request = self.build_event_request("workflow_run", payload);
+        self.send_request(request).await
+    }
+
+    pub async fn send_push_event(&mut self, payload: &Value) -> Response<Bytes> {
+        let request = self.build_event_request("push", payload);
+        self.send_request(request).await
+    }
+
+    pub async fn send_event(&mut self, event_type: &str, payload: &Value) -> Response<Bytes> {
+        let request = self.build_event_request(event_type, payload);
+        self.send_request(request).await
+    }
+
+    pub async fn get(&mut self, uri: &str) -> Response<Bytes> {
+        let request = Request::builder()
+            .method("GET")
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+
+        self.send_request(request).await
+    }
+
+    /// Sends a `GET` request with the admin token
+    pub async fn get_admin(&mut self, uri: &str) -> Response<Bytes> {
+        let request = Request::builder()
+            .method("GET")
+            .uri(uri)
+            .header("Authorization", format!("Bearer {ADMIN_TOKEN}"))
+            .body(Body::empty())
+            .unwrap();
+
+        self.send_request(request).await
+    }
+
+    pub async fn send_request(&mut self, request: Request) -> Response<Bytes> {
+        let (parts, body) = self
+            .service
+            .ready()
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap()
+            .into_parts();
+
+        let body_bytes = body.collect().await.unwrap().to_bytes();
+        Response::from_parts(parts, body_bytes)
+    }
+
+    pub fn build_event_request(&self, event_type: &str, payload: &Value) -> Request {
+        let payload = serde_json::to_vec(payload).unwrap();
+        self.build_raw_event_request(event_type, "application/json", payload)
+    }
+
+    pub fn build_raw_event_request(
+        &self,
+        event_type: &str,
+        content_type: &str,
+        payload: Vec<u8>,
+    ) -> Request {
+        let signature = self.compute_signature(&payload);
+
+        Request::builder()
+            .method("POST")
+            .uri("/github/events")
+            .header("Content-Type", content_type)
+            .header("X-GitHub-Event", event_type)
+            .header("X-Hub-Signature-256", format!("sha256={}", signature))
+            .body(Body::from(payload))
+            .unwrap()
+    }
+
+    pub fn api_calls(&self) -> Vec<ApiCall> {
+        self.api_calls.lock().unwrap().clone()
+    }
+
+    /// The scopes of all installation access tokens the application asked for
+    pub fn token_scopes(&self) -> Vec<Option<TokenScope>> {
+        self.token_scopes.lock().unwrap().clone()
+    }
+
+    fn compute_signature(&self, payload: &[u8]) -> String {
+        let secret = self.config.github_webhook_secret.as_bytes();
+
+        let signature = Hmac::<Sha256>::new_from_slice(secret)
+            .unwrap()
+            .chain_update(payload)
+            .finalize()
+            .into_bytes();
+
+        signature
+            .into_iter()
+            .flat_map(|byte| [Self::byte_to_hex(byte >> 4), Self::byte_to_hex(byte)])
+            .collect()
+    }
+
+    fn byte_to_hex(byte: u8) -> char {
+        let encoding = [
+            '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f',
+        ];
+        encoding[(byte & 15u8) as usize]
+    }
+}
+
+pub trait ResponseExt {
+    fn body_as_json(&self) -> Value;
+}
+
+impl ResponseExt for Response<Bytes> {
+    fn body_as_json(&self) -> Value {
+        serde_json::from_slice(self.body()).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiCall {
+    UpdateReference { reference: String, sha1: String },
+    CreateCommitStatus(CommitStatusRequest),
+    CreateCheckRun(CheckRunRequest),
+    MergeBranch(MergeBranchRequest),
+    DeleteReference(DeleteReferenceRequest),
+    ForgetInstallation(usize),
+}
+
+struct TestGitHubApi {
+    api_calls: Arc<Mutex<Vec<ApiCall>>>,
+    workflow_runs: Arc<Mutex<Vec<WorkflowRunSummary>>>,
+    repository: Arc<Mutex<TestRepository>>,
+    rate_limits: Arc<Mutex<Vec<RateLimitStatus>>>,
+    token_scopes: Arc<Mutex<Vec<Option<TokenScope>>>>,
+}
+
+impl TestGitHubApi {
+    fn record(&self, call: ApiCall) {
+        self.api_calls.lock().unwrap().push(call);
+    }
+}
+
+impl GitHubApiProvider for TestGitHubApi {
+    type Credentials = ();
+
+    fn load_credentials(
+        &self,
+        config: &ApplicationConfig,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match config.private_keys.as_slice() {
+            [PrivateKeySource::Inline(key)] if key == "invalid" => {
+                Err("invalid private key".into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_api(
+        &self,
+        _: Arc<()>,
+        auth_method: AuthenticationMethod,
+    ) -> Result<impl GitHubApi, ApiError> {
+        if let AuthenticationMethod::AppInstallation { scope, .. } = auth_method {
+            let refused = scope.as_ref().is_some_and(|scope| {
+                scope.repositories.contains(".github")
+                    && !self
+                        .repository
+                        .lock()
+                        .unwrap()
+                        .organisation_repository_accessible
+            });
+            self.token_scopes.lock().unwrap().push(scope);
+
+            if refused {
+                return Err(ApiError::Validation(ErrorDetails::from_message(
+                    "There is at least one repository that does not exist or is not accessible to the parent installation.",
+                )));
+            }
+        }
+
+        Ok(self)
+    }
+
+    async fn get_installation(
+        &self,
+        _: &(),
+        installation_id: usize,
+    ) -> Result<Option<InstallationDetails>, ApiError> {
+        let details = InstallationDetails {
+            id: installation_id,
+            account: "test-owner".to_owned(),
+            permissions: BTreeMap::from([("contents".to_owned(), "write".to_owned())]),
+            suspended: false,
+        };
+
+        Ok((installation_id == INSTALLATION_ID).then_some(details))
+    }
+
+    async fn get_repository_installation(
+        &self,
+        credentials: &(),
+        repository_name: &str,
+    ) -> Result<Option<InstallationDetails>, ApiError> {
+        if repository_name != "test-owner/test-repo" {
+            return Ok(None);
+        }
+
+        self.get_installation(credentials, INSTALLATION_ID).await
+    }
+
+    fn forget_installation(&self, installation_id: usize) {
+        self.record(ApiCall::ForgetInstallation(installation_id));
+    }
+
+    fn rate_limits(&self) -> Vec<RateLimitStatus> {
+        self.rate_limits.lock().unwrap().clone()
+    }
+}
+
+impl GitHubApi for &TestGitHubApi {
+    async fn compare_commits(
+        &self,
+        request: BranchComparisonRequest,
+    ) -> Result<BranchComparison, ApiError> {
+        self.repository.lock().unwrap().comparisons += 1;
+
+        if request.head_branch.contains("unknown") {
+            return Err(ApiError::RepositoryNotFound(ErrorDetails {
+                status: Some(StatusCode::NOT_FOUND),
+                request_id: Some("CAFE:1337".to_owned()),
+                message: Some("Not Found".to_owned()),
+                errors: Vec::new(),
+                documentation_url: None,
+            }));
+        }
+
+        if request.head_branch.contains("unavailable") {
+            return Err(ApiError::Server(ErrorDetails {
+                status: Some(StatusCode::BAD_GATEWAY),
+                ..ErrorDetails::default()
+            }));
+        }
+
+        if request.head_branch.contains("error") {
+            return Err(ApiError::Unspecific);
+        }
+
+        let (ahead_by, behind_by) = match request.head_branch.deref() {
+            "ready/two_ahead" => (2, 0),
+            "ready/one_ahead" => (1, 0),
+            "ready/behind" => (1, 1),
+            _ => (0, 0),
+        };
+
+        let commits = if request.head_branch.contains("unsigned") {
+            vec![ComparedCommit {
+                sha: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+                verified: false,
+                ..ComparedCommit::default()
+            }]
+        } else {
+            Vec::new()
+        };
+
+        Ok(BranchComparison {
+            ahead_by,
+            behind_by,
+            commits,
+        })
+    }
+
+    async fn list_changed_files(
+        &self,
+        _: BranchComparisonRequest,
+    ) -> Result<Vec<ChangedFile>, ApiError> {
+        Ok(Vec::new())
+    }
+
+    async fn update_reference(&self, request: UpdateReferenceRequest) -> Result<(), ApiError> {
+        self.record(ApiCall::UpdateReference {
+            reference: request.reference,
+            sha1: request.sha1,
+        });
+        Ok(())
+    }
+
+    async fn create_commit_status(&self, request: CommitStatusRequest) -> Result<(), ApiError> {
+        self.record(ApiCall::CreateCommitStatus(request));
+        Ok(())
+    }
+
+    async fn list_workflow_runs(
+        &self,
+        _: WorkflowRunsRequest,
+    ) -> Result<Vec<WorkflowRunSummary>, ApiError> {
+        Ok(self.workflow_runs.lock().unwrap().clone())
+    }
+
+    async fn get_branch_head(&self, _: BranchHeadRequest) -> Result<String, ApiError> {
+        Ok(self.repository.lock().unwrap().default_branch_head.clone())
+    }
+
+    async fn get_file_content(
+        &self,
+        request: FileContentRequest,
+    ) -> Result<Option<String>, ApiError> {
+        let mut repository = self.repository.lock().unwrap();
+
+        if request.repository_name.ends_with("/.github") {
+            return Ok(repository.organisation_policy_file.clone());
+        }
+
+        repository.policy_file_reads += 1;
+        Ok(repository.policy_file.clone())
+    }
+
+    async fn create_check_run(&self, request: CheckRunRequest) -> Result<(), ApiError> {
+        self.record(ApiCall::CreateCheckRun(request));
+        Ok(())
+    }
+
+    async fn merge_branch(&self, request: MergeBranchRequest) -> Result<MergeResult, ApiError> {
+        let result = if request.head_branch.contains("conflict") {
+            MergeResult::Conflict
+        } else {
+            MergeResult::Merged
+        };
+
+        self.record(ApiCall::MergeBranch(request));
+        Ok(result)
+    }
+
+    async fn delete_reference(&self, request: DeleteReferenceRequest) -> Result<(), ApiError> {
+        self.record(ApiCall::DeleteReference(request));
+        Ok(())
+    }
+
+    async fn list_installation_repositories(&self) -> Result<Vec<RepositorySummary>, ApiError> {
+        Ok(vec![RepositorySummary {
+            full_name: "test-owner/test-repo".to_owned(),
+            default_branch: "main".to_owned(),
+        }])
+    }
+
+    async fn list_installations(&self) -> Result<Vec<InstallationDetails>, ApiError> {
+        Ok(self
+            .get_installation(&(), INSTALLATION_ID)
+            .await?
+            .into_iter()
+            .collect())
+    }
+}
@@ -0,0 +1,168 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    env,
+    fs::{self, File},
+    path::PathBuf,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use axum::http::StatusCode;
+use common::TestClient;
+use koritsu_app::{PrivateKeySource, ReloadError};
+use serde_json::{Value, json};
+use tokio::signal::unix::{SignalKind, signal};
+
+mod common;
+
+#[tokio::test]
+async fn verifies_events_with_the_reloaded_webhook_secret() {
+    let mut client = TestClient::new();
+
+    client
+        .reload_config(|config| config.github_webhook_secret = "rotated".to_owned())
+        .unwrap();
+    let response = client.send_push_event(&given_push_event_payload()).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn applies_reloaded_merge_policies() {
+    let mut client = TestClient::new();
+
+    client
+        .reload_config(|config| config.default_policy.branch_prefix = "ship/".to_owned())
+        .unwrap();
+    client.send_push_event(&given_push_event_payload()).await;
+
+    assert!(client.api_calls().is_empty());
+}
+
+#[tokio::test]
+async fn keeps_the_previous_configuration_if_the_credentials_are_invalid() {
+    let mut client = TestClient::new();
+
+    let result = client.reload_config(|config| {
+        config.github_webhook_secret = "rotated".to_owned();
+        config.private_keys = vec![PrivateKeySource::Inline("invalid".to_owned())];
+    });
+
+    assert!(matches!(result, Err(ReloadError::Credentials(_))));
+    let response = client.send_push_event(&given_push_event_payload()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn keeps_the_running_settings_that_take_effect_after_a_restart() {
+    let mut client = TestClient::new();
+    let running = client.current_config();
+
+    client
+        .reload_config(|config| {
+            config.server.port = 9090;
+            config.github_base_url = "https://github.example.com/api/v3".to_owned();
+            config.github_http.request_timeout = Duration::from_secs(5);
+            config.default_policy.branch_prefix = "ship/".to_owned();
+        })
+        .unwrap();
+
+    let reloaded = client.current_config();
+    assert_eq!(reloaded.server, running.server);
+    assert_eq!(reloaded.github_base_url, running.github_base_url);
+    assert_eq!(reloaded.github_http, running.github_http);
+    assert_eq!(reloaded.default_policy.branch_prefix, "ship/");
+}
+
+#[tokio::test]
+async fn reloads_the_configuration_file_on_sighup() {
+    // Handling the signal in the test keeps it from terminating the tests
+    // before the watch listens for it
+    let _hangup = signal(SignalKind::hangup()).unwrap();
+    let config_file = given_config_file("sighup", "ready/");
+    let client = TestClient::new();
+    client.watch_config_file(config_file.clone());
+    tokio::task::yield_now().await;
+
+    // Only the signal reveals the change, the file keeps its modification time
+    let modified = fs::metadata(&config_file).unwrap().modified().unwrap();
+    write_config_file(&config_file, "ship/");
+    File::options()
+        .write(true)
+        .open(&config_file)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    Command::new("kill")
+        .args(["-HUP", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+
+    wait_for_branch_prefix(&client, "ship/").await;
+}
+
+#[tokio::test]
+async fn reloads_the_configuration_file_once_it_changed() {
+    let config_file = given_config_file("modified", "ready/");
+    let client = TestClient::new();
+    client.watch_config_file(config_file.clone());
+    tokio::task::yield_now().await;
+
+    write_config_file(&config_file, "ship/");
+
+    wait_for_branch_prefix(&client, "ship/").await;
+}
+
+fn given_config_file(name: &str, branch_prefix: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("koritsu-config-{name}-{}.toml", std::process::id()));
+    write_config_file(&path, branch_prefix);
+    path
+}
+
+fn write_config_file(path: &PathBuf, branch_prefix: &str) {
+    let content = format!(
+        r#"
+[github]
+webhook_secret = "secret"
+client_id = "client"
+private_key = "key"
+
+[merge_policy]
+branch_prefix = "{branch_prefix}"
+"#
+    );
+    fs::write(path, content).unwrap();
+}
+
+async fn wait_for_branch_prefix(client: &TestClient, branch_prefix: &str) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while client.current_config().default_policy.branch_prefix != branch_prefix {
+        assert!(Instant::now() < deadline, "configuration was not reloaded");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+fn given_push_event_payload() -> Value {
+    json!({
+        "ref": "refs/heads/ready/new-feature",
+        "after": "6dcb09b5b57875f334f61aebed695e2e4193db5e",
+        "created": false,
+        "deleted": false,
+        "forced": false,
+        "repository": {
+          "full_name": "test-owner/test-repo",
+          "default_branch": "main",
+        },
+        "installation": {
+          "id": 1337,
+        },
+    })
+}
@@ -0,0 +1,115 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use axum::http::StatusCode;
+use common::{ResponseExt, TestClient};
+use serde_json::json;
+
+mod common;
+
+#[tokio::test]
+async fn accepts_form_encoded_payloads() {
+    let mut client = TestClient::new();
+    let payload = json!({
+        "ref": "refs/heads/ready/new-feature",
+        "after": "6dcb09b5b57875f334f61aebed695e2e4193db5e",
+        "repository": {"full_name": "test-owner/test-repo", "default_branch": "main"},
+        "installation": {"id": 1337},
+    });
+    let form = serde_urlencoded::to_string([("payload", payload.to_string())]).unwrap();
+    let request = client.build_raw_event_request(
+        "push",
+        "application/x-www-form-urlencoded",
+        form.into_bytes(),
+    );
+
+    let response = client.send_request(request).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        client.get("/status").await.body_as_json()["ready_branches"][0]["branch"],
+        "ready/new-feature"
+    );
+}
+
+#[tokio::test]
+async fn accepts_json_payloads_with_charset_parameter() {
+    let mut client = TestClient::new();
+    let request = client.build_raw_event_request(
+        "ping",
+        "application/json; charset=utf-8",
+        br#"{"zen": "Design for failure."}"#.to_vec(),
+    );
+
+    let response = client.send_request(request).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn returns_an_error_if_the_form_has_no_payload_field() {
+    let mut client = TestClient::new();
+    let request = client.build_raw_event_request(
+        "ping",
+        "application/x-www-form-urlencoded",
+        b"data=%7B%7D".to_vec(),
+    );
+
+    let response = client.send_request(request).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.body_as_json(),
+        json!({
+            "status": 400,
+            "title": "Form encoded event payload is invalid",
+            "detail": "missing field `payload`",
+        })
+    );
+}
+
+#[tokio::test]
+async fn rejects_unsupported_content_types() {
+    let mut client = TestClient::new();
+    let request = client.build_raw_event_request("ping", "text/plain", b"ping".to_vec());
+
+    let response = client.send_request(request).await;
+
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(
+        response.body_as_json(),
+        json!({"status": 415, "title": "Unsupported content type text/plain"})
+    );
+}
+
+#[tokio::test]
+async fn treats_payloads_without_content_type_as_json() {
+    let mut client = TestClient::new();
+    let mut request = client.build_raw_event_request(
+        "ping",
+        "application/json",
+        br#"{"zen": "Design for failure."}"#.to_vec(),
+    );
+    request.headers_mut().remove("Content-Type");
+
+    let response = client.send_request(request).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn rejects_payloads_without_content_type_that_are_not_json() {
+    let mut client = TestClient::new();
+    let mut request = client.build_raw_event_request("ping", "application/json", b"ping".to_vec());
+    request.headers_mut().remove("Content-Type");
+
+    let response = client.send_request(request).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
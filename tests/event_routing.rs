@@ -0,0 +1,64 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use axum::http::StatusCode;
+use common::TestClient;
+use serde_json::json;
+
+mod common;
+
+#[tokio::test]
+async fn answers_ping_events() {
+    let mut client = TestClient::new();
+    let payload = json!({"zen": "Keep it logically awesome.", "hook_id": 42});
+
+    let response = client.send_event("ping", &payload).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.body().is_empty());
+}
+
+#[tokio::test]
+async fn reports_unsupported_events_as_not_handled() {
+    let mut client = TestClient::new();
+    let payload = json!({"action": "created"});
+
+    let response = client.send_event("gollum", &payload).await;
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert!(client.api_calls().is_empty());
+}
+
+#[tokio::test]
+async fn reports_actions_without_a_handler_as_not_handled() {
+    let mut client = TestClient::new();
+    let payload = json!({
+        "action": "requested",
+        "workflow_run": {
+            "id": 30433642,
+            "name": "CI",
+            "path": ".github/workflows/ci.yml",
+            "workflow_id": 159038,
+            "run_attempt": 1,
+            "status": "queued",
+            "conclusion": null,
+            "head_branch": "ready/one_ahead",
+            "head_sha": "6dcb09b5b57875f334f61aebed695e2e4193db5e",
+            "head_repository": {"full_name": "test-owner/test-repo"},
+            "event": "push",
+        },
+        "repository": {"full_name": "test-owner/test-repo", "default_branch": "main"},
+        "installation": {"id": 1337},
+    });
+
+    let response = client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert!(client.api_calls().is_empty());
+}
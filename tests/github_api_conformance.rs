@@ -0,0 +1,796 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+//! Runs the same scenarios against every implementation of the GitHub API
+//! traits. The fake GitHub answers REST requests and GraphQL queries from the
+//! same repository.
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use common::{BRANCH_HEAD_PATH, FakeGitHub, given_config, given_credentials};
+use koritsu_app::{
+    ApplicationConfig,
+    github_api::{
+        ApiError, AuthenticationMethod, BranchComparisonRequest, BranchHeadRequest, ChangedFile,
+        CommitIdentity, CommitState, CommitStatusRequest, ComparedCommit, FileContentRequest,
+        GitHubApi, GitHubApiProvider, GitHubGraphQlApiProvider, GitHubRestApiProvider,
+    },
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+mod common;
+
+const MAIN_SHA: &str = "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15";
+const FEATURE_SHA: &str = "e242ed3bffccdf271b7fbaf34ed72d089537b42f";
+const SIGNED_SHA: &str = "6dcb09b5b57875f334f61aebed695e2e4193db5e";
+const POLICY_FILE: &str = ".github/koritsu.toml";
+/// As many changed files as GitHub lists at most for a comparison
+const MAX_FILES: usize = 300;
+
+macro_rules! conformance_tests {
+    ($($scenario:ident),* $(,)?) => {
+        mod rest {
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    let github = super::given_github("").await;
+                    let provider =
+                        koritsu_app::github_api::GitHubRestApiProvider::new(&github.config())
+                            .unwrap();
+                    super::$scenario(&github, &provider).await;
+                }
+            )*
+        }
+
+        mod graphql {
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    let github = super::given_github("").await;
+                    let provider =
+                        koritsu_app::github_api::GitHubGraphQlApiProvider::new(&github.config())
+                            .unwrap();
+                    super::$scenario(&github, &provider).await;
+                }
+            )*
+        }
+    };
+}
+
+conformance_tests!(
+    reads_the_head_of_a_branch,
+    reports_missing_branches_as_not_found,
+    compares_branches_and_finds_unverified_commits,
+    compares_branches_with_commit_details,
+    compares_branches_with_too_many_changed_files,
+    lists_the_changed_files_of_a_comparison,
+    lists_as_many_changed_files_as_github_allows,
+    refuses_truncated_changed_files,
+    reads_files_from_the_default_branch,
+    reads_files_at_a_reference,
+    reports_missing_files_as_absent,
+    creates_commit_statuses,
+);
+
+async fn reads_the_head_of_a_branch(_: &TestGitHub, provider: &impl GitHubApiProvider) {
+    let api = given_api(provider).await;
+
+    let head = api
+        .get_branch_head(branch_head_request("ready/feature"))
+        .await
+        .unwrap();
+
+    assert_eq!(head, FEATURE_SHA);
+}
+
+async fn reports_missing_branches_as_not_found(_: &TestGitHub, provider: &impl GitHubApiProvider) {
+    let api = given_api(provider).await;
+
+    let error = api
+        .get_branch_head(branch_head_request("gone"))
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(error, ApiError::RepositoryNotFound(_)),
+        "expected not found, got {error:?}"
+    );
+}
+
+async fn compares_branches_and_finds_unverified_commits(
+    _: &TestGitHub,
+    provider: &impl GitHubApiProvider,
+) {
+    let api = given_api(provider).await;
+
+    let comparison = api
+        .compare_commits(feature_comparison_request())
+        .await
+        .unwrap();
+
+    assert_eq!(comparison.ahead_by, 2);
+    assert_eq!(comparison.behind_by, 0);
+    assert_eq!(
+        comparison
+            .unverified_commits()
+            .map(|commit| commit.sha.as_str())
+            .collect::<Vec<_>>(),
+        vec![FEATURE_SHA]
+    );
+}
+
+async fn compares_branches_with_commit_details(_: &TestGitHub, provider: &impl GitHubApiProvider) {
+    let api = given_api(provider).await;
+
+    let comparison = api
+        .compare_commits(feature_comparison_request())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        comparison.commits,
+        vec![
+            ComparedCommit {
+                sha: SIGNED_SHA.to_owned(),
+                message: "Add the policy file".to_owned(),
+                author: Some(CommitIdentity {
+                    name: "Mona Lisa".to_owned(),
+                    email: "mona@example.com".to_owned(),
+                    login: Some("octocat".to_owned()),
+                }),
+                committer: Some(CommitIdentity {
+                    name: "GitHub".to_owned(),
+                    email: "noreply@github.com".to_owned(),
+                    login: None,
+                }),
+                parents: vec![MAIN_SHA.to_owned()],
+                verified: true,
+            },
+            ComparedCommit {
+                sha: FEATURE_SHA.to_owned(),
+                message: "Rename the module".to_owned(),
+                author: Some(CommitIdentity {
+                    name: "Mona Lisa".to_owned(),
+                    email: "mona@example.com".to_owned(),
+                    login: Some("octocat".to_owned()),
+                }),
+                committer: Some(CommitIdentity {
+                    name: "Mona Lisa".to_owned(),
+                    email: "mona@example.com".to_owned(),
+                    login: Some("octocat".to_owned()),
+                }),
+                parents: vec![SIGNED_SHA.to_owned()],
+                verified: false,
+            },
+        ]
+    );
+}
+
+/// Merges only look at the commits, so the changed files must not stand in
+/// their way
+async fn compares_branches_with_too_many_changed_files(
+    _: &TestGitHub,
+    provider: &impl GitHubApiProvider,
+) {
+    let api = given_api(provider).await;
+
+    let comparison = api
+        .compare_commits(BranchComparisonRequest {
+            head_branch: "ready/huge".to_owned(),
+            ..feature_comparison_request()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!((comparison.ahead_by, comparison.behind_by), (1, 0));
+}
+
+async fn lists_the_changed_files_of_a_comparison(
+    _: &TestGitHub,
+    provider: &impl GitHubApiProvider,
+) {
+    let api = given_api(provider).await;
+
+    let files = api
+        .list_changed_files(feature_comparison_request())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        files,
+        vec![
+            ChangedFile {
+                filename: POLICY_FILE.to_owned(),
+                status: "modified".to_owned(),
+                additions: 1,
+                deletions: 1,
+                changes: 2,
+                previous_filename: None,
+            },
+            ChangedFile {
+                filename: "src/new.rs".to_owned(),
+                status: "renamed".to_owned(),
+                additions: 3,
+                deletions: 0,
+                changes: 3,
+                previous_filename: Some("src/old.rs".to_owned()),
+            },
+        ]
+    );
+}
+
+async fn lists_as_many_changed_files_as_github_allows(
+    _: &TestGitHub,
+    provider: &impl GitHubApiProvider,
+) {
+    let api = given_api(provider).await;
+
+    let files = api
+        .list_changed_files(BranchComparisonRequest {
+            head_branch: "ready/full".to_owned(),
+            ..feature_comparison_request()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(files.len(), MAX_FILES);
+}
+
+async fn refuses_truncated_changed_files(_: &TestGitHub, provider: &impl GitHubApiProvider) {
+    let api = given_api(provider).await;
+
+    let error = api
+        .list_changed_files(BranchComparisonRequest {
+            head_branch: "ready/huge".to_owned(),
+            ..feature_comparison_request()
+        })
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(error, ApiError::Truncated(_)),
+        "expected a truncated answer, got {error:?}"
+    );
+}
+
+async fn reads_files_from_the_default_branch(_: &TestGitHub, provider: &impl GitHubApiProvider) {
+    let api = given_api(provider).await;
+
+    let content = api
+        .get_file_content(file_content_request("test-owner/test-repo", None))
+        .await
+        .unwrap();
+
+    assert_eq!(content.as_deref(), Some("branch_prefix = \"ready/\"\n"));
+}
+
+async fn reads_files_at_a_reference(_: &TestGitHub, provider: &impl GitHubApiProvider) {
+    let api = given_api(provider).await;
+
+    let content = api
+        .get_file_content(file_content_request(
+            "test-owner/test-repo",
+            Some("ready/feature"),
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(content.as_deref(), Some("branch_prefix = \"ship/\"\n"));
+}
+
+async fn reports_missing_files_as_absent(_: &TestGitHub, provider: &impl GitHubApiProvider) {
+    let api = given_api(provider).await;
+
+    let missing_file = api
+        .get_file_content(FileContentRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            path: "missing.toml".to_owned(),
+            reference: None,
+        })
+        .await
+        .unwrap();
+    let missing_repository = api
+        .get_file_content(file_content_request("test-owner/.github", None))
+        .await
+        .unwrap();
+
+    assert_eq!(missing_file, None);
+    assert_eq!(missing_repository, None);
+}
+
+async fn creates_commit_statuses(github: &TestGitHub, provider: &impl GitHubApiProvider) {
+    let api = given_api(provider).await;
+
+    api.create_commit_status(CommitStatusRequest {
+        repository_name: "test-owner/test-repo".to_owned(),
+        sha1: FEATURE_SHA.to_owned(),
+        state: CommitState::Pending,
+        description: "Waiting for CI".to_owned(),
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(
+        github.state.lock().unwrap().statuses,
+        vec![(FEATURE_SHA.to_owned(), "pending".to_owned())]
+    );
+}
+
+#[tokio::test]
+async fn graphql_reads_with_queries_only() {
+    let github = given_github("").await;
+    let provider = GitHubGraphQlApiProvider::new(&github.config()).unwrap();
+    let api = given_api(&provider).await;
+
+    api.get_branch_head(branch_head_request("main"))
+        .await
+        .unwrap();
+    api.get_file_content(file_content_request("test-owner/test-repo", None))
+        .await
+        .unwrap();
+    api.compare_commits(feature_comparison_request())
+        .await
+        .unwrap();
+
+    let state = github.state.lock().unwrap();
+    assert_eq!(state.rest_reads, 0);
+    assert_eq!(state.queries, 4);
+}
+
+/// The GraphQL API has no changed files for comparisons, they take a single
+/// request to the REST API however many commits the comparison has
+#[tokio::test]
+async fn graphql_reads_the_changed_files_with_a_single_rest_request() {
+    let github = given_github("").await;
+    let provider = GitHubGraphQlApiProvider::new(&github.config()).unwrap();
+    let api = given_api(&provider).await;
+
+    api.list_changed_files(feature_comparison_request())
+        .await
+        .unwrap();
+
+    let state = github.state.lock().unwrap();
+    assert_eq!(state.rest_reads, 1);
+    assert_eq!(state.queries, 0);
+}
+
+#[tokio::test]
+async fn rest_follows_the_link_headers_of_long_comparisons() {
+    let github = given_github("").await;
+    let provider = GitHubRestApiProvider::new(&github.config()).unwrap();
+    let api = given_api(&provider).await;
+
+    let comparison = api
+        .compare_commits(feature_comparison_request())
+        .await
+        .unwrap();
+
+    assert_eq!(comparison.commits.len(), 2);
+    assert_eq!(github.state.lock().unwrap().rest_reads, 2);
+}
+
+#[tokio::test]
+async fn graphql_pages_through_long_comparisons() {
+    let github = given_github("").await;
+    let provider = GitHubGraphQlApiProvider::new(&github.config()).unwrap();
+    let api = given_api(&provider).await;
+
+    api.compare_commits(feature_comparison_request())
+        .await
+        .unwrap();
+
+    assert_eq!(github.state.lock().unwrap().queries, 2);
+}
+
+#[tokio::test]
+async fn graphql_uses_the_endpoint_of_github_enterprise_server() {
+    let github = given_github("/api/v3").await;
+    let provider = GitHubGraphQlApiProvider::new(&github.config()).unwrap();
+    let api = given_api(&provider).await;
+
+    let head = api
+        .get_branch_head(branch_head_request("main"))
+        .await
+        .unwrap();
+
+    assert_eq!(head, MAIN_SHA);
+}
+
+/// The fake GitHub accepts the JWT of any key
+async fn given_api(provider: &impl GitHubApiProvider) -> impl GitHubApi {
+    let credentials = given_credentials(provider, &given_config(&[]));
+
+    provider
+        .get_api(
+            credentials,
+            AuthenticationMethod::AppInstallation {
+                installation_id: 1337,
+                scope: None,
+            },
+        )
+        .await
+        .unwrap()
+}
+
+fn feature_comparison_request() -> BranchComparisonRequest {
+    BranchComparisonRequest {
+        repository_name: "test-owner/test-repo".to_owned(),
+        base_branch: "main".to_owned(),
+        head_branch: "ready/feature".to_owned(),
+    }
+}
+
+fn branch_head_request(branch: &str) -> BranchHeadRequest {
+    BranchHeadRequest {
+        repository_name: "test-owner/test-repo".to_owned(),
+        branch: branch.to_owned(),
+    }
+}
+
+fn file_content_request(repository_name: &str, reference: Option<&str>) -> FileContentRequest {
+    FileContentRequest {
+        repository_name: repository_name.to_owned(),
+        path: POLICY_FILE.to_owned(),
+        reference: reference.map(str::to_owned),
+    }
+}
+
+struct TestGitHub {
+    /// Includes the `/api/v3` prefix of GitHub Enterprise Server
+    base_url: String,
+    state: Arc<Mutex<GitHubState>>,
+}
+
+impl TestGitHub {
+    fn config(&self) -> ApplicationConfig {
+        given_config(&[("GITHUB_BASE_URL", &self.base_url)])
+    }
+}
+
+#[derive(Default)]
+struct GitHubState {
+    rest_reads: usize,
+    queries: usize,
+    /// Commit and state of the created statuses
+    statuses: Vec<(String, String)>,
+}
+
+type SharedState = State<Arc<Mutex<GitHubState>>>;
+
+/// The repository `test-owner/test-repo` has the default branch `main` and a
+/// branch `ready/feature` with a signed and an unsigned commit on top of it.
+/// Both branches contain a policy file.
+fn branch_head(branch: &str) -> Option<&'static str> {
+    match branch {
+        "main" => Some(MAIN_SHA),
+        "ready/feature" => Some(FEATURE_SHA),
+        _ => None,
+    }
+}
+
+fn policy_file(repository: &str, reference: &str, path: &str) -> Option<&'static str> {
+    match (repository, reference, path) {
+        ("test-repo", "main", POLICY_FILE) => Some("branch_prefix = \"ready/\"\n"),
+        ("test-repo", "ready/feature", POLICY_FILE) => Some("branch_prefix = \"ship/\"\n"),
+        _ => None,
+    }
+}
+
+struct FeatureCommit {
+    sha: &'static str,
+    parent: &'static str,
+    message: &'static str,
+    /// Login, name and email
+    author: (Option<&'static str>, &'static str, &'static str),
+    committer: (Option<&'static str>, &'static str, &'static str),
+    verified: bool,
+}
+
+const MONA: (Option<&str>, &str, &str) = (Some("octocat"), "Mona Lisa", "mona@example.com");
+const WEB_FLOW: (Option<&str>, &str, &str) = (None, "GitHub", "noreply@github.com");
+
+/// Oldest first
+const FEATURE_COMMITS: [FeatureCommit; 2] = [
+    FeatureCommit {
+        sha: SIGNED_SHA,
+        parent: MAIN_SHA,
+        message: "Add the policy file",
+        author: MONA,
+        committer: WEB_FLOW,
+        verified: true,
+    },
+    FeatureCommit {
+        sha: FEATURE_SHA,
+        parent: SIGNED_SHA,
+        message: "Rename the module",
+        author: MONA,
+        committer: MONA,
+        verified: false,
+    },
+];
+
+fn feature_files() -> Value {
+    json!([
+        {"filename": POLICY_FILE, "status": "modified", "additions": 1, "deletions": 1, "changes": 2},
+        {
+            "filename": "src/new.rs",
+            "previous_filename": "src/old.rs",
+            "status": "renamed",
+            "additions": 3,
+            "deletions": 0,
+            "changes": 3,
+        },
+    ])
+}
+
+fn many_files(count: usize) -> Value {
+    (0..count)
+        .map(|index| json!({"filename": format!("src/{index}.rs"), "status": "added", "additions": 1, "deletions": 0, "changes": 1}))
+        .collect()
+}
+
+async fn given_github(rest_prefix: &str) -> TestGitHub {
+    let state = Arc::new(Mutex::new(GitHubState::default()));
+
+    let base_url = FakeGitHub::new()
+        .with_prefix(rest_prefix)
+        .route(
+            BRANCH_HEAD_PATH,
+            get(reference_handler).with_state(state.clone()),
+        )
+        .route(
+            "/repos/{owner}/{repository}/compare/{*spec}",
+            get(compare_handler).with_state(state.clone()),
+        )
+        .route(
+            "/repos/{owner}/{repository}/contents/{*path}",
+            get(contents_handler).with_state(state.clone()),
+        )
+        .route(
+            "/repos/{owner}/{repository}/statuses/{sha}",
+            post(status_handler).with_state(state.clone()),
+        )
+        .graphql(post(graphql_handler).with_state(state.clone()))
+        .start()
+        .await;
+
+    TestGitHub { base_url, state }
+}
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, Json(json!({"message": "Not Found"}))).into_response()
+}
+
+async fn reference_handler(
+    State(state): SharedState,
+    Path((_, repository, branch)): Path<(String, String, String)>,
+) -> Response {
+    state.lock().unwrap().rest_reads += 1;
+
+    match branch_head(&branch).filter(|_| repository == "test-repo") {
+        Some(sha) => Json(json!({"object": {"sha": sha}})).into_response(),
+        None => not_found(),
+    }
+}
+
+#[derive(Deserialize)]
+struct CompareQuery {
+    page: Option<usize>,
+}
+
+/// Lists one commit per page and the files only on the first page, like
+/// GitHub does for long comparisons
+async fn compare_handler(
+    State(state): SharedState,
+    Path((_, repository, spec)): Path<(String, String, String)>,
+    Query(query): Query<CompareQuery>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Response {
+    state.lock().unwrap().rest_reads += 1;
+
+    let file_count = match spec.as_str() {
+        "main...ready/full" => Some(MAX_FILES),
+        "main...ready/huge" => Some(MAX_FILES + 1),
+        _ => None,
+    };
+    if let Some(file_count) = file_count.filter(|_| repository == "test-repo") {
+        return Json(json!({
+            "ahead_by": 1,
+            "behind_by": 0,
+            "commits": [],
+            "files": many_files(file_count),
+        }))
+        .into_response();
+    }
+    if repository != "test-repo" || spec != "main...ready/feature" {
+        return not_found();
+    }
+
+    let page = query.page.unwrap_or(1);
+    let commit = &FEATURE_COMMITS[page - 1];
+    let identity = |(login, name, email): (Option<&str>, &str, &str)| {
+        (
+            json!({"name": name, "email": email}),
+            login.map(|login| json!({"login": login})),
+        )
+    };
+    let (author, author_account) = identity(commit.author);
+    let (committer, committer_account) = identity(commit.committer);
+    let files = if page == 1 {
+        feature_files()
+    } else {
+        json!([])
+    };
+
+    let body = Json(json!({
+        "ahead_by": 2,
+        "behind_by": 0,
+        "commits": [{
+            "sha": commit.sha,
+            "commit": {
+                "message": commit.message,
+                "author": author,
+                "committer": committer,
+                "verification": {"verified": commit.verified},
+            },
+            "author": author_account,
+            "committer": committer_account,
+            "parents": [{"sha": commit.parent}],
+        }],
+        "files": files,
+    }));
+
+    if page < FEATURE_COMMITS.len() {
+        let host = headers["host"].to_str().unwrap();
+        let link = format!(
+            "<http://{host}{}?page={}>; rel=\"next\"",
+            uri.path(),
+            page + 1
+        );
+        ([("link", link)], body).into_response()
+    } else {
+        body.into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct ContentsQuery {
+    #[serde(rename = "ref")]
+    reference: Option<String>,
+}
+
+async fn contents_handler(
+    State(state): SharedState,
+    Path((_, repository, path)): Path<(String, String, String)>,
+    Query(query): Query<ContentsQuery>,
+) -> Response {
+    state.lock().unwrap().rest_reads += 1;
+
+    let reference = query.reference.as_deref().unwrap_or("main");
+    match policy_file(&repository, reference, &path) {
+        Some(content) => Json(json!({
+            "content": BASE64_STANDARD.encode(content),
+            "encoding": "base64",
+        }))
+        .into_response(),
+        None => not_found(),
+    }
+}
+
+async fn status_handler(
+    State(state): SharedState,
+    Path((_, _, sha)): Path<(String, String, String)>,
+    body: Bytes,
+) -> StatusCode {
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let commit_state = body["state"].as_str().unwrap().to_owned();
+    state.lock().unwrap().statuses.push((sha, commit_state));
+    StatusCode::CREATED
+}
+
+#[derive(Deserialize)]
+struct GraphQlRequest {
+    query: String,
+    variables: Value,
+}
+
+/// Tells the queries apart by the variables they use
+async fn graphql_handler(
+    State(state): SharedState,
+    Json(request): Json<GraphQlRequest>,
+) -> Json<Value> {
+    state.lock().unwrap().queries += 1;
+    assert!(
+        request
+            .query
+            .contains("repository(owner: $owner, name: $name)")
+    );
+
+    let variables = &request.variables;
+    let repository = variables["name"].as_str().unwrap();
+    if variables["owner"] != "test-owner" || !matches!(repository, "test-repo" | ".github") {
+        return Json(json!({
+            "data": {"repository": null},
+            "errors": [{
+                "type": "NOT_FOUND",
+                "path": ["repository"],
+                "message": format!("Could not resolve to a Repository with the name 'test-owner/{repository}'."),
+            }],
+        }));
+    }
+    if repository == ".github" {
+        return Json(json!({"data": {"repository": {"object": null, "ref": null}}}));
+    }
+
+    let data = if let Some(expression) = variables["expression"].as_str() {
+        let (reference, path) = expression.split_once(':').unwrap();
+        let reference = if reference == "HEAD" {
+            "main"
+        } else {
+            reference
+        };
+        let object = policy_file(repository, reference, path)
+            .map(|text| json!({"text": text, "isTruncated": false}));
+        json!({"object": object})
+    } else if let Some(head) = variables["head"].as_str() {
+        assert_eq!(variables["base"], "refs/heads/main");
+        if matches!(head, "refs/heads/ready/full" | "refs/heads/ready/huge") {
+            return Json(json!({"data": {"repository": {"ref": {"compare": {
+                "aheadBy": 1,
+                "behindBy": 0,
+                "commits": {"nodes": [], "pageInfo": {"hasNextPage": false, "endCursor": null}},
+            }}}}}));
+        }
+        assert_eq!(head, "refs/heads/ready/feature");
+
+        // One commit per page to cover paging
+        let page = variables["after"]
+            .as_str()
+            .map_or(0, |cursor| cursor.parse().unwrap());
+        let commit = &FEATURE_COMMITS[page];
+        let has_next_page = page + 1 < FEATURE_COMMITS.len();
+        let actor = |(login, name, email): (Option<&str>, &str, &str)| json!({"name": name, "email": email, "user": login.map(|login| json!({"login": login}))});
+
+        json!({"ref": {"compare": {
+            "aheadBy": 2,
+            "behindBy": 0,
+            "commits": {
+                "nodes": [{
+                    "oid": commit.sha,
+                    "message": commit.message,
+                    "author": actor(commit.author),
+                    "committer": actor(commit.committer),
+                    "parents": {"nodes": [{"oid": commit.parent}]},
+                    "signature": {"isValid": commit.verified},
+                }],
+                "pageInfo": {"hasNextPage": has_next_page, "endCursor": (page + 1).to_string()},
+            },
+        }}})
+    } else {
+        let qualified_name = variables["qualifiedName"].as_str().unwrap();
+        let reference = qualified_name
+            .strip_prefix("refs/heads/")
+            .and_then(branch_head)
+            .map(|sha| json!({"target": {"oid": sha}}));
+        json!({"ref": reference})
+    };
+
+    Json(json!({"data": {"repository": data}}))
+}
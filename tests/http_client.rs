@@ -0,0 +1,123 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::time::Duration;
+
+use axum::{http::StatusCode, routing::post};
+use common::{FakeGitHub, GivenProvider, given_config};
+use koritsu_app::github_api::{
+    ApiError, AuthenticationMethod, BranchHeadRequest, CheckConclusion, CheckRunRequest, GitHubApi,
+    GitHubRestApiProvider,
+};
+use tokio::time::timeout;
+
+mod common;
+
+#[tokio::test]
+async fn gives_up_on_requests_github_does_not_answer() {
+    let github = given_github().await;
+    let provider = GivenProvider::new(&given_config(&[
+        ("GITHUB_BASE_URL", &github),
+        ("GITHUB_REQUEST_TIMEOUT", "1"),
+    ]));
+    let api = provider.get_api(auth_method()).await.unwrap();
+
+    let check_run = timeout(
+        Duration::from_secs(10),
+        api.create_check_run(CheckRunRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            head_sha: "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15".to_owned(),
+            conclusion: CheckConclusion::Success,
+            title: "Merged".to_owned(),
+            summary: "Merged".to_owned(),
+        }),
+    )
+    .await
+    .expect("the request timeout ends the request");
+
+    assert!(matches!(check_run, Err(ApiError::Transport(_))));
+}
+
+#[tokio::test]
+async fn sends_requests_through_the_configured_proxy() {
+    let proxy = given_github().await;
+    let provider = GivenProvider::new(&given_config(&[
+        ("GITHUB_BASE_URL", "http://github.invalid"),
+        ("GITHUB_PROXY", &proxy),
+    ]));
+    let api = provider.get_api(auth_method()).await.unwrap();
+
+    let head = api.get_branch_head(branch_head_request()).await;
+
+    assert_eq!(head.unwrap(), "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15");
+}
+
+#[tokio::test]
+async fn bypasses_the_proxy_for_hosts_in_no_proxy() {
+    let github = given_github().await;
+    let provider = GivenProvider::new(&given_config(&[
+        ("GITHUB_BASE_URL", &github),
+        ("GITHUB_PROXY", "http://proxy.invalid:3128"),
+        ("GITHUB_NO_PROXY", "127.0.0.1"),
+    ]));
+    let api = provider.get_api(auth_method()).await.unwrap();
+
+    let head = api.get_branch_head(branch_head_request()).await;
+
+    assert_eq!(head.unwrap(), "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15");
+}
+
+#[test]
+fn rejects_files_without_ca_certificates() {
+    let file = std::env::temp_dir().join(format!("koritsu-ca-{}.pem", std::process::id()));
+    std::fs::write(&file, "not a certificate").unwrap();
+
+    let provider = GitHubRestApiProvider::new(&given_config(&[(
+        "GITHUB_CA_CERTIFICATE_FILE",
+        file.to_str().unwrap(),
+    )]));
+    std::fs::remove_file(&file).unwrap();
+
+    let Err(error) = provider else {
+        panic!("the provider must not be created");
+    };
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "{} does not contain PEM encoded certificates",
+            file.display()
+        )
+    );
+}
+
+fn auth_method() -> AuthenticationMethod {
+    AuthenticationMethod::AppInstallation {
+        installation_id: 1337,
+        scope: None,
+    }
+}
+
+fn branch_head_request() -> BranchHeadRequest {
+    BranchHeadRequest {
+        repository_name: "test-owner/test-repo".to_owned(),
+        branch: "main".to_owned(),
+    }
+}
+
+/// Starts a server that answers like GitHub, except for check runs, which it
+/// never answers. It also serves as a proxy, because it only looks at paths.
+async fn given_github() -> String {
+    FakeGitHub::new()
+        .route(
+            "/repos/{owner}/{repository}/check-runs",
+            post(std::future::pending::<StatusCode>),
+        )
+        .start()
+        .await
+}
@@ -0,0 +1,267 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+};
+use common::{ACCESS_TOKENS_PATH, BRANCH_HEAD_PATH, FakeGitHub, GivenProvider, given_provider};
+use koritsu_app::github_api::{
+    AuthenticationMethod, BranchHeadRequest, GitHubApi, GitHubApiProvider, TokenScope,
+};
+use serde_json::{Value, json};
+
+mod common;
+
+const INSTALLATION_ID: usize = 1337;
+const FAR_FUTURE: &str = "2999-01-01T00:00:00Z";
+
+#[tokio::test]
+async fn reuses_the_access_token_of_an_installation() {
+    let github = given_github(FAR_FUTURE).await;
+    let provider = github.provider();
+
+    get_branch_head(&provider).await.unwrap();
+    get_branch_head(&provider).await.unwrap();
+
+    assert_eq!(github.token_requests(), 1);
+}
+
+#[tokio::test]
+async fn refreshes_access_tokens_ahead_of_their_expiry() {
+    let github = given_github("2000-01-01T00:00:00Z").await;
+    let provider = github.provider();
+
+    get_branch_head(&provider).await.unwrap();
+    get_branch_head(&provider).await.unwrap();
+
+    assert_eq!(github.token_requests(), 2);
+}
+
+#[tokio::test]
+async fn concurrent_events_share_one_token_request() {
+    let github = given_github(FAR_FUTURE).await;
+    let provider = github.provider();
+
+    let (first, second) = tokio::join!(get_branch_head(&provider), get_branch_head(&provider));
+
+    assert!(first.is_ok() && second.is_ok());
+    assert_eq!(github.token_requests(), 1);
+}
+
+#[tokio::test]
+async fn requests_a_new_access_token_after_github_rejected_it() {
+    let github = given_github(FAR_FUTURE).await;
+    let provider = github.provider();
+
+    get_branch_head(&provider).await.unwrap();
+    github.state.revoked_tokens.store(1, Ordering::SeqCst);
+    assert!(get_branch_head(&provider).await.is_err());
+    get_branch_head(&provider).await.unwrap();
+
+    assert_eq!(github.token_requests(), 2);
+}
+
+#[tokio::test]
+async fn forgets_the_access_token_of_a_removed_installation() {
+    let github = given_github(FAR_FUTURE).await;
+    let provider = github.provider();
+
+    get_branch_head(&provider).await.unwrap();
+    get_scoped_branch_head(&provider, contents_scope())
+        .await
+        .unwrap();
+    provider.forget_installation(INSTALLATION_ID);
+    get_branch_head(&provider).await.unwrap();
+    get_scoped_branch_head(&provider, contents_scope())
+        .await
+        .unwrap();
+
+    assert_eq!(github.token_requests(), 4);
+}
+
+#[tokio::test]
+async fn requests_access_tokens_limited_to_the_scope() {
+    let github = given_github(FAR_FUTURE).await;
+    let provider = github.provider();
+
+    get_scoped_branch_head(&provider, contents_scope())
+        .await
+        .unwrap();
+    get_branch_head(&provider).await.unwrap();
+
+    assert_eq!(
+        github.token_request_bodies(),
+        vec![
+            json!({"repositories": ["test-repo"], "permissions": {"contents": "read"}}),
+            Value::Null,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn keeps_one_access_token_per_scope() {
+    let github = given_github(FAR_FUTURE).await;
+    let provider = github.provider();
+    let statuses_scope = contents_scope().with_permission("statuses", "write");
+
+    get_scoped_branch_head(&provider, contents_scope())
+        .await
+        .unwrap();
+    get_scoped_branch_head(&provider, statuses_scope.clone())
+        .await
+        .unwrap();
+    get_scoped_branch_head(&provider, contents_scope())
+        .await
+        .unwrap();
+    get_scoped_branch_head(&provider, statuses_scope)
+        .await
+        .unwrap();
+
+    assert_eq!(github.token_requests(), 2);
+}
+
+fn contents_scope() -> TokenScope {
+    TokenScope::repository("test-owner/test-repo").with_permission("contents", "read")
+}
+
+async fn get_branch_head(provider: &GivenProvider) -> Result<String, String> {
+    branch_head(provider, None).await
+}
+
+async fn get_scoped_branch_head(
+    provider: &GivenProvider,
+    scope: TokenScope,
+) -> Result<String, String> {
+    branch_head(provider, Some(scope)).await
+}
+
+async fn branch_head(
+    provider: &GivenProvider,
+    scope: Option<TokenScope>,
+) -> Result<String, String> {
+    let api = provider
+        .get_api(AuthenticationMethod::AppInstallation {
+            installation_id: INSTALLATION_ID,
+            scope,
+        })
+        .await
+        .map_err(|error| error.to_string())?;
+
+    api.get_branch_head(BranchHeadRequest {
+        repository_name: "test-owner/test-repo".to_owned(),
+        branch: "main".to_owned(),
+    })
+    .await
+    .map_err(|error| error.to_string())
+}
+
+struct TestGitHub {
+    base_url: String,
+    state: Arc<GitHubState>,
+}
+
+impl TestGitHub {
+    fn provider(&self) -> GivenProvider {
+        given_provider(&self.base_url)
+    }
+
+    fn token_requests(&self) -> usize {
+        self.state.token_requests.load(Ordering::SeqCst)
+    }
+
+    fn token_request_bodies(&self) -> Vec<Value> {
+        self.state.token_request_bodies.lock().unwrap().clone()
+    }
+}
+
+struct GitHubState {
+    expires_at: &'static str,
+    token_requests: AtomicUsize,
+    /// The JSON bodies of the token requests, `null` for empty ones
+    token_request_bodies: Mutex<Vec<Value>>,
+    /// Access tokens below this number are answered with `401 Unauthorized`
+    revoked_tokens: AtomicUsize,
+}
+
+/// Starts a server that hands out numbered access tokens like GitHub and
+/// answers reference requests made with tokens that are not revoked.
+async fn given_github(expires_at: &'static str) -> TestGitHub {
+    let state = Arc::new(GitHubState {
+        expires_at,
+        token_requests: AtomicUsize::new(0),
+        token_request_bodies: Mutex::new(Vec::new()),
+        revoked_tokens: AtomicUsize::new(0),
+    });
+
+    let base_url = FakeGitHub::new()
+        .route(
+            ACCESS_TOKENS_PATH,
+            post(access_tokens_handler).with_state(state.clone()),
+        )
+        .route(
+            BRANCH_HEAD_PATH,
+            get(reference_handler).with_state(state.clone()),
+        )
+        .start()
+        .await;
+
+    TestGitHub { base_url, state }
+}
+
+async fn access_tokens_handler(
+    State(state): State<Arc<GitHubState>>,
+    body: Bytes,
+) -> (StatusCode, Json<Value>) {
+    let token = state.token_requests.fetch_add(1, Ordering::SeqCst);
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    state.token_request_bodies.lock().unwrap().push(body);
+    // Gives concurrent requests the chance to ask for a token as well
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    (
+        StatusCode::CREATED,
+        Json(json!({"token": format!("token-{token}"), "expires_at": state.expires_at})),
+    )
+}
+
+async fn reference_handler(
+    State(state): State<Arc<GitHubState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    let token: usize = headers["Authorization"]
+        .to_str()
+        .unwrap()
+        .trim_start_matches("Bearer token-")
+        .parse()
+        .unwrap();
+
+    if token < state.revoked_tokens.load(Ordering::SeqCst) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"message": "Bad credentials"})),
+        );
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({"object": {"sha": "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15"}})),
+    )
+}
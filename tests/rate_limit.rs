@@ -0,0 +1,245 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::get,
+};
+use common::{
+    BRANCH_HEAD_PATH, FakeGitHub, GivenProvider, ResponseExt, TestClient, given_provider,
+};
+use koritsu_app::github_api::{
+    AuthenticationMethod, BranchHeadRequest, GitHubApi, GitHubApiProvider, RateLimitStatus,
+};
+use serde_json::{Value, json};
+
+mod common;
+
+#[tokio::test]
+async fn repeats_requests_after_a_secondary_rate_limit() {
+    let github = given_github().await;
+    let provider = github.provider();
+    github.answer(StatusCode::FORBIDDEN, &[("retry-after", "1")]);
+    let started = Instant::now();
+
+    let head = get_branch_head(&provider).await;
+
+    assert_eq!(head.unwrap(), "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15");
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn delays_requests_while_the_budget_is_exhausted() {
+    let github = given_github().await;
+    let provider = github.provider();
+    let reset = seconds_since_epoch() + 2;
+    github.answer(
+        StatusCode::OK,
+        &[
+            ("x-ratelimit-limit", "5000"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", &reset.to_string()),
+        ],
+    );
+
+    get_branch_head(&provider).await.unwrap();
+    let started = Instant::now();
+    get_branch_head(&provider).await.unwrap();
+
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn fails_requests_instead_of_pausing_for_long() {
+    let github = given_github().await;
+    let provider = github.provider();
+    github.answer(StatusCode::FORBIDDEN, &[("retry-after", "3600")]);
+    let started = Instant::now();
+
+    let head = get_branch_head(&provider).await;
+
+    assert!(head.unwrap_err().starts_with("Rate limit exceeded"));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn fails_requests_while_the_budget_is_exhausted_for_long() {
+    let github = given_github().await;
+    let provider = github.provider();
+    let reset = seconds_since_epoch() + 3600;
+    github.answer(
+        StatusCode::OK,
+        &[
+            ("x-ratelimit-limit", "5000"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", &reset.to_string()),
+        ],
+    );
+
+    get_branch_head(&provider).await.unwrap();
+    let started = Instant::now();
+    let head = get_branch_head(&provider).await;
+
+    assert!(head.unwrap_err().starts_with("Rate limit exceeded"));
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn keeps_the_rate_limit_of_every_installation() {
+    let github = given_github().await;
+    let provider = github.provider();
+    github.answer(
+        StatusCode::OK,
+        &[
+            ("x-ratelimit-limit", "5000"),
+            ("x-ratelimit-remaining", "4999"),
+            ("x-ratelimit-reset", "1900000000"),
+        ],
+    );
+
+    get_branch_head(&provider).await.unwrap();
+
+    assert_eq!(
+        provider.rate_limits(),
+        [
+            RateLimitStatus {
+                installation_id: None,
+                limit: None,
+                remaining: None,
+                reset_at: None,
+                paused_until: None,
+            },
+            RateLimitStatus {
+                installation_id: Some(1337),
+                limit: Some(5000),
+                remaining: Some(4999),
+                reset_at: Some(1900000000),
+                paused_until: None,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn exposes_the_rate_limits_on_the_status_and_metrics_endpoints() {
+    let mut client = TestClient::new();
+    client.given_rate_limits(vec![RateLimitStatus {
+        installation_id: Some(1337),
+        limit: Some(5000),
+        remaining: Some(12),
+        reset_at: Some(1900000000),
+        paused_until: None,
+    }]);
+
+    assert_eq!(
+        client.get("/status").await.body_as_json()["rate_limits"],
+        json!([{
+            "installation_id": 1337,
+            "limit": 5000,
+            "remaining": 12,
+            "reset_at": 1900000000,
+            "paused_until": null,
+        }])
+    );
+
+    let metrics = client.get("/metrics").await;
+    let metrics = String::from_utf8(metrics.body().to_vec()).unwrap();
+    assert!(metrics.contains(
+        "koritsu_github_rate_limit_remaining{subject=\"installation\",installation_id=\"1337\"} 12\n"
+    ));
+    assert!(
+        metrics.contains("# TYPE koritsu_github_rate_limit_paused_until_timestamp_seconds gauge\n")
+    );
+}
+
+async fn get_branch_head(provider: &GivenProvider) -> Result<String, String> {
+    let api = provider
+        .get_api(AuthenticationMethod::AppInstallation {
+            installation_id: 1337,
+            scope: None,
+        })
+        .await
+        .map_err(|error| error.to_string())?;
+
+    api.get_branch_head(BranchHeadRequest {
+        repository_name: "test-owner/test-repo".to_owned(),
+        branch: "main".to_owned(),
+    })
+    .await
+    .map_err(|error| error.to_string())
+}
+
+fn seconds_since_epoch() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+type Answers = Arc<Mutex<VecDeque<(StatusCode, HeaderMap)>>>;
+
+struct TestGitHub {
+    base_url: String,
+    answers: Answers,
+}
+
+impl TestGitHub {
+    fn provider(&self) -> GivenProvider {
+        given_provider(&self.base_url)
+    }
+
+    /// Queues the status code and headers of the next reference request
+    fn answer(&self, status: StatusCode, headers: &[(&'static str, &str)]) {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect();
+        self.answers.lock().unwrap().push_back((status, headers));
+    }
+}
+
+/// Starts a server that answers reference requests with the queued status
+/// codes and headers, and like GitHub once they are used up.
+async fn given_github() -> TestGitHub {
+    let answers = Answers::default();
+
+    let base_url = FakeGitHub::new()
+        .route(
+            BRANCH_HEAD_PATH,
+            get(reference_handler).with_state(answers.clone()),
+        )
+        .start()
+        .await;
+
+    TestGitHub { base_url, answers }
+}
+
+async fn reference_handler(State(answers): State<Answers>) -> (StatusCode, HeaderMap, Json<Value>) {
+    let (status, headers) = answers
+        .lock()
+        .unwrap()
+        .pop_front()
+        .unwrap_or((StatusCode::OK, HeaderMap::new()));
+
+    let body = if status.is_success() {
+        json!({"object": {"sha": "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15"}})
+    } else {
+        json!({"message": "You have exceeded a secondary rate limit"})
+    };
+
+    (status, headers, Json(body))
+}
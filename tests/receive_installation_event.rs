@@ -0,0 +1,268 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use axum::{body::Body, extract::Request, http::StatusCode};
+use common::{ApiCall, INSTALLATION_ID, ResponseExt, TestClient};
+use serde_json::{Value, json};
+
+mod common;
+
+#[tokio::test]
+async fn registers_created_installations() {
+    let mut client = TestClient::new();
+    let payload = given_installation_event_payload("created", 42);
+
+    let response = client.send_event("installation", &payload).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        client
+            .get_admin("/admin/installations/42")
+            .await
+            .body_as_json(),
+        json!({
+            "id": 42,
+            "account": "test-owner",
+            "repositories": ["test-owner/test-repo"],
+            "permissions": {"contents": "write", "statuses": "write"},
+            "suspended": false,
+        })
+    );
+}
+
+#[tokio::test]
+async fn lists_registered_installations() {
+    let mut client = TestClient::new();
+    client
+        .send_event(
+            "installation",
+            &given_installation_event_payload("created", 42),
+        )
+        .await;
+    client
+        .send_event(
+            "installation",
+            &given_installation_event_payload("created", 43),
+        )
+        .await;
+
+    let response = client.get_admin("/admin/installations").await;
+
+    let ids: Vec<Value> = response
+        .body_as_json()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|installation| installation["id"].clone())
+        .collect();
+    assert_eq!(ids, vec![json!(42), json!(43)]);
+}
+
+#[tokio::test]
+async fn returns_not_found_for_unregistered_installations() {
+    let mut client = TestClient::new();
+
+    let response = client.get_admin("/admin/installations/42").await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.body_as_json(),
+        json!({
+            "status": 404,
+            "title": "Installation not found",
+            "detail": "Installation 42 is not registered",
+        })
+    );
+}
+
+#[tokio::test]
+async fn requires_the_admin_token() {
+    let mut client = TestClient::new();
+
+    let missing = client.get("/admin/installations").await;
+    let wrong = client
+        .send_request(
+            Request::builder()
+                .uri("/admin/installations/42")
+                .header("Authorization", "Bearer guessed")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn disables_the_admin_endpoints_without_a_token() {
+    let mut client = TestClient::with_config(|config| config.admin_token = None);
+
+    let response = client.get_admin("/admin/installations").await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn purges_the_state_of_deleted_installations() {
+    let mut client = TestClient::new();
+    client
+        .send_event(
+            "installation",
+            &given_installation_event_payload("created", INSTALLATION_ID),
+        )
+        .await;
+    client
+        .send_push_event(&given_ready_branch_push_payload(INSTALLATION_ID))
+        .await;
+
+    client
+        .send_event(
+            "installation",
+            &given_installation_event_payload("deleted", INSTALLATION_ID),
+        )
+        .await;
+
+    assert_eq!(
+        client
+            .get_admin("/admin/installations")
+            .await
+            .body_as_json(),
+        json!([])
+    );
+    assert_eq!(
+        client.get("/status").await.body_as_json(),
+        json!({"ready_branches": [], "rate_limits": []})
+    );
+    assert!(
+        client
+            .api_calls()
+            .contains(&ApiCall::ForgetInstallation(INSTALLATION_ID))
+    );
+}
+
+#[tokio::test]
+async fn looks_up_installations_that_are_not_registered_yet() {
+    let mut client = TestClient::new();
+
+    let response = client
+        .send_push_event(&given_ready_branch_push_payload(INSTALLATION_ID))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        client
+            .get_admin("/admin/installations/1337")
+            .await
+            .body_as_json()["account"],
+        "test-owner"
+    );
+}
+
+#[tokio::test]
+async fn rejects_events_from_unknown_installations() {
+    let mut client = TestClient::new();
+
+    let response = client
+        .send_push_event(&given_ready_branch_push_payload(4242))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.body_as_json(),
+        json!({
+            "status": 403,
+            "title": "Event was rejected",
+            "detail": "Installation 4242 is not known",
+        })
+    );
+}
+
+#[tokio::test]
+async fn rejects_events_for_removed_repositories() {
+    let mut client = TestClient::new();
+    client
+        .send_event(
+            "installation",
+            &given_installation_event_payload("created", INSTALLATION_ID),
+        )
+        .await;
+    client
+        .send_event(
+            "installation_repositories",
+            &json!({
+                "action": "removed",
+                "installation": given_installation(INSTALLATION_ID),
+                "repository_selection": "selected",
+                "repositories_added": [],
+                "repositories_removed": [{"full_name": "test-owner/test-repo"}],
+            }),
+        )
+        .await;
+
+    let response = client
+        .send_push_event(&given_ready_branch_push_payload(INSTALLATION_ID))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.body_as_json()["detail"],
+        "Repository test-owner/test-repo is not part of installation 1337"
+    );
+}
+
+#[tokio::test]
+async fn rejects_events_from_suspended_installations() {
+    let mut client = TestClient::new();
+    let mut payload = given_installation_event_payload("suspend", INSTALLATION_ID);
+    payload["installation"]["suspended_at"] = json!("2025-05-01T12:00:00Z");
+    client.send_event("installation", &payload).await;
+
+    let response = client
+        .send_push_event(&given_ready_branch_push_payload(INSTALLATION_ID))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.body_as_json()["detail"],
+        "Installation 1337 is suspended"
+    );
+}
+
+fn given_installation_event_payload(action: &str, installation_id: usize) -> Value {
+    json!({
+        "action": action,
+        "installation": given_installation(installation_id),
+        "repositories": [{"full_name": "test-owner/test-repo"}],
+    })
+}
+
+fn given_installation(installation_id: usize) -> Value {
+    json!({
+        "id": installation_id,
+        "account": {"login": "test-owner"},
+        "repository_selection": "selected",
+        "permissions": {"contents": "write", "statuses": "write"},
+        "suspended_at": null,
+    })
+}
+
+fn given_ready_branch_push_payload(installation_id: usize) -> Value {
+    json!({
+        "ref": "refs/heads/ready/new-feature",
+        "after": "6dcb09b5b57875f334f61aebed695e2e4193db5e",
+        "repository": {
+          "full_name": "test-owner/test-repo",
+          "default_branch": "main",
+        },
+        "installation": {
+          "id": installation_id,
+        },
+    })
+}
@@ -0,0 +1,199 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use axum::http::StatusCode;
+use common::{ApiCall, ResponseExt, TestClient};
+use koritsu_app::github_api::{CommitState, CommitStatusRequest, TokenScope};
+use serde_json::{Value, json};
+
+mod common;
+
+const FIRST_SHA: &str = "6dcb09b5b57875f334f61aebed695e2e4193db5e";
+const SECOND_SHA: &str = "0d1a26e67d8f5eaf1f6ba5c57fc3c7d91ac0fd1c";
+
+#[tokio::test]
+async fn registers_a_pushed_ready_branch() {
+    let mut client = TestClient::new();
+    let payload = given_push_event_payload("refs/heads/ready/new-feature", FIRST_SHA);
+
+    let response = client.send_push_event(&payload).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        client.get("/status").await.body_as_json(),
+        json!({
+            "ready_branches": [{
+                "installation_id": 1337,
+                "repository_name": "test-owner/test-repo",
+                "branch": "ready/new-feature",
+                "head_sha": FIRST_SHA,
+                "status": "waiting_for_ci",
+            }],
+            "rate_limits": [],
+        })
+    );
+}
+
+#[tokio::test]
+async fn posts_a_pending_status_for_a_pushed_ready_branch() {
+    let mut client = TestClient::new();
+    let payload = given_push_event_payload("refs/heads/ready/new-feature", FIRST_SHA);
+
+    client.send_push_event(&payload).await;
+
+    assert_eq!(
+        client.api_calls(),
+        vec![ApiCall::CreateCommitStatus(CommitStatusRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            sha1: FIRST_SHA.to_owned(),
+            state: CommitState::Pending,
+            description: "Waiting for CI".to_owned(),
+        })]
+    );
+}
+
+#[tokio::test]
+async fn limits_the_access_token_to_the_repository_and_the_needed_permissions() {
+    let mut client = TestClient::new();
+    let payload = given_push_event_payload("refs/heads/ready/new-feature", FIRST_SHA);
+
+    client.send_push_event(&payload).await;
+
+    assert_eq!(
+        client.token_scopes().first(),
+        Some(&Some(
+            TokenScope::repository("test-owner/test-repo")
+                .with_permission("contents", "read")
+                .with_permission("statuses", "write")
+                .with_permission("checks", "write")
+        ))
+    );
+}
+
+#[tokio::test]
+async fn ignores_pushes_to_other_branches() {
+    let mut client = TestClient::new();
+    let payload = given_push_event_payload("refs/heads/feature/new-feature", FIRST_SHA);
+
+    let response = client.send_push_event(&payload).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(client.api_calls().is_empty());
+    assert_eq!(
+        client.get("/status").await.body_as_json(),
+        json!({"ready_branches": [], "rate_limits": []})
+    );
+}
+
+#[tokio::test]
+async fn ignores_pushed_tags() {
+    let mut client = TestClient::new();
+    let payload = given_push_event_payload("refs/tags/ready/v1", FIRST_SHA);
+
+    client.send_push_event(&payload).await;
+
+    assert!(client.api_calls().is_empty());
+}
+
+#[tokio::test]
+async fn a_force_push_replaces_the_registered_commit() {
+    let mut client = TestClient::new();
+    let first_push = given_push_event_payload("refs/heads/ready/new-feature", FIRST_SHA);
+    let mut force_push = given_push_event_payload("refs/heads/ready/new-feature", SECOND_SHA);
+    force_push["forced"] = json!(true);
+
+    client.send_push_event(&first_push).await;
+    client.send_push_event(&force_push).await;
+
+    let status = client.get("/status").await.body_as_json();
+    assert_eq!(status["ready_branches"][0]["head_sha"], SECOND_SHA);
+    assert_eq!(status["ready_branches"][0]["status"], "waiting_for_ci");
+}
+
+#[tokio::test]
+async fn removes_deleted_ready_branches() {
+    let mut client = TestClient::new();
+    let push = given_push_event_payload("refs/heads/ready/new-feature", FIRST_SHA);
+    let mut deletion = given_push_event_payload(
+        "refs/heads/ready/new-feature",
+        "0000000000000000000000000000000000000000",
+    );
+    deletion["deleted"] = json!(true);
+
+    client.send_push_event(&push).await;
+    let response = client.send_push_event(&deletion).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        client.get("/status").await.body_as_json(),
+        json!({"ready_branches": [], "rate_limits": []})
+    );
+}
+
+#[tokio::test]
+async fn reads_the_policy_file_again_after_the_default_branch_changed() {
+    let mut client = TestClient::new();
+    let payload = given_push_event_payload("refs/heads/ready/new-feature", FIRST_SHA);
+
+    client.send_push_event(&payload).await;
+    client.send_push_event(&payload).await;
+    assert_eq!(client.repository().policy_file_reads, 1);
+
+    client.repository().default_branch_head = SECOND_SHA.to_owned();
+    client.send_push_event(&payload).await;
+    assert_eq!(client.repository().policy_file_reads, 2);
+}
+
+#[tokio::test]
+async fn ignores_other_branches_without_a_token_once_the_policy_is_known() {
+    let mut client = TestClient::new();
+    let ready = given_push_event_payload("refs/heads/ready/new-feature", FIRST_SHA);
+    let other = given_push_event_payload("refs/heads/feature/new-feature", SECOND_SHA);
+
+    client.send_push_event(&ready).await;
+    let token_requests = client.token_scopes().len();
+    let response = client.send_push_event(&other).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(client.token_scopes().len(), token_requests);
+}
+
+#[tokio::test]
+async fn applies_a_changed_branch_prefix_after_a_push_to_the_default_branch() {
+    let mut client = TestClient::new();
+    let ready = given_push_event_payload("refs/heads/ready/new-feature", FIRST_SHA);
+    let default_branch = given_push_event_payload("refs/heads/main", SECOND_SHA);
+    let shipped = given_push_event_payload("refs/heads/ship/new-feature", FIRST_SHA);
+
+    client.send_push_event(&ready).await;
+    client.repository().policy_file = Some(r#"branch_prefix = "ship/""#.to_owned());
+    client.repository().default_branch_head = SECOND_SHA.to_owned();
+    client.send_push_event(&default_branch).await;
+    client.send_push_event(&shipped).await;
+
+    let status = client.get("/status").await.body_as_json();
+    assert_eq!(status["ready_branches"][1]["branch"], "ship/new-feature");
+}
+
+fn given_push_event_payload(reference: &str, after: &str) -> Value {
+    json!({
+        "ref": reference,
+        "after": after,
+        "created": false,
+        "deleted": false,
+        "forced": false,
+        "repository": {
+          "full_name": "test-owner/test-repo",
+          "default_branch": "main",
+        },
+        "installation": {
+          "id": 1337,
+        },
+    })
+}
@@ -7,27 +7,18 @@
  * received a copy of the license along with this program.
  */
 
-use std::ops::Deref;
-
-use axum::{
-    body::{Body, Bytes},
-    extract::Request,
-    http::{HeaderValue, StatusCode},
-    response::Response,
-    routing::RouterIntoService,
-};
-use hmac::{Hmac, Mac};
-use http_body_util::BodyExt;
+use axum::http::{HeaderValue, StatusCode};
+use common::{ApiCall, ResponseExt, TestClient};
 use koritsu_app::{
-    ApplicationConfig, build_app_with_api,
+    MergePolicy,
     github_api::{
-        ApiError, AuthenticationMethod, BranchComparison, BranchComparisonRequest, GitHubApi,
-        GitHubApiProvider, UpdateReferenceRequest,
+        CheckConclusion, CheckRunRequest, CommitState, CommitStatusRequest, DeleteReferenceRequest,
+        MergeBranchRequest, TokenScope, WorkflowRunSummary,
     },
 };
 use serde_json::{Value, json};
-use sha2::Sha256;
-use tower::{Service, ServiceExt};
+
+mod common;
 
 #[tokio::test]
 async fn returns_ok_for_a_valid_workflow_run() {
@@ -118,13 +109,32 @@ async fn returns_an_error_if_the_repository_is_not_known() {
 
     let response = client.send_request(request).await;
 
-    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
     assert_eq!(
         response.body_as_json(),
         json!({
-            "status": 400,
+            "status": 404,
+            "title": "GitHub API request failed",
+            "detail": "Not found: Not Found, status 404, request CAFE:1337",
+        })
+    );
+}
+
+#[tokio::test]
+async fn reports_a_bad_gateway_if_github_is_unavailable() {
+    let mut client = TestClient::new();
+    let payload = given_workflow_run_event_payload("ready/unavailable");
+    let request = client.build_event_request("workflow_run", &payload);
+
+    let response = client.send_request(request).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert_eq!(
+        response.body_as_json(),
+        json!({
+            "status": 502,
             "title": "GitHub API request failed",
-            "detail": "Repository not found",
+            "detail": "GitHub is unavailable: no message, status 502",
         })
     );
 }
@@ -148,156 +158,472 @@ async fn returns_an_error_if_the_api_request_fails() {
     );
 }
 
-fn given_successful_workflow_run_event_payload() -> Value {
-    given_workflow_run_event_payload("read/new-feature")
+#[tokio::test]
+async fn fast_forwards_the_default_branch_for_a_single_commit() {
+    let mut client = TestClient::new();
+    let payload = given_workflow_run_event_payload("ready/one_ahead");
+
+    let response = client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        client.api_calls(),
+        vec![
+            ApiCall::UpdateReference {
+                reference: "heads/main".to_owned(),
+                sha1: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+            },
+            ApiCall::CreateCommitStatus(CommitStatusRequest {
+                repository_name: "test-owner/test-repo".to_owned(),
+                sha1: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+                state: CommitState::Success,
+                description: "Merged into main".to_owned(),
+            }),
+        ]
+    );
 }
 
-fn given_workflow_run_event_payload(head_branch: &str) -> Value {
-    json!({
-        "action": "completed",
-        "workflow_run": {
-            "conclusion": "success",
-            "head_branch": head_branch,
-        },
-        "repository": {
-          "full_name": "test-owner/test-repo",
-          "default_branch": "main",
-        },
-        "installation": {
-          "id": 1337,
-        },
-    })
+#[tokio::test]
+async fn reports_a_failure_status_if_the_branch_can_not_be_fast_forwarded() {
+    let mut client = TestClient::new();
+    let payload = given_workflow_run_event_payload("ready/behind");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(
+        client.api_calls(),
+        vec![ApiCall::CreateCommitStatus(CommitStatusRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            sha1: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+            state: CommitState::Failure,
+            description: "Can not fast forward main".to_owned(),
+        })]
+    );
 }
 
-struct TestClient {
-    config: ApplicationConfig,
-    service: RouterIntoService<Body>,
+#[tokio::test]
+async fn updates_the_ready_branch_status_after_a_failed_workflow_run() {
+    let mut client = TestClient::new();
+    let push = json!({
+        "ref": "refs/heads/ready/one_ahead",
+        "after": "6dcb09b5b57875f334f61aebed695e2e4193db5e",
+        "repository": {"full_name": "test-owner/test-repo", "default_branch": "main"},
+        "installation": {"id": 1337},
+    });
+    client.send_push_event(&push).await;
+    let payload = given_workflow_run_event_payload_with_conclusion("ready/one_ahead", "failure");
+
+    client.send_workflow_run_event(&payload).await;
+
+    let status = client.get("/status").await;
+    assert_eq!(
+        status.body_as_json()["ready_branches"][0]["status"],
+        "ci_failed"
+    );
 }
 
-impl TestClient {
-    fn new() -> Self {
-        let config = ApplicationConfig {
-            github_base_url: String::default(),
-            github_webhook_secret: "secret".to_owned(),
-            client_id: String::default(),
-            private_key_file: String::default(),
-        };
+#[tokio::test]
+async fn rejects_workflow_runs_from_forks() {
+    let mut client = TestClient::new();
+    let mut payload = given_workflow_run_event_payload("ready/one_ahead");
+    payload["workflow_run"]["head_repository"] = json!({"full_name": "attacker/test-repo"});
 
-        let api = TestGitHubApi {};
-        let service = build_app_with_api(config.clone(), api).into_service();
+    let response = client.send_workflow_run_event(&payload).await;
 
-        TestClient { config, service }
-    }
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.body_as_json(),
+        json!({
+            "status": 403,
+            "title": "Workflow run was rejected",
+            "detail": "Head repository attacker/test-repo differs from the base repository test-owner/test-repo",
+        })
+    );
+    assert!(client.api_calls().is_empty());
+}
 
-    async fn send_workflow_run_event(&mut self, payload: &Value) -> Response<Bytes> {
-        let request = self.build_event_request("workflow_run", payload);
-        self.send_request(request).await
-    }
+#[tokio::test]
+async fn rejects_workflow_runs_without_head_repository() {
+    let mut client = TestClient::new();
+    let mut payload = given_workflow_run_event_payload("ready/one_ahead");
+    payload["workflow_run"]["head_repository"] = Value::Null;
 
-    async fn send_request(&mut self, request: Request) -> Response<Bytes> {
-        let (parts, body) = self
-            .service
-            .ready()
-            .await
-            .unwrap()
-            .call(request)
-            .await
-            .unwrap()
-            .into_parts();
-
-        let body_bytes = body.collect().await.unwrap().to_bytes();
-        Response::from_parts(parts, body_bytes)
-    }
+    let response = client.send_workflow_run_event(&payload).await;
 
-    fn build_event_request(&self, event_type: &str, payload: &Value) -> Request {
-        let payload = serde_json::to_vec(payload).unwrap();
-        let signature = self.compute_signature(&payload);
-
-        Request::builder()
-            .method("POST")
-            .uri("/github/events")
-            .header("X-GitHub-Event", event_type)
-            .header("X-Hub-Signature-256", format!("sha256={}", signature))
-            .body(Body::from(payload))
-            .unwrap()
-    }
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.body_as_json()["detail"],
+        "Workflow run has no head repository"
+    );
+    assert!(client.api_calls().is_empty());
+}
 
-    fn compute_signature(&self, payload: &[u8]) -> String {
-        let secret = self.config.github_webhook_secret.as_bytes();
+#[tokio::test]
+async fn rejects_workflow_runs_triggered_by_events_that_are_not_allowed() {
+    let mut client = TestClient::new();
+    let mut payload = given_workflow_run_event_payload("ready/one_ahead");
+    payload["workflow_run"]["event"] = json!("pull_request_target");
 
-        let signature = Hmac::<Sha256>::new_from_slice(secret)
-            .unwrap()
-            .chain_update(payload)
-            .finalize()
-            .into_bytes();
+    let response = client.send_workflow_run_event(&payload).await;
 
-        signature
-            .into_iter()
-            .flat_map(|byte| [Self::byte_to_hex(byte >> 4), Self::byte_to_hex(byte)])
-            .collect()
-    }
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.body_as_json()["detail"],
+        "Workflow runs triggered by pull_request_target events are not allowed"
+    );
+    assert!(client.api_calls().is_empty());
+}
 
-    fn byte_to_hex(byte: u8) -> char {
-        let encoding = [
-            '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f',
-        ];
-        encoding[(byte & 15u8) as usize]
-    }
+#[tokio::test]
+async fn ignores_untrusted_workflow_runs_on_other_branches() {
+    let mut client = TestClient::new();
+    let mut payload = given_workflow_run_event_payload("feature/from-fork");
+    payload["workflow_run"]["event"] = json!("pull_request");
+    payload["workflow_run"]["head_repository"] = Value::Null;
+
+    let response = client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(client.api_calls().is_empty());
 }
 
-trait ResponseExt {
-    fn body_as_json(&self) -> Value;
+#[tokio::test]
+async fn ignores_workflow_runs_that_are_not_required() {
+    let mut client = given_client_requiring(&["CI", ".github/workflows/lint.yml"]);
+    let mut payload = given_workflow_run_event_payload("ready/one_ahead");
+    payload["workflow_run"]["name"] = json!("Label pull requests");
+    payload["workflow_run"]["path"] = json!(".github/workflows/label.yml");
+
+    let response = client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(client.api_calls().is_empty());
 }
 
-impl ResponseExt for Response<Bytes> {
-    fn body_as_json(&self) -> Value {
-        serde_json::from_slice(self.body()).unwrap()
-    }
+#[tokio::test]
+async fn merges_once_all_required_workflows_succeeded() {
+    let mut client = given_client_requiring(&["CI", ".github/workflows/lint.yml"]);
+    client.given_workflow_runs(vec![given_lint_run("completed", Some("success"))]);
+    let payload = given_workflow_run_event_payload("ready/one_ahead");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert!(client.api_calls().contains(&ApiCall::UpdateReference {
+        reference: "heads/main".to_owned(),
+        sha1: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+    }));
 }
 
-struct TestGitHubApi;
+#[tokio::test]
+async fn waits_for_required_workflows_that_did_not_complete() {
+    let mut client = given_client_requiring(&["CI", ".github/workflows/lint.yml"]);
+    client.given_workflow_runs(vec![given_lint_run("in_progress", None)]);
+    let payload = given_workflow_run_event_payload("ready/one_ahead");
 
-impl GitHubApiProvider for TestGitHubApi {
-    async fn get_api(&self, _: AuthenticationMethod) -> Result<impl GitHubApi, ApiError> {
-        Ok(self)
-    }
+    let response = client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(client.api_calls().is_empty());
 }
 
-impl GitHubApi for &TestGitHubApi {
-    async fn compare_commits(
-        &self,
-        request: BranchComparisonRequest,
-    ) -> Result<BranchComparison, ApiError> {
-        if request.head_branch.contains("unknown") {
-            return Err(ApiError::RepositoryNotFound(
-                "Repository not found".to_string(),
-            ));
-        }
-
-        if request.head_branch.contains("error") {
-            return Err(ApiError::Unspecific);
-        }
-
-        let (ahead_by, behind_by) = match request.head_branch.deref() {
-            "ready/two_ahead" => (2, 0),
-            "ready/one_ahead" => (1, 0),
-            "ready/behind" => (1, 1),
-            _ => (0, 0),
-        };
-
-        Ok(BranchComparison {
-            ahead_by,
-            behind_by,
-        })
-    }
+#[tokio::test]
+async fn reports_a_failure_if_a_required_workflow_failed() {
+    let mut client = given_client_requiring(&["CI", ".github/workflows/lint.yml"]);
+    client.given_workflow_runs(vec![given_lint_run("completed", Some("failure"))]);
+    let payload = given_workflow_run_event_payload("ready/one_ahead");
 
-    async fn update_reference(&self, _: UpdateReferenceRequest) -> Result<(), ApiError> {
-        Ok(())
-    }
+    client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(
+        client.api_calls(),
+        vec![ApiCall::CreateCommitStatus(CommitStatusRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            sha1: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+            state: CommitState::Failure,
+            description: "Workflow Lint did not succeed".to_owned(),
+        })]
+    );
+}
+
+#[tokio::test]
+async fn merges_for_conclusions_the_policy_accepts() {
+    let mut client = TestClient::with_config(|config| {
+        config.repository_policies.insert(
+            "test-owner/test-repo".to_owned(),
+            MergePolicy {
+                required_workflows: Vec::new(),
+                accepted_conclusions: vec!["success".to_owned(), "skipped".to_owned()],
+                ..MergePolicy::default()
+            },
+        );
+    });
+    let payload = given_workflow_run_event_payload_with_conclusion("ready/one_ahead", "skipped");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert!(matches!(
+        client.api_calls().first(),
+        Some(ApiCall::UpdateReference { .. })
+    ));
+}
+
+#[tokio::test]
+async fn applies_the_policy_file_of_the_repository() {
+    let mut client = TestClient::new();
+    client.repository().policy_file = Some(
+        r#"
+branch_prefix = "ship/"
+merge_strategy = "merge"
+delete_branch = true
+"#
+        .to_owned(),
+    );
+    let payload = given_workflow_run_event_payload("ship/new-feature");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(
+        client.api_calls(),
+        vec![
+            ApiCall::MergeBranch(MergeBranchRequest {
+                repository_name: "test-owner/test-repo".to_owned(),
+                base_branch: "main".to_owned(),
+                head_branch: "ship/new-feature".to_owned(),
+            }),
+            ApiCall::CreateCommitStatus(CommitStatusRequest {
+                repository_name: "test-owner/test-repo".to_owned(),
+                sha1: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+                state: CommitState::Success,
+                description: "Merged into main".to_owned(),
+            }),
+            ApiCall::DeleteReference(DeleteReferenceRequest {
+                repository_name: "test-owner/test-repo".to_owned(),
+                reference: "heads/ship/new-feature".to_owned(),
+            }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn ignores_branches_without_the_prefix_of_the_policy_file() {
+    let mut client = TestClient::new();
+    client.repository().policy_file = Some(r#"branch_prefix = "ship/""#.to_owned());
+    let payload = given_workflow_run_event_payload("ready/one_ahead");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert!(client.api_calls().is_empty());
+}
+
+#[tokio::test]
+async fn reports_a_merge_conflict() {
+    let mut client = TestClient::new();
+    client.repository().policy_file = Some(r#"merge_strategy = "merge""#.to_owned());
+    let payload = given_workflow_run_event_payload("ready/conflict");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(
+        client.api_calls().last(),
+        Some(&ApiCall::CreateCommitStatus(CommitStatusRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            sha1: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+            state: CommitState::Failure,
+            description: "Merge conflict with main".to_owned(),
+        }))
+    );
+}
+
+#[tokio::test]
+async fn blocks_the_merge_if_the_policy_file_is_invalid() {
+    let mut client = TestClient::new();
+    client.repository().policy_file = Some(r#"merge_strategy = "rebase""#.to_owned());
+    let payload = given_workflow_run_event_payload("ready/one_ahead");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(
+        client.api_calls(),
+        vec![ApiCall::CreateCheckRun(CheckRunRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            head_sha: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+            conclusion: CheckConclusion::Failure,
+            title: "Invalid .github/koritsu.toml".to_owned(),
+            summary:
+                "Invalid configuration\n  - merge_strategy: \"rebase\" must be one of \"fast_forward\" or \"merge\""
+                    .to_owned(),
+        })]
+    );
+}
+
+#[tokio::test]
+async fn applies_the_policy_file_of_the_organisation() {
+    let mut client = TestClient::new();
+    client.repository().organisation_policy_file = Some(r#"merge_strategy = "merge""#.to_owned());
+    let payload = given_workflow_run_event_payload("ready/new-feature");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert!(matches!(
+        client.api_calls().first(),
+        Some(ApiCall::MergeBranch(_))
+    ));
+}
+
+#[tokio::test]
+async fn ignores_the_organisation_policy_if_the_installation_can_not_access_it() {
+    let mut client = TestClient::new();
+    client.repository().organisation_policy_file = Some(r#"merge_strategy = "merge""#.to_owned());
+    client.repository().organisation_repository_accessible = false;
+    let payload = given_workflow_run_event_payload("ready/one_ahead");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert!(matches!(
+        client.api_calls().first(),
+        Some(ApiCall::UpdateReference { .. })
+    ));
+}
+
+#[tokio::test]
+async fn limits_the_access_tokens_to_the_repository_and_the_needed_permissions() {
+    let mut client = TestClient::new();
+    let payload = given_workflow_run_event_payload("ready/new-feature");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(
+        client.token_scopes(),
+        vec![
+            Some(
+                TokenScope::repository("test-owner/test-repo")
+                    .with_permission("contents", "write")
+                    .with_permission("statuses", "write")
+                    .with_permission("checks", "write")
+                    .with_permission("actions", "read")
+            ),
+            Some(TokenScope::repository("test-owner/.github").with_permission("contents", "read")),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn blocks_the_merge_if_the_repository_overrides_a_locked_key() {
+    let mut client = TestClient::new();
+    client.repository().organisation_policy_file = Some(
+        r#"
+merge_strategy = "fast_forward"
+locked = ["merge_strategy"]
+"#
+        .to_owned(),
+    );
+    client.repository().policy_file = Some(r#"merge_strategy = "merge""#.to_owned());
+    let payload = given_workflow_run_event_payload("ready/one_ahead");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(
+        client.api_calls(),
+        vec![ApiCall::CreateCheckRun(CheckRunRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            head_sha: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+            conclusion: CheckConclusion::Failure,
+            title: "Invalid .github/koritsu.toml".to_owned(),
+            summary:
+                "Invalid configuration\n  - merge_strategy: is locked by the organisation policy"
+                    .to_owned(),
+        })]
+    );
+}
+
+#[tokio::test]
+async fn refuses_commits_without_verified_signature_if_required() {
+    let mut client = TestClient::new();
+    client.repository().organisation_policy_file = Some("require_signed_commits = true".to_owned());
+    let payload = given_workflow_run_event_payload("ready/unsigned");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert_eq!(
+        client.api_calls(),
+        vec![ApiCall::CreateCommitStatus(CommitStatusRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            sha1: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+            state: CommitState::Failure,
+            description: "Commit 6dcb09b has no verified signature".to_owned(),
+        })]
+    );
+}
+
+#[tokio::test]
+async fn compares_the_branches_once_to_verify_signatures_and_fast_forward() {
+    let mut client = TestClient::new();
+    client.repository().organisation_policy_file = Some("require_signed_commits = true".to_owned());
+    let payload = given_workflow_run_event_payload("ready/one_ahead");
+
+    client.send_workflow_run_event(&payload).await;
+
+    assert!(client.api_calls().contains(&ApiCall::UpdateReference {
+        reference: "heads/main".to_owned(),
+        sha1: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+    }));
+    assert_eq!(client.repository().comparisons, 1);
+}
+
+fn given_client_requiring(workflows: &[&str]) -> TestClient {
+    TestClient::with_config(|config| {
+        config.default_policy.required_workflows = workflows
+            .iter()
+            .map(|workflow| workflow.to_string())
+            .collect();
+    })
 }
 
-impl From<&ApplicationConfig> for TestGitHubApi {
-    fn from(_: &ApplicationConfig) -> Self {
-        Self {}
+fn given_lint_run(status: &str, conclusion: Option<&str>) -> WorkflowRunSummary {
+    WorkflowRunSummary {
+        id: 30433643,
+        workflow_id: 159039,
+        name: "Lint".to_owned(),
+        path: ".github/workflows/lint.yml".to_owned(),
+        run_attempt: 1,
+        status: status.to_owned(),
+        conclusion: conclusion.map(str::to_owned),
     }
 }
+
+fn given_successful_workflow_run_event_payload() -> Value {
+    given_workflow_run_event_payload("read/new-feature")
+}
+
+fn given_workflow_run_event_payload(head_branch: &str) -> Value {
+    given_workflow_run_event_payload_with_conclusion(head_branch, "success")
+}
+
+fn given_workflow_run_event_payload_with_conclusion(head_branch: &str, conclusion: &str) -> Value {
+    json!({
+        "action": "completed",
+        "workflow_run": {
+            "id": 30433642,
+            "name": "CI",
+            "path": ".github/workflows/ci.yml",
+            "workflow_id": 159038,
+            "run_attempt": 1,
+            "status": "completed",
+            "conclusion": conclusion,
+            "head_branch": head_branch,
+            "head_sha": "6dcb09b5b57875f334f61aebed695e2e4193db5e",
+            "head_repository": {"full_name": "test-owner/test-repo"},
+            "event": "push",
+            "actor": {"login": "octocat"},
+            "triggering_actor": {"login": "octocat"},
+            "pull_requests": [],
+        },
+        "repository": {
+          "full_name": "test-owner/test-repo",
+          "default_branch": "main",
+        },
+        "installation": {
+          "id": 1337,
+        },
+    })
+}
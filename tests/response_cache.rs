@@ -0,0 +1,180 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use common::{BRANCH_HEAD_PATH, FakeGitHub, GivenProvider, TestClient, given_provider};
+use koritsu_app::github_api::{
+    AuthenticationMethod, BranchHeadRequest, GitHubApi, GitHubApiProvider, ResponseCacheStats,
+    TokenScope,
+};
+use serde_json::json;
+
+mod common;
+
+const ETAG: &str = "\"644b5b0155e6404a9cc4bd9d8b1ae730\"";
+const HEAD_SHA: &str = "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15";
+
+#[tokio::test]
+async fn answers_unchanged_resources_from_the_cache() {
+    let github = given_github().await;
+    let provider = github.provider();
+
+    assert_eq!(get_branch_head(&provider, 1337).await, HEAD_SHA);
+    assert_eq!(get_branch_head(&provider, 1337).await, HEAD_SHA);
+
+    assert_eq!(github.state.full_responses.load(Ordering::SeqCst), 1);
+    assert_eq!(github.state.not_modified.load(Ordering::SeqCst), 1);
+    let stats = provider.response_cache_stats();
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.hits, 1);
+}
+
+#[tokio::test]
+async fn keeps_the_responses_of_installations_apart() {
+    let github = given_github().await;
+    let provider = github.provider();
+
+    get_branch_head(&provider, 1337).await;
+    get_branch_head(&provider, 1338).await;
+
+    assert_eq!(github.state.full_responses.load(Ordering::SeqCst), 2);
+    assert_eq!(provider.response_cache_stats().entries, 2);
+}
+
+#[tokio::test]
+async fn keeps_the_responses_of_token_scopes_apart() {
+    let github = given_github().await;
+    let provider = github.provider();
+
+    get_branch_head(&provider, 1337).await;
+    get_scoped_branch_head(&provider, 1337).await;
+    get_scoped_branch_head(&provider, 1337).await;
+
+    assert_eq!(github.state.full_responses.load(Ordering::SeqCst), 2);
+    assert_eq!(github.state.not_modified.load(Ordering::SeqCst), 1);
+    assert_eq!(provider.response_cache_stats().entries, 2);
+}
+
+#[tokio::test]
+async fn drops_the_responses_of_removed_installations() {
+    let github = given_github().await;
+    let provider = github.provider();
+
+    get_branch_head(&provider, 1337).await;
+    provider.forget_installation(1337);
+
+    assert_eq!(
+        provider.response_cache_stats(),
+        ResponseCacheStats::default()
+    );
+}
+
+#[tokio::test]
+async fn exposes_the_cache_size_as_metrics() {
+    let mut client = TestClient::new();
+
+    let metrics = client.get("/metrics").await;
+    let metrics = String::from_utf8(metrics.body().to_vec()).unwrap();
+
+    assert!(metrics.contains("koritsu_github_response_cache_entries 0\n"));
+    assert!(metrics.contains("koritsu_github_response_cache_bytes 0\n"));
+    assert!(metrics.contains("# TYPE koritsu_github_response_cache_hits_total counter\n"));
+}
+
+async fn get_branch_head(provider: &GivenProvider, installation_id: usize) -> String {
+    read_branch_head(provider, installation_id, None).await
+}
+
+/// Reads with a token that is limited to the repository
+async fn get_scoped_branch_head(provider: &GivenProvider, installation_id: usize) -> String {
+    let scope = TokenScope::repository("test-owner/test-repo").with_permission("contents", "read");
+    read_branch_head(provider, installation_id, Some(scope)).await
+}
+
+async fn read_branch_head(
+    provider: &GivenProvider,
+    installation_id: usize,
+    scope: Option<TokenScope>,
+) -> String {
+    let api = provider
+        .get_api(AuthenticationMethod::AppInstallation {
+            installation_id,
+            scope,
+        })
+        .await
+        .unwrap();
+
+    api.get_branch_head(BranchHeadRequest {
+        repository_name: "test-owner/test-repo".to_owned(),
+        branch: "main".to_owned(),
+    })
+    .await
+    .unwrap()
+}
+
+struct TestGitHub {
+    base_url: String,
+    state: Arc<GitHubState>,
+}
+
+impl TestGitHub {
+    fn provider(&self) -> GivenProvider {
+        given_provider(&self.base_url)
+    }
+}
+
+#[derive(Default)]
+struct GitHubState {
+    full_responses: AtomicUsize,
+    not_modified: AtomicUsize,
+}
+
+/// Starts a server that answers reference requests with an `ETag` and
+/// conditional requests with `304 Not Modified` like GitHub.
+async fn given_github() -> TestGitHub {
+    let state = Arc::new(GitHubState::default());
+
+    let base_url = FakeGitHub::new()
+        .route(
+            BRANCH_HEAD_PATH,
+            get(reference_handler).with_state(state.clone()),
+        )
+        .start()
+        .await;
+
+    TestGitHub { base_url, state }
+}
+
+async fn reference_handler(State(state): State<Arc<GitHubState>>, headers: HeaderMap) -> Response {
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|etag| etag == ETAG)
+    {
+        state.not_modified.fetch_add(1, Ordering::SeqCst);
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, ETAG)]).into_response();
+    }
+
+    state.full_responses.fetch_add(1, Ordering::SeqCst);
+    (
+        [(header::ETAG, ETAG)],
+        Json(json!({"object": {"sha": HEAD_SHA}})),
+    )
+        .into_response()
+}
@@ -0,0 +1,221 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    routing::{get, patch, post},
+};
+use common::{BRANCH_HEAD_PATH, FakeGitHub, GivenProvider, given_provider};
+use koritsu_app::github_api::{
+    ApiError, AuthenticationMethod, BranchHeadRequest, CheckConclusion, CheckRunRequest,
+    DeleteReferenceRequest, GitHubApi, UpdateReferenceRequest,
+};
+use serde_json::{Value, json};
+
+mod common;
+
+#[tokio::test]
+async fn retries_reading_requests_after_temporary_server_errors() {
+    let github = given_github(2).await;
+    let provider = github.provider();
+    let api = provider.get_api(auth_method()).await.unwrap();
+
+    let head = api
+        .get_branch_head(BranchHeadRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            branch: "main".to_owned(),
+        })
+        .await;
+
+    assert_eq!(head.unwrap(), "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15");
+    assert_eq!(github.requests(), 3);
+}
+
+#[tokio::test]
+async fn gives_up_after_a_bounded_number_of_retries() {
+    let github = given_github(usize::MAX).await;
+    let provider = github.provider();
+    let api = provider.get_api(auth_method()).await.unwrap();
+
+    let head = api
+        .get_branch_head(BranchHeadRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            branch: "main".to_owned(),
+        })
+        .await;
+
+    let error = head.unwrap_err();
+    assert!(matches!(error, ApiError::Server(_)));
+    assert!(error.is_retryable());
+    assert_eq!(github.requests(), 4);
+}
+
+#[tokio::test]
+async fn retries_reference_updates_without_force() {
+    let github = given_github(1).await;
+    let provider = github.provider();
+    let api = provider.get_api(auth_method()).await.unwrap();
+
+    let update = api.update_reference(update_reference_request(false)).await;
+
+    assert!(update.is_ok());
+    assert_eq!(github.requests(), 2);
+}
+
+#[tokio::test]
+async fn does_not_retry_forced_reference_updates() {
+    let github = given_github(1).await;
+    let provider = github.provider();
+    let api = provider.get_api(auth_method()).await.unwrap();
+
+    let update = api.update_reference(update_reference_request(true)).await;
+
+    assert!(update.is_err());
+    assert_eq!(github.requests(), 1);
+}
+
+#[tokio::test]
+async fn does_not_retry_reference_deletions() {
+    let github = given_github(1).await;
+    let provider = github.provider();
+    let api = provider.get_api(auth_method()).await.unwrap();
+
+    let deletion = api
+        .delete_reference(DeleteReferenceRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            reference: "heads/ready/feature".to_owned(),
+        })
+        .await;
+
+    assert!(deletion.is_err());
+    assert_eq!(github.requests(), 1);
+}
+
+#[tokio::test]
+async fn does_not_retry_requests_that_create_something() {
+    let github = given_github(1).await;
+    let provider = github.provider();
+    let api = provider.get_api(auth_method()).await.unwrap();
+
+    let check_run = api
+        .create_check_run(CheckRunRequest {
+            repository_name: "test-owner/test-repo".to_owned(),
+            head_sha: "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15".to_owned(),
+            conclusion: CheckConclusion::Failure,
+            title: "Invalid .github/koritsu.toml".to_owned(),
+            summary: "Invalid configuration".to_owned(),
+        })
+        .await;
+
+    assert!(check_run.is_err());
+    assert_eq!(github.requests(), 1);
+}
+
+fn auth_method() -> AuthenticationMethod {
+    AuthenticationMethod::AppInstallation {
+        installation_id: 1337,
+        scope: None,
+    }
+}
+
+fn update_reference_request(force: bool) -> UpdateReferenceRequest {
+    UpdateReferenceRequest {
+        repository_name: "test-owner/test-repo".to_owned(),
+        reference: "heads/main".to_owned(),
+        sha1: "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15".to_owned(),
+        force,
+    }
+}
+
+struct TestGitHub {
+    base_url: String,
+    state: Arc<GitHubState>,
+}
+
+impl TestGitHub {
+    fn provider(&self) -> GivenProvider {
+        given_provider(&self.base_url)
+    }
+
+    /// Repository requests GitHub received, access token requests excluded
+    fn requests(&self) -> usize {
+        self.state.requests.load(Ordering::SeqCst)
+    }
+}
+
+struct GitHubState {
+    requests: AtomicUsize,
+    /// Repository requests answered with `502 Bad Gateway` before GitHub
+    /// recovers
+    failures: usize,
+}
+
+impl GitHubState {
+    fn answer(&self, success: (StatusCode, Value)) -> (StatusCode, Json<Value>) {
+        let request = self.requests.fetch_add(1, Ordering::SeqCst);
+
+        if request < self.failures {
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(json!({"message": "Bad Gateway"})),
+            )
+        } else {
+            (success.0, Json(success.1))
+        }
+    }
+}
+
+/// Starts a server that answers like GitHub after failing the given number of
+/// repository requests.
+async fn given_github(failures: usize) -> TestGitHub {
+    let state = Arc::new(GitHubState {
+        requests: AtomicUsize::new(0),
+        failures,
+    });
+
+    let base_url = FakeGitHub::new()
+        .route(
+            BRANCH_HEAD_PATH,
+            get(|State(state): State<Arc<GitHubState>>| async move {
+                state.answer((
+                    StatusCode::OK,
+                    json!({"object": {"sha": "f1d2d2f924e986ac86fdf7b36c94bcdf32beec15"}}),
+                ))
+            })
+            .with_state(state.clone()),
+        )
+        .route(
+            "/repos/{owner}/{repository}/git/refs/{*reference}",
+            patch(|State(state): State<Arc<GitHubState>>| async move {
+                state.answer((StatusCode::OK, json!({})))
+            })
+            .delete(|State(state): State<Arc<GitHubState>>| async move {
+                state.answer((StatusCode::NO_CONTENT, json!({})))
+            })
+            .with_state(state.clone()),
+        )
+        .route(
+            "/repos/{owner}/{repository}/check-runs",
+            post(|State(state): State<Arc<GitHubState>>| async move {
+                state.answer((StatusCode::CREATED, json!({})))
+            })
+            .with_state(state.clone()),
+        )
+        .start()
+        .await;
+
+    TestGitHub { base_url, state }
+}
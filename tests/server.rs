@@ -0,0 +1,247 @@
+/*
+ * This file is part of koritsu
+ *
+ * Copyright (c) 2025 Thomas Himmelstoss
+ *
+ * This software is subject to the MIT license. You should have
+ * received a copy of the license along with this program.
+ */
+
+use std::{
+    env, fs,
+    net::{Ipv4Addr, TcpListener},
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use axum::{Router, routing::get};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use koritsu_app::{ServerConfig, ServerError, TlsConfig, serve, serve_checking_tls_files};
+use reqwest::tls::TlsInfo;
+use rsa::{
+    RsaPrivateKey,
+    pkcs1v15::SigningKey,
+    pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding},
+    sha2::Sha256,
+    signature::{SignatureEncoding, Signer},
+};
+
+#[tokio::test]
+async fn serves_the_application_over_tls() {
+    let key = given_key();
+    let certificate = self_signed_certificate(&key, 1);
+    let config = given_tls_config("serves", &key, &certificate);
+    let url = config_address(&config);
+
+    tokio::spawn(async move { serve(given_app(), &config).await });
+    let served = wait_for_certificate(&url, &certificate).await;
+
+    assert_eq!(served, certificate);
+}
+
+#[tokio::test]
+async fn picks_up_a_replaced_certificate() {
+    let key = given_key();
+    let first = self_signed_certificate(&key, 1);
+    let second = self_signed_certificate(&key, 2);
+    let config = given_tls_config("replaced", &key, &first);
+    let url = config_address(&config);
+    let certificate_file = config.tls.as_ref().unwrap().certificate_file.clone();
+
+    tokio::spawn(async move {
+        serve_checking_tls_files(given_app(), &config, Duration::from_millis(50)).await
+    });
+    wait_for_certificate(&url, &first).await;
+    fs::write(&certificate_file, pem("CERTIFICATE", &second)).unwrap();
+
+    let served = wait_for_certificate(&url, &second).await;
+
+    assert_eq!(served, second);
+}
+
+#[tokio::test]
+async fn refuses_to_start_with_an_invalid_certificate() {
+    let key = given_key();
+    let config = given_tls_config("invalid", &key, &self_signed_certificate(&key, 1));
+    let certificate_file = &config.tls.as_ref().unwrap().certificate_file;
+    fs::write(certificate_file, "not a certificate").unwrap();
+
+    let result = serve(given_app(), &config).await;
+
+    assert!(matches!(result, Err(ServerError::InvalidTlsFiles(_))));
+}
+
+fn given_app() -> Router {
+    Router::new().route("/", get(|| async { "koritsu" }))
+}
+
+/// ring only signs with RSA keys of at least 2048 bits, which take a while to
+/// generate, so all tests share one
+fn given_key() -> RsaPrivateKey {
+    static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+    KEY.get_or_init(|| RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap())
+        .clone()
+}
+
+/// Writes the key and the certificate to files and configures a free port
+fn given_tls_config(name: &str, key: &RsaPrivateKey, certificate: &[u8]) -> ServerConfig {
+    let directory = env::temp_dir().join(format!("koritsu-tls-{name}-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let certificate_file = directory.join("certificate.pem");
+    let key_file = directory.join("key.pem");
+    fs::write(&certificate_file, pem("CERTIFICATE", certificate)).unwrap();
+    fs::write(
+        &key_file,
+        key.to_pkcs8_pem(LineEnding::LF).unwrap().as_bytes(),
+    )
+    .unwrap();
+
+    ServerConfig {
+        listen_address: Ipv4Addr::LOCALHOST.into(),
+        port: free_port(),
+        tls: Some(TlsConfig {
+            certificate_file,
+            key_file,
+        }),
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn config_address(config: &ServerConfig) -> String {
+    format!("https://{}/", config.socket_address())
+}
+
+/// Connects until the server answers with the certificate and returns the
+/// certificate of the last answer
+async fn wait_for_certificate(url: &str, expected: &[u8]) -> Vec<u8> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    loop {
+        let served = served_certificate(url).await;
+        if served.as_deref() == Some(expected) || Instant::now() > deadline {
+            return served.expect("server did not answer");
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// The certificate of a new connection to the server, which is self-signed
+/// and therefore accepted without verification
+async fn served_certificate(url: &str) -> Option<Vec<u8>> {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .tls_info(true)
+        .build()
+        .unwrap();
+
+    let response = client.get(url).send().await.ok()?;
+    let certificate = response
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(TlsInfo::peer_certificate)
+        .map(<[u8]>::to_vec);
+    assert_eq!(response.text().await.unwrap(), "koritsu");
+
+    certificate
+}
+
+/// A minimal X.509 v3 certificate for `localhost` that the key signed itself
+fn self_signed_certificate(key: &RsaPrivateKey, serial: u8) -> Vec<u8> {
+    let sha256_with_rsa = der(
+        0x30,
+        &[
+            der(
+                0x06,
+                &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b],
+            ),
+            der(0x05, &[]),
+        ]
+        .concat(),
+    );
+    let name = der(
+        0x30,
+        &der(
+            0x31,
+            &der(
+                0x30,
+                &[der(0x06, &[0x55, 0x04, 0x03]), der(0x0c, b"localhost")].concat(),
+            ),
+        ),
+    );
+    let validity = der(
+        0x30,
+        &[der(0x17, b"200101000000Z"), der(0x17, b"491231235959Z")].concat(),
+    );
+    let public_key = key.to_public_key().to_public_key_der().unwrap();
+
+    let tbs_certificate = der(
+        0x30,
+        &[
+            der(0xa0, &der(0x02, &[2])),
+            der(0x02, &[serial]),
+            sha256_with_rsa.clone(),
+            name.clone(),
+            validity,
+            name,
+            public_key.as_bytes().to_vec(),
+        ]
+        .concat(),
+    );
+
+    let signature = SigningKey::<Sha256>::new(key.clone())
+        .sign(&tbs_certificate)
+        .to_vec();
+
+    der(
+        0x30,
+        &[
+            tbs_certificate,
+            sha256_with_rsa,
+            der(0x03, &[&[0][..], &signature].concat()),
+        ]
+        .concat(),
+    )
+}
+
+/// Encodes a DER value with its tag and length
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let length = content.len();
+    let mut encoded = vec![tag];
+
+    if length < 0x80 {
+        encoded.push(length as u8);
+    } else {
+        let bytes: Vec<u8> = length
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        encoded.push(0x80 | bytes.len() as u8);
+        encoded.extend(bytes);
+    }
+
+    encoded.extend_from_slice(content);
+    encoded
+}
+
+fn pem(label: &str, content: &[u8]) -> String {
+    let encoded = STANDARD.encode(content);
+    let lines: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(64)
+        .map(|line| std::str::from_utf8(line).unwrap())
+        .collect();
+
+    format!(
+        "-----BEGIN {label}-----\n{}\n-----END {label}-----\n",
+        lines.join("\n")
+    )
+}